@@ -0,0 +1,10 @@
+#![no_main]
+
+mod roundtrip;
+
+use libfuzzer_sys::fuzz_target;
+use zigbee_rs::nwk::frame::FrameControl;
+
+use crate::roundtrip::roundtrip;
+
+fuzz_target!(|data: &[u8]| roundtrip::<FrameControl>(data));
@@ -0,0 +1,39 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zigbee_rs::nwk::frame_ref::{
+    NpduMut,
+    NpduRef,
+};
+
+// The borrowed views must agree with the owned decoder and never index out of bounds.
+fuzz_target!(|data: &[u8]| {
+    let view = match NpduRef::new(data) {
+        Ok(view) => view,
+        Err(_) => return,
+    };
+    view.control();
+    view.destination_address();
+    view.source_address();
+    view.radius();
+    view.sequence_number();
+    view.destination_ieee_address();
+    view.source_ieee_address();
+    view.multicast_control();
+    view.relay_count();
+    view.relay_index();
+    view.relays().for_each(drop);
+    for index in 0..=u8::MAX {
+        view.relay(index);
+    }
+    assert_eq!(view.header_length() + view.payload().len(), data.len());
+    view.to_frame().ok();
+
+    let mut buffer = data.to_vec();
+    let mut frame = NpduMut::new(&mut buffer).expect("parsed by NpduRef");
+    frame.decrement_radius();
+    if let Some(relay_index) = frame.view().relay_index() {
+        frame.set_relay_index(relay_index.saturating_sub(1)).expect("index within the relay list");
+    }
+    frame.payload_mut();
+});
@@ -0,0 +1,13 @@
+use zigbee_rs::serde::Serde;
+
+/// Whatever decodes has to encode again, and the encoding has to be stable.
+pub fn roundtrip<T: Serde>(data: &[u8]) {
+    if let Ok(value) = T::deserialize(data) {
+        let mut buffer = vec![0; value.encoded_len()];
+        let length = value.serialize(&mut buffer).expect("decoded value must encode");
+        let decoded = T::deserialize(&buffer[..length]).expect("encoded value must decode");
+        let mut again = vec![0; decoded.encoded_len()];
+        let again_length = decoded.serialize(&mut again).expect("decoded value must encode");
+        assert_eq!(buffer[..length], again[..again_length]);
+    }
+}
@@ -5,12 +5,20 @@ use crate::state::{
     CommissioningStatus,
     CommissioningModeFlag,
 };
-use crate::machine_state::MachineState;
+use crate::machine_state::{
+    MachineState,
+    RejoinAttempt,
+};
+use crate::nwk::commands::rejoin_response::RejoinStatus;
+
+/// bdbcRecSameNetworkRetryAttempts, rejoin requests sent before falling back to
+/// commissioning.
+pub const REC_SAME_NETWORK_RETRY_ATTEMPTS: u8 = 3;
 
 pub fn load_data() -> MachineState {
     let state = State::new();
     match state.device_type {
-        DeviceType::EndDevice => MachineState::AttemptRejoin(state),
+        DeviceType::EndDevice => MachineState::AttemptRejoin(state, RejoinAttempt::default()),
         DeviceType::Router => if state.bdbCommissioningCapability.contains(CommissioningModeFlag::Touchlink) {
             // TODO:
             // select_channel(state);
@@ -23,16 +31,29 @@ pub fn load_data() -> MachineState {
     }
 }
 
-fn try_rejoin() -> bool {
-    true
+/// Announces the device once the parent accepted the rejoin request.
+///
+/// A refused rejoin means the network no longer takes the device, it falls back to
+/// commissioning as a node which is not on a network. Without any response another request
+/// is sent, up to `REC_SAME_NETWORK_RETRY_ATTEMPTS` of them before commissioning begins.
+pub fn attempt_rejoin(mut state: State, attempt: RejoinAttempt) -> MachineState {
+    match attempt.status {
+        Some(RejoinStatus::Success) => MachineState::BroadcastDeviceAnnounce(state),
+        Some(_) => {
+            state.bdbNodeIsOnANetwork = false;
+            MachineState::CommissioningBegin(state)
+        },
+        None if attempt.requests >= REC_SAME_NETWORK_RETRY_ATTEMPTS => MachineState::CommissioningBegin(state),
+        None => MachineState::SendRejoinRequest(state, RejoinAttempt {
+            requests: attempt.requests + 1,
+            status: None,
+        }),
+    }
 }
 
-pub fn attempt_rejoin(state: State) -> MachineState {
-    if try_rejoin() {
-        MachineState::BroadcastDeviceAnnounce(state)
-    } else {
-        MachineState::AttemptRejoin(state)
-    }
+pub fn send_rejoin_request(state: State, attempt: RejoinAttempt) -> MachineState {
+    // TODO: Send the rejoin request and record the response of the parent in the attempt
+    MachineState::AttemptRejoin(state, attempt)
 }
 
 pub fn broadcast_device_announce(state: State) -> MachineState {
@@ -66,7 +87,7 @@ pub fn try_touchlink(mut state: State) -> MachineState {
 pub fn try_network_steering(state: State) -> MachineState {
     // TODO: - NetworkSteering init
     //       - Add actual response to the state struct (bdbCommissioningStatus)
-    
+    network_steering(&state);
     if state.bdbCommissioningMode.contains(CommissioningModeFlag::NetworkFormation) {
         MachineState::Commissioning(state, CommissioningMode::NetworkFormation)
     } else {
@@ -74,7 +95,7 @@ pub fn try_network_steering(state: State) -> MachineState {
     }
 }
 
-fn network_steering(state: State) {
+fn network_steering(state: &State) {
     if state.bdbNodeIsOnANetwork {
         // TODO: Perform network steering for a node on a network
     } else {
@@ -86,7 +107,6 @@ pub fn try_network_formation(mut state: State) -> MachineState {
     // TODO: - NetworkFormation init
     //       - Add actual response to the state struct (bdbCommissioningStatus)
 
-    let mut c = false;
     if !state.bdbNodeIsOnANetwork {
         match state.device_type {
             DeviceType::EndDevice => (),
@@ -109,4 +129,59 @@ pub fn try_finding_and_binding(state: State) -> MachineState {
     // TODO: - FindingAndBinding init
     //       - Add actual response to the state struct (bdbCommissioningStatus)
     MachineState::CommissioningDone(state)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on_network() -> State {
+        let mut state = State::new();
+        state.bdbNodeIsOnANetwork = true;
+        state
+    }
+
+    fn attempt(requests: u8, status: Option<RejoinStatus>) -> RejoinAttempt {
+        RejoinAttempt { requests, status }
+    }
+
+    #[test]
+    fn end_devices_start_by_rejoining() {
+        let state = load_data();
+        assert!(matches!(state, MachineState::AttemptRejoin(_, attempt) if attempt.requests == 0 && attempt.status.is_none()));
+    }
+
+    #[test]
+    fn accepted_rejoin_announces_the_device() {
+        let state = attempt_rejoin(on_network(), attempt(1, Some(RejoinStatus::Success)));
+        assert!(matches!(state, MachineState::BroadcastDeviceAnnounce(state) if state.bdbNodeIsOnANetwork));
+    }
+
+    #[test]
+    fn refused_rejoin_falls_back_to_commissioning() {
+        let state = attempt_rejoin(on_network(), attempt(1, Some(RejoinStatus::PanAtCapacity)));
+        assert!(matches!(state, MachineState::CommissioningBegin(state) if !state.bdbNodeIsOnANetwork));
+    }
+
+    #[test]
+    fn unanswered_rejoin_is_retried_until_the_limit() {
+        for requests in 0..REC_SAME_NETWORK_RETRY_ATTEMPTS {
+            let state = attempt_rejoin(on_network(), attempt(requests, None));
+            assert!(matches!(state, MachineState::SendRejoinRequest(_, attempt) if attempt.requests == requests + 1));
+        }
+        let state = attempt_rejoin(on_network(), attempt(REC_SAME_NETWORK_RETRY_ATTEMPTS, None));
+        assert!(matches!(state, MachineState::CommissioningBegin(state) if state.bdbNodeIsOnANetwork));
+    }
+
+    #[test]
+    fn unanswered_rejoin_ends_in_commissioning() {
+        let mut state = attempt_rejoin(on_network(), RejoinAttempt::default());
+        let mut requests = 0;
+        while let MachineState::SendRejoinRequest(node, attempt) = state {
+            requests += 1;
+            state = attempt_rejoin(node, attempt);
+        }
+        assert_eq!(requests, REC_SAME_NETWORK_RETRY_ATTEMPTS);
+        assert!(matches!(state, MachineState::CommissioningBegin(_)));
+    }
+}
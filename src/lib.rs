@@ -1,3 +1,13 @@
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+// Lets the derives of zigbee-rs-derive refer to `::zigbee_rs` from within this crate.
+extern crate self as zigbee_rs;
+
 pub mod state;
 pub mod machine_state;
 pub mod machine;
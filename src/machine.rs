@@ -3,6 +3,7 @@ use crate::state::CommissioningMode;
 use crate::init::{
     load_data,
     attempt_rejoin,
+    send_rejoin_request,
     broadcast_device_announce,
     try_touchlink,
     try_network_steering,
@@ -14,7 +15,8 @@ use crate::init::{
 pub fn process(state: MachineState) -> MachineState {
     match state {
         MachineState::RestorePersistentData => load_data(),
-        MachineState::AttemptRejoin(state) => attempt_rejoin(state),
+        MachineState::AttemptRejoin(state, attempt) => attempt_rejoin(state, attempt),
+        MachineState::SendRejoinRequest(state, attempt) => send_rejoin_request(state, attempt),
         MachineState::BroadcastDeviceAnnounce(state) => broadcast_device_announce(state),
         MachineState::CommissioningBegin(state) => begin_commissioning(state),
         MachineState::Commissioning(state, CommissioningMode::Touchlink) => try_touchlink(state),
@@ -1,11 +1,27 @@
+use crate::nwk::commands::rejoin_response::RejoinStatus;
 use crate::state::{
     CommissioningMode,
     State,
 };
 
+/// Progress of the rejoin after a restart.
+///
+/// `AttemptRejoin` counts each request it asks for in `requests`. While in
+/// `SendRejoinRequest`, the driver sends the request and records the response of the
+/// parent, if any arrived within its timeout, in `status` before processing the state
+/// again.
+#[derive(Copy, Clone, Default)]
+pub struct RejoinAttempt {
+    /// Rejoin requests sent so far.
+    pub requests: u8,
+    /// Response of the parent to the last request, `None` if it did not answer.
+    pub status: Option<RejoinStatus>,
+}
+
 pub enum MachineState {
     RestorePersistentData,
-    AttemptRejoin(State),
+    AttemptRejoin(State, RejoinAttempt),
+    SendRejoinRequest(State, RejoinAttempt),
     BroadcastDeviceAnnounce(State),
     InitDone(State),
     CommissioningBegin(State),
@@ -1,77 +1,335 @@
-use crate::nwk::frame::SerdeError;
-use crate::serde::Serde;
+use core::fmt;
+use core::str::FromStr;
 
-pub struct Address([u8; 2]);
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
 
-impl Serde<Address, SerdeError> for Address {
-    fn serialize(&self, data: &mut [u8]) -> Result<u8, SerdeError> {
-        if data.len() > 1 {
-            data.clone_from_slice(&self.0);
-            Ok(2)
-        } else {
-            Err(SerdeError::NotEnoughSpace)
-        }
+/// 16-bit network address of a device.
+///
+/// Values of 0xfff8 and above are not assigned to devices, they are either one of the
+/// broadcast addresses of table 3.69 or reserved.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ShortAddress(pub u16);
+
+/// 3.6.5 Broadcast to all devices in the PAN.
+pub const BROADCAST_ALL_DEVICES: ShortAddress = ShortAddress(0xffff);
+/// 3.6.5 Broadcast to all devices with macRxOnWhenIdle = TRUE.
+pub const BROADCAST_RX_ON_WHEN_IDLE: ShortAddress = ShortAddress(0xfffd);
+/// 3.6.5 Broadcast to all routers and the coordinator.
+pub const BROADCAST_ROUTERS_AND_COORDINATOR: ShortAddress = ShortAddress(0xfffc);
+/// 3.6.5 Broadcast to low power routers only.
+pub const BROADCAST_LOW_POWER_ROUTERS: ShortAddress = ShortAddress(0xfffb);
+
+/// Table 3.69 Broadcast Addresses
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BroadcastClass {
+    AllDevices,
+    RxOnWhenIdle,
+    RoutersAndCoordinator,
+    LowPowerRouters,
+}
+
+impl ShortAddress {
+    /// The network address of the coordinator.
+    pub const COORDINATOR: ShortAddress = ShortAddress(0x0000);
+
+    pub fn from_le_bytes(bytes: [u8; 2]) -> Self {
+        ShortAddress(u16::from_le_bytes(bytes))
     }
-    
-    fn deserialize(data: &[u8]) -> Result<Self, SerdeError> {
-        if data.len() == 2 {
-            let mut address = Address([0; 2]);
-            address.0.clone_from_slice(&data);
-            Ok(address)
-        } else {
-            Err(SerdeError::WrongNumberOfBytes)
+
+    pub fn to_le_bytes(self) -> [u8; 2] {
+        self.0.to_le_bytes()
+    }
+
+    pub fn is_coordinator(&self) -> bool {
+        *self == Self::COORDINATOR
+    }
+
+    /// Whether the address can be assigned to a single device.
+    pub fn is_unicast(&self) -> bool {
+        self.0 < 0xfff8
+    }
+
+    /// Whether the address is one of the broadcast addresses 0xfffb to 0xffff.
+    pub fn is_broadcast(&self) -> bool {
+        self.broadcast_class().is_some()
+    }
+
+    /// Whether the address is one of the reserved addresses 0xfff8 to 0xfffa and 0xfffe.
+    pub fn is_reserved(&self) -> bool {
+        !self.is_unicast() && !self.is_broadcast()
+    }
+
+    pub fn broadcast_class(&self) -> Option<BroadcastClass> {
+        match *self {
+            BROADCAST_ALL_DEVICES => Some(BroadcastClass::AllDevices),
+            BROADCAST_RX_ON_WHEN_IDLE => Some(BroadcastClass::RxOnWhenIdle),
+            BROADCAST_ROUTERS_AND_COORDINATOR => Some(BroadcastClass::RoutersAndCoordinator),
+            BROADCAST_LOW_POWER_ROUTERS => Some(BroadcastClass::LowPowerRouters),
+            _ => None,
         }
     }
 }
 
+impl Serde for ShortAddress {
+    fn encoded_len(&self) -> usize {
+        2
+    }
 
-pub struct IEEEAddress([u8; 8]);
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u16(self.0)
+    }
 
-impl Serde<IEEEAddress, SerdeError> for IEEEAddress {
-    fn serialize(&self, data: &mut [u8]) -> Result<u8, SerdeError> {
-        if data.len() > 7 {
-            data.clone_from_slice(&self.0);
-            Ok(8)
-        } else {
-            Err(SerdeError::NotEnoughSpace)
-        }
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(ShortAddress(reader.read_u16()?))
     }
-    
-    fn deserialize(data: &[u8]) -> Result<Self, SerdeError> {
-        if data.len() == 8 {
-            let mut address = IEEEAddress([0; 8]);
-            address.0.clone_from_slice(&data);
-            Ok(address)
-        } else {
-            Err(SerdeError::WrongNumberOfBytes)
-        }
+}
+
+/// 64-bit IEEE address of a device, its extended address.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IEEEAddress(pub u64);
+
+impl IEEEAddress {
+    pub fn from_le_bytes(bytes: [u8; 8]) -> Self {
+        IEEEAddress(u64::from_le_bytes(bytes))
+    }
+
+    pub fn to_le_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+}
+
+impl Serde for IEEEAddress {
+    fn encoded_len(&self) -> usize {
+        8
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u64(self.0)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(IEEEAddress(reader.read_u64()?))
     }
 }
 
-pub struct GroupIdentifier([u8; 2]);
+/// 16-bit multicast group of the APS layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GroupIdentifier(pub u16);
 
-impl Serde<GroupIdentifier, SerdeError> for GroupIdentifier {
-    fn serialize(&self, data: &mut [u8]) -> Result<u8, SerdeError> {
-        if data.len() > 1 {
-            data.clone_from_slice(&self.0);
-            Ok(2)
-        } else {
-            Err(SerdeError::NotEnoughSpace)
+impl Serde for GroupIdentifier {
+    fn encoded_len(&self) -> usize {
+        2
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u16(self.0)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(GroupIdentifier(reader.read_u16()?))
+    }
+}
+
+/// 16-bit identifier of a PAN, valid identifiers range from 0x0000 to 0x3fff.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PanId(pub u16);
+
+impl PanId {
+    /// Largest valid PAN identifier.
+    pub const MAX: PanId = PanId(0x3fff);
+    /// Used to broadcast to all PANs and while a device is not associated.
+    pub const BROADCAST: PanId = PanId(0xffff);
+}
+
+impl Serde for PanId {
+    fn encoded_len(&self) -> usize {
+        2
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u16(self.0)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(PanId(reader.read_u16()?))
+    }
+}
+
+/// 64-bit extended PAN identifier, unique for every network.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedPanId(pub u64);
+
+impl Serde for ExtendedPanId {
+    fn encoded_len(&self) -> usize {
+        8
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u64(self.0)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(ExtendedPanId(reader.read_u64()?))
+    }
+}
+
+/// A string is not a valid address or identifier.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseAddressError;
+
+impl fmt::Display for ParseAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid address")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseAddressError {}
+
+fn parse_hex(digits: &str, max_digits: usize) -> Result<u64, ParseAddressError> {
+    if digits.is_empty() || digits.len() > max_digits || !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ParseAddressError);
+    }
+    u64::from_str_radix(digits, 16).map_err(|_| ParseAddressError)
+}
+
+/// Parses 16-bit values written as hex, with or without a `0x` prefix.
+fn parse_u16(s: &str) -> Result<u16, ParseAddressError> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    Ok(parse_hex(digits, 4)? as u16)
+}
+
+/// Parses 64-bit values written as eight colon separated octets, most significant
+/// first, or as hex with or without a `0x` prefix.
+fn parse_u64(s: &str) -> Result<u64, ParseAddressError> {
+    if s.contains(':') {
+        let mut value = 0;
+        let mut octets = 0;
+        for octet in s.split(':') {
+            if octet.len() != 2 || octets == 8 {
+                return Err(ParseAddressError);
+            }
+            value = (value << 8) | parse_hex(octet, 2)?;
+            octets += 1;
+        }
+        if octets != 8 {
+            return Err(ParseAddressError);
         }
+        Ok(value)
+    } else {
+        parse_hex(s.strip_prefix("0x").unwrap_or(s), 16)
     }
-    
-    fn deserialize(data: &[u8]) -> Result<Self, SerdeError> {
-        if data.len() == 2 {
-            let mut group_id = GroupIdentifier([0; 2]);
-            group_id.0.clone_from_slice(&data);
-            Ok(group_id)
-        } else {
-            Err(SerdeError::WrongNumberOfBytes)
+}
+
+fn fmt_colon_hex(f: &mut fmt::Formatter, value: u64) -> fmt::Result {
+    for (i, octet) in value.to_be_bytes().iter().enumerate() {
+        if i > 0 {
+            f.write_str(":")?;
         }
+        write!(f, "{:02x}", octet)?;
     }
+    Ok(())
 }
 
+impl fmt::Display for ShortAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+impl FromStr for ShortAddress {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_u16(s).map(ShortAddress)
+    }
+}
+
+impl fmt::Display for IEEEAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_colon_hex(f, self.0)
+    }
+}
+
+impl FromStr for IEEEAddress {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_u64(s).map(IEEEAddress)
+    }
+}
+
+impl fmt::Display for GroupIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+impl FromStr for GroupIdentifier {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_u16(s).map(GroupIdentifier)
+    }
+}
+
+impl fmt::Display for PanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+impl FromStr for PanId {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_u16(s).map(PanId)
+    }
+}
+
+impl fmt::Display for ExtendedPanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_colon_hex(f, self.0)
+    }
+}
+
+impl FromStr for ExtendedPanId {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_u64(s).map(ExtendedPanId)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AddressType {
-    Singlecast(Address),
+    Singlecast(ShortAddress),
     Multicast(GroupIdentifier),
-}
\ No newline at end of file
+}
+
+impl AddressType {
+    pub fn is_multicast(&self) -> bool {
+        matches!(self, AddressType::Multicast(_))
+    }
+
+    /// Writes the two byte address, whether it is a group is carried by a separate flag.
+    pub fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        match self {
+            AddressType::Singlecast(address) => writer.write(address),
+            AddressType::Multicast(group_id) => writer.write(group_id),
+        }
+    }
+
+    pub fn read(reader: &mut Reader, is_multicast: bool) -> Result<Self, SerdeError> {
+        if is_multicast {
+            Ok(AddressType::Multicast(reader.read()?))
+        } else {
+            Ok(AddressType::Singlecast(reader.read()?))
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use crate::nwk::address::{
+    ShortAddress,
+    BROADCAST_ALL_DEVICES,
+    BROADCAST_LOW_POWER_ROUTERS,
+    BROADCAST_ROUTERS_AND_COORDINATOR,
+    BROADCAST_RX_ON_WHEN_IDLE,
+};
+use crate::nwk::constants::MAX_NPDU_LENGTH;
+use crate::nwk::frame::{
+    NPDUFrame,
+    SerdeError,
+};
+use crate::nwk::neighbor_table::NeighborTable;
+use crate::serde::Serde;
+use crate::state::DeviceType;
+
+/// Default of nwkBroadcastDeliveryTime in milliseconds.
+pub const DEFAULT_BROADCAST_DELIVERY_TIME_MS: u32 = 9000;
+/// Default of nwkPassiveAckTimeout in milliseconds.
+pub const DEFAULT_PASSIVE_ACK_TIMEOUT_MS: u32 = 500;
+/// Default of nwkMaxBroadcastRetries.
+pub const DEFAULT_MAX_BROADCAST_RETRIES: u8 = 0x02;
+/// nwkcMaxBroadcastJitter in milliseconds.
+pub const MAX_BROADCAST_JITTER_MS: u16 = 0x40;
+/// Neighbors whose relaying of a broadcast is remembered. Beyond that a broadcast is
+/// simply repeated until nwkMaxBroadcastRetries is used up.
+const MAX_PASSIVE_ACKS: usize = 32;
+
+/// A serialized broadcast handed out for transmission.
+pub type BroadcastFrame = heapless::Vec<u8, MAX_NPDU_LENGTH>;
+
+#[derive(Debug)]
+pub enum BroadcastError {
+    /// No free broadcast transaction record is left, the frame must be dropped.
+    TableFull,
+    /// Broadcasts to low power routers are not supported, see `is_broadcast_recipient`.
+    LowPowerRouters,
+    Serde(SerdeError),
+}
+
+impl From<SerdeError> for BroadcastError {
+    fn from(error: SerdeError) -> Self {
+        BroadcastError::Serde(error)
+    }
+}
+
+/// What to do with a received broadcast.
+#[derive(Debug)]
+pub enum BroadcastIndication {
+    /// The broadcast was seen before and only counted as a passive acknowledgement.
+    Duplicate,
+    /// The broadcast is new. `deliver` tells whether it is addressed to this device,
+    /// a rebroadcast has been scheduled if `rebroadcast` is set.
+    New { deliver: bool, rebroadcast: bool },
+}
+
+/// A broadcast which still has to be (re)transmitted.
+struct PendingBroadcast {
+    frame: BroadcastFrame,
+    transmissions: u8,
+    next_transmission_ms: u64,
+    /// Neighbors which were heard relaying the broadcast.
+    passive_acks: heapless::Vec<ShortAddress, MAX_PASSIVE_ACKS>,
+}
+
+/// Broadcast Transaction Record
+pub struct BroadcastTransactionRecord {
+    pub source_address: ShortAddress,
+    pub sequence_number: u8,
+    pub expiration_ms: u64,
+    pending: Option<PendingBroadcast>,
+}
+
+/// Broadcast Transaction Table
+///
+/// Suppresses duplicate broadcasts and rebroadcasts new ones after a random jitter.
+/// Rebroadcasts are repeated until every router neighbor was heard relaying the frame
+/// or nwkMaxBroadcastRetries is used up. Holds up to `B` broadcast transaction records.
+pub struct BroadcastTransactionTable<const B: usize> {
+    pub broadcast_delivery_time_ms: u32,
+    pub passive_ack_timeout_ms: u32,
+    pub max_broadcast_retries: u8,
+    records: heapless::Vec<BroadcastTransactionRecord, B>,
+}
+
+/// Whether a device receives broadcasts to `destination`.
+///
+/// Only low power routers receive, relay and acknowledge broadcasts to
+/// `BROADCAST_LOW_POWER_ROUTERS`. Neither this device nor its neighbor table knows which
+/// routers are low power ones, so these broadcasts are rejected instead of being treated
+/// like broadcasts to all routers.
+pub fn is_broadcast_recipient(destination: &ShortAddress, device_type: DeviceType, rx_on_when_idle: bool) -> bool {
+    let is_router = device_type != DeviceType::EndDevice;
+    if *destination == BROADCAST_ALL_DEVICES {
+        true
+    } else if *destination == BROADCAST_RX_ON_WHEN_IDLE {
+        rx_on_when_idle
+    } else if *destination == BROADCAST_ROUTERS_AND_COORDINATOR {
+        is_router
+    } else {
+        false
+    }
+}
+
+impl<const B: usize> BroadcastTransactionTable<B> {
+    pub fn new() -> Self {
+        Self {
+            broadcast_delivery_time_ms: DEFAULT_BROADCAST_DELIVERY_TIME_MS,
+            passive_ack_timeout_ms: DEFAULT_PASSIVE_ACK_TIMEOUT_MS,
+            max_broadcast_retries: DEFAULT_MAX_BROADCAST_RETRIES,
+            records: heapless::Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BroadcastTransactionRecord> {
+        self.records.iter()
+    }
+
+    pub fn get(&self, source_address: &ShortAddress, sequence_number: u8) -> Option<&BroadcastTransactionRecord> {
+        self.records.iter().find(|record| {
+            record.source_address == *source_address && record.sequence_number == sequence_number
+        })
+    }
+
+    fn get_mut(&mut self, source_address: &ShortAddress, sequence_number: u8) -> Option<&mut BroadcastTransactionRecord> {
+        self.records.iter_mut().find(|record| {
+            record.source_address == *source_address && record.sequence_number == sequence_number
+        })
+    }
+
+    fn expire(&mut self, now_ms: u64) {
+        self.records.retain(|record| record.expiration_ms > now_ms);
+    }
+
+    fn insert(
+        &mut self,
+        frame: &NPDUFrame,
+        pending: Option<PendingBroadcast>,
+        now_ms: u64,
+    ) -> Result<(), BroadcastError> {
+        self.expire(now_ms);
+        self.records.push(BroadcastTransactionRecord {
+            source_address: frame.source_address,
+            sequence_number: frame.sequence_number,
+            expiration_ms: now_ms + u64::from(self.broadcast_delivery_time_ms),
+            pending,
+        }).map_err(|_| BroadcastError::TableFull)
+    }
+
+    fn pending(&self, frame: &NPDUFrame, next_transmission_ms: u64) -> Result<PendingBroadcast, BroadcastError> {
+        let mut data = [0; MAX_NPDU_LENGTH];
+        let length = frame.serialize(&mut data)?;
+        Ok(PendingBroadcast {
+            frame: data[..length].iter().copied().collect(),
+            transmissions: 0,
+            next_transmission_ms,
+            passive_acks: heapless::Vec::new(),
+        })
+    }
+
+    /// Originating a broadcast
+    ///
+    /// Records the transaction so that echoes are suppressed. The frame is handed out by
+    /// the next `poll` and retried until it was passively acknowledged.
+    pub fn broadcast_request(&mut self, frame: &NPDUFrame, now_ms: u64) -> Result<(), BroadcastError> {
+        if frame.destination_address == BROADCAST_LOW_POWER_ROUTERS {
+            return Err(BroadcastError::LowPowerRouters);
+        }
+        let pending = self.pending(frame, now_ms)?;
+        self.insert(frame, Some(pending), now_ms)
+    }
+
+    /// Upon receipt of a broadcast
+    ///
+    /// `previous_hop` is the MAC source of the frame. Routers rebroadcast new frames with
+    /// a decremented radius after a jitter of up to nwkcMaxBroadcastJitter, derived from
+    /// `random`. Broadcasts to low power routers are dropped without a record.
+    pub fn broadcast_indication(
+        &mut self,
+        frame: &mut NPDUFrame,
+        previous_hop: ShortAddress,
+        device_type: DeviceType,
+        rx_on_when_idle: bool,
+        now_ms: u64,
+        random: u16,
+    ) -> Result<BroadcastIndication, BroadcastError> {
+        if frame.destination_address == BROADCAST_LOW_POWER_ROUTERS {
+            return Err(BroadcastError::LowPowerRouters);
+        }
+        self.expire(now_ms);
+        let source_address = frame.source_address;
+        if let Some(record) = self.get_mut(&source_address, frame.sequence_number) {
+            if let Some(pending) = record.pending.as_mut() {
+                if !pending.passive_acks.contains(&previous_hop) {
+                    pending.passive_acks.push(previous_hop).ok();
+                }
+            }
+            return Ok(BroadcastIndication::Duplicate);
+        }
+
+        let destination = frame.destination_address;
+        let deliver = is_broadcast_recipient(&destination, device_type, rx_on_when_idle);
+        let rebroadcast = device_type != DeviceType::EndDevice && frame.radius > 1;
+
+        let pending = if rebroadcast {
+            frame.radius -= 1;
+            let jitter = random % (MAX_BROADCAST_JITTER_MS + 1);
+            let pending = self.pending(frame, now_ms + u64::from(jitter));
+            frame.radius += 1;
+            let mut pending = pending?;
+            pending.passive_acks.push(previous_hop).ok();
+            Some(pending)
+        } else {
+            None
+        };
+        self.insert(frame, pending, now_ms)?;
+
+        Ok(BroadcastIndication::New { deliver, rebroadcast })
+    }
+
+    /// Returns the serialized broadcasts which are due for (re)transmission.
+    ///
+    /// A broadcast is repeated every nwkPassiveAckTimeout as long as a router neighbor,
+    /// other than its originator, has not been heard relaying it.
+    pub fn poll<const N: usize>(&mut self, now_ms: u64, neighbor_table: &NeighborTable<N>) -> heapless::Vec<BroadcastFrame, B> {
+        self.expire(now_ms);
+
+        let passive_ack_timeout_ms = u64::from(self.passive_ack_timeout_ms);
+        let max_broadcast_retries = self.max_broadcast_retries;
+        let mut frames = heapless::Vec::new();
+        for record in self.records.iter_mut() {
+            let source_address = record.source_address;
+            let done = match record.pending.as_mut() {
+                None => continue,
+                Some(pending) if now_ms < pending.next_transmission_ms => continue,
+                Some(pending) => {
+                    let acknowledged = neighbor_table.iter()
+                        .filter(|neighbor| neighbor.device_type != DeviceType::EndDevice)
+                        .filter(|neighbor| neighbor.network_address != source_address)
+                        .all(|neighbor| pending.passive_acks.contains(&neighbor.network_address));
+                    if pending.transmissions > 0 && (acknowledged || pending.transmissions > max_broadcast_retries) {
+                        true
+                    } else {
+                        pending.transmissions += 1;
+                        pending.next_transmission_ms = now_ms + passive_ack_timeout_ms;
+                        // At most one frame per record, so this always fits.
+                        frames.push(pending.frame.clone()).ok();
+                        false
+                    }
+                },
+            };
+            if done {
+                record.pending = None;
+            }
+        }
+        frames
+    }
+}
+
+impl<const B: usize> Default for BroadcastTransactionTable<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::frame_builder::NPDUFrameBuilder;
+    use crate::nwk::neighbor_table::{
+        NeighborTableEntry,
+        Relationship,
+    };
+    use crate::nwk::payload::{
+        DataFrame,
+        Payload,
+    };
+
+    const ORIGINATOR: ShortAddress = ShortAddress(0x0001);
+    const NEIGHBOR: ShortAddress = ShortAddress(0x0002);
+
+    fn broadcast(source_address: ShortAddress, mut sequence_number: u8, radius: u8) -> NPDUFrame {
+        NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::new() }))
+            .destination(BROADCAST_ALL_DEVICES)
+            .source(source_address)
+            .radius(radius)
+            .build(&mut sequence_number)
+            .unwrap()
+    }
+
+    /// Router neighbors `ORIGINATOR` and `NEIGHBOR` plus an end device child.
+    fn neighbor_table() -> NeighborTable<4> {
+        let mut neighbor_table = NeighborTable::new();
+        for entry in [
+            NeighborTableEntry::new(ORIGINATOR, None, DeviceType::Router, Relationship::Sibling),
+            NeighborTableEntry::new(NEIGHBOR, None, DeviceType::Router, Relationship::Sibling),
+            NeighborTableEntry::new(ShortAddress(0x0003), None, DeviceType::EndDevice, Relationship::Child),
+        ] {
+            neighbor_table.insert(entry).unwrap();
+        }
+        neighbor_table
+    }
+
+    fn indication(
+        table: &mut BroadcastTransactionTable<4>,
+        mut frame: NPDUFrame,
+        previous_hop: ShortAddress,
+        now_ms: u64,
+        random: u16,
+    ) -> BroadcastIndication {
+        table.broadcast_indication(&mut frame, previous_hop, DeviceType::Router, true, now_ms, random).unwrap()
+    }
+
+    #[test]
+    fn duplicates_are_suppressed_by_source_and_sequence_number() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        assert!(matches!(
+            indication(&mut table, broadcast(ORIGINATOR, 1, 5), ORIGINATOR, 0, 0),
+            BroadcastIndication::New { deliver: true, rebroadcast: true }
+        ));
+        assert!(matches!(indication(&mut table, broadcast(ORIGINATOR, 1, 4), NEIGHBOR, 0, 0), BroadcastIndication::Duplicate));
+        assert!(matches!(indication(&mut table, broadcast(ORIGINATOR, 2, 5), ORIGINATOR, 0, 0), BroadcastIndication::New { .. }));
+        assert!(matches!(indication(&mut table, broadcast(NEIGHBOR, 1, 5), NEIGHBOR, 0, 0), BroadcastIndication::New { .. }));
+        assert_eq!(table.len(), 3);
+
+        // Records are kept for nwkBroadcastDeliveryTime.
+        let expiration_ms = u64::from(DEFAULT_BROADCAST_DELIVERY_TIME_MS);
+        assert!(matches!(indication(&mut table, broadcast(ORIGINATOR, 1, 5), NEIGHBOR, expiration_ms - 1, 0), BroadcastIndication::Duplicate));
+        assert!(matches!(indication(&mut table, broadcast(ORIGINATOR, 1, 5), NEIGHBOR, expiration_ms, 0), BroadcastIndication::New { .. }));
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn rebroadcast_has_decremented_radius() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        let mut frame = broadcast(ORIGINATOR, 1, 5);
+        table.broadcast_indication(&mut frame, ORIGINATOR, DeviceType::Router, true, 0, 0).unwrap();
+        assert_eq!(frame.radius, 5);
+
+        let frames = table.poll(0, &neighbor_table());
+        assert_eq!(frames.len(), 1);
+        let rebroadcast = NPDUFrame::deserialize(&frames[0]).unwrap();
+        assert_eq!(rebroadcast.radius, 4);
+        assert_eq!(rebroadcast.source_address, ORIGINATOR);
+        assert_eq!(rebroadcast.sequence_number, 1);
+    }
+
+    #[test]
+    fn exhausted_radius_and_end_devices_do_not_rebroadcast() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        assert!(matches!(
+            indication(&mut table, broadcast(ORIGINATOR, 1, 1), ORIGINATOR, 0, 0),
+            BroadcastIndication::New { deliver: true, rebroadcast: false }
+        ));
+        let mut frame = broadcast(ORIGINATOR, 2, 5);
+        assert!(matches!(
+            table.broadcast_indication(&mut frame, ORIGINATOR, DeviceType::EndDevice, false, 0, 0).unwrap(),
+            BroadcastIndication::New { deliver: true, rebroadcast: false }
+        ));
+        assert!(table.poll(0, &neighbor_table()).is_empty());
+    }
+
+    #[test]
+    fn passive_acks_stop_the_retries() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        let neighbor_table = neighbor_table();
+        indication(&mut table, broadcast(ORIGINATOR, 1, 5), ORIGINATOR, 0, 0);
+        assert_eq!(table.poll(0, &neighbor_table).len(), 1);
+
+        // The end device child does not have to relay the broadcast.
+        indication(&mut table, broadcast(ORIGINATOR, 1, 3), NEIGHBOR, 100, 0);
+        let passive_ack_timeout_ms = u64::from(DEFAULT_PASSIVE_ACK_TIMEOUT_MS);
+        assert!(table.poll(passive_ack_timeout_ms, &neighbor_table).is_empty());
+        assert!(table.poll(10 * passive_ack_timeout_ms, &neighbor_table).is_empty());
+    }
+
+    #[test]
+    fn originated_broadcast_is_retried_until_acknowledged() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        let neighbor_table = neighbor_table();
+        table.broadcast_request(&broadcast(ShortAddress(0x0100), 1, 5), 0).unwrap();
+        let passive_ack_timeout_ms = u64::from(DEFAULT_PASSIVE_ACK_TIMEOUT_MS);
+
+        assert_eq!(table.poll(0, &neighbor_table).len(), 1);
+        indication(&mut table, broadcast(ShortAddress(0x0100), 1, 4), ORIGINATOR, 10, 0);
+        assert_eq!(table.poll(passive_ack_timeout_ms, &neighbor_table).len(), 1);
+        indication(&mut table, broadcast(ShortAddress(0x0100), 1, 4), NEIGHBOR, 600, 0);
+        assert!(table.poll(2 * passive_ack_timeout_ms, &neighbor_table).is_empty());
+    }
+
+    #[test]
+    fn retries_are_bounded() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        let neighbor_table = neighbor_table();
+        indication(&mut table, broadcast(ORIGINATOR, 1, 5), ORIGINATOR, 0, 0);
+        let passive_ack_timeout_ms = u64::from(DEFAULT_PASSIVE_ACK_TIMEOUT_MS);
+
+        let transmissions: usize = (0..10)
+            .map(|i| table.poll(i * passive_ack_timeout_ms, &neighbor_table).len())
+            .sum();
+        assert_eq!(transmissions, 1 + usize::from(DEFAULT_MAX_BROADCAST_RETRIES));
+        // The record stays to suppress duplicates.
+        assert!(table.get(&ORIGINATOR, 1).is_some());
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        let max_jitter_ms = u64::from(MAX_BROADCAST_JITTER_MS);
+        for (random, jitter_ms) in [
+            (0, 0),
+            (MAX_BROADCAST_JITTER_MS, max_jitter_ms),
+            (MAX_BROADCAST_JITTER_MS + 1, 0),
+            (u16::MAX, u64::from(u16::MAX) % (max_jitter_ms + 1)),
+        ] {
+            let mut table = BroadcastTransactionTable::<4>::new();
+            indication(&mut table, broadcast(ORIGINATOR, 1, 5), ORIGINATOR, 1000, random);
+            if jitter_ms > 0 {
+                assert!(table.poll(1000 + jitter_ms - 1, &neighbor_table()).is_empty());
+            }
+            assert_eq!(table.poll(1000 + jitter_ms, &neighbor_table()).len(), 1);
+        }
+    }
+
+    #[test]
+    fn full_table_drops_the_broadcast() {
+        let mut table = BroadcastTransactionTable::<1>::new();
+        let mut frame = broadcast(ORIGINATOR, 1, 5);
+        table.broadcast_indication(&mut frame, ORIGINATOR, DeviceType::Router, true, 0, 0).unwrap();
+        let mut frame = broadcast(ORIGINATOR, 2, 5);
+        assert!(matches!(
+            table.broadcast_indication(&mut frame, ORIGINATOR, DeviceType::Router, true, 0, 0),
+            Err(BroadcastError::TableFull)
+        ));
+    }
+
+    #[test]
+    fn broadcast_recipients() {
+        assert!(is_broadcast_recipient(&BROADCAST_ALL_DEVICES, DeviceType::EndDevice, false));
+        assert!(is_broadcast_recipient(&BROADCAST_RX_ON_WHEN_IDLE, DeviceType::EndDevice, true));
+        assert!(!is_broadcast_recipient(&BROADCAST_RX_ON_WHEN_IDLE, DeviceType::EndDevice, false));
+        assert!(!is_broadcast_recipient(&BROADCAST_ROUTERS_AND_COORDINATOR, DeviceType::EndDevice, true));
+        assert!(is_broadcast_recipient(&BROADCAST_ROUTERS_AND_COORDINATOR, DeviceType::Coordinator, true));
+        assert!(!is_broadcast_recipient(&BROADCAST_LOW_POWER_ROUTERS, DeviceType::Router, true));
+        assert!(!is_broadcast_recipient(&ShortAddress(0x1234), DeviceType::Router, true));
+    }
+
+    #[test]
+    fn broadcasts_to_low_power_routers_are_rejected() {
+        let mut table = BroadcastTransactionTable::<4>::new();
+        let mut frame = broadcast(ORIGINATOR, 1, 5);
+        frame.destination_address = BROADCAST_LOW_POWER_ROUTERS;
+        assert!(matches!(table.broadcast_request(&frame, 0), Err(BroadcastError::LowPowerRouters)));
+        assert!(matches!(
+            table.broadcast_indication(&mut frame, ORIGINATOR, DeviceType::Router, true, 0, 0),
+            Err(BroadcastError::LowPowerRouters)
+        ));
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+
+/// 3.4.11.3.1 Requested Timeout Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RequestedTimeout {
+    Seconds10 = 0,
+    Minutes2 = 1,
+    Minutes4 = 2,
+    Minutes8 = 3,
+    Minutes16 = 4,
+    Minutes32 = 5,
+    Minutes64 = 6,
+    Minutes128 = 7,
+    Minutes256 = 8,
+    Minutes512 = 9,
+    Minutes1024 = 10,
+    Minutes2048 = 11,
+    Minutes4096 = 12,
+    Minutes8192 = 13,
+    Minutes16384 = 14,
+}
+
+impl RequestedTimeout {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(RequestedTimeout::Seconds10),
+            1 => Some(RequestedTimeout::Minutes2),
+            2 => Some(RequestedTimeout::Minutes4),
+            3 => Some(RequestedTimeout::Minutes8),
+            4 => Some(RequestedTimeout::Minutes16),
+            5 => Some(RequestedTimeout::Minutes32),
+            6 => Some(RequestedTimeout::Minutes64),
+            7 => Some(RequestedTimeout::Minutes128),
+            8 => Some(RequestedTimeout::Minutes256),
+            9 => Some(RequestedTimeout::Minutes512),
+            10 => Some(RequestedTimeout::Minutes1024),
+            11 => Some(RequestedTimeout::Minutes2048),
+            12 => Some(RequestedTimeout::Minutes4096),
+            13 => Some(RequestedTimeout::Minutes8192),
+            14 => Some(RequestedTimeout::Minutes16384),
+            _ => None,
+        }
+    }
+
+    pub fn as_millis(&self) -> u64 {
+        match self {
+            RequestedTimeout::Seconds10 => 10 * 1000,
+            timeout => (1 << (*timeout as u64)) * 60 * 1000,
+        }
+    }
+}
+
+impl Serde for RequestedTimeout {
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(*self as u8)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        let value = reader.read_u8()?;
+        RequestedTimeout::from_u8(value).ok_or_else(|| SerdeError::invalid_value(value, offset))
+    }
+}
+
+/// 3.4.11 End Device Timeout Request Command
+#[derive(Debug)]
+pub struct EndDeviceTimeoutRequest {
+    /// 3.4.11.3.1 Requested Timeout Field, kept as received so that the parent can answer
+    /// values beyond `RequestedTimeout::Minutes16384` with INCORRECT_VALUE.
+    pub requested_timeout: u8,
+    /// 3.4.11.3.2 End Device Configuration Field, reserved and zero.
+    pub end_device_configuration: u8,
+}
+
+impl EndDeviceTimeoutRequest {
+    pub fn new(requested_timeout: RequestedTimeout) -> Self {
+        Self {
+            requested_timeout: requested_timeout as u8,
+            end_device_configuration: 0,
+        }
+    }
+
+    /// The requested timeout, `None` if the value is out of range.
+    pub fn timeout(&self) -> Option<RequestedTimeout> {
+        RequestedTimeout::from_u8(self.requested_timeout)
+    }
+}
+
+impl Serde for EndDeviceTimeoutRequest {
+    fn encoded_len(&self) -> usize {
+        2
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(self.requested_timeout)?;
+        writer.write_u8(self.end_device_configuration)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(EndDeviceTimeoutRequest {
+            requested_timeout: reader.read_u8()?,
+            end_device_configuration: reader.read_u8()?,
+        })
+    }
+}
@@ -0,0 +1,69 @@
+use bitflags::bitflags;
+
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+
+/// 3.4.12.3.1 Status Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeoutStatus {
+    Success = 0x00,
+    IncorrectValue = 0x01,
+}
+
+impl Serde for TimeoutStatus {
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(*self as u8)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        match reader.read_u8()? {
+            0x00 => Ok(TimeoutStatus::Success),
+            0x01 => Ok(TimeoutStatus::IncorrectValue),
+            value => Err(SerdeError::invalid_value(value, offset)),
+        }
+    }
+}
+
+bitflags! {
+    /// 3.4.12.3.2 Parent Information Field
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct ParentInformation: u8 {
+        const MacDataPollKeepalive             = 0b0000_0001;
+        const EndDeviceTimeoutRequestKeepalive = 0b0000_0010;
+        const PowerNegotiation                 = 0b0000_0100;
+    }
+}
+
+/// 3.4.12 End Device Timeout Response Command
+#[derive(Debug)]
+pub struct EndDeviceTimeoutResponse {
+    pub status: TimeoutStatus,
+    pub parent_information: ParentInformation,
+}
+
+impl Serde for EndDeviceTimeoutResponse {
+    fn encoded_len(&self) -> usize {
+        2
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&self.status)?;
+        writer.write_u8(self.parent_information.bits())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(EndDeviceTimeoutResponse {
+            status: reader.read()?,
+            parent_information: ParentInformation::from_bits_truncate(reader.read_u8()?),
+        })
+    }
+}
@@ -0,0 +1,15 @@
+use zigbee_rs_derive::Bitfield;
+
+/// 3.4.4 Leave Command
+#[derive(Debug, Bitfield)]
+pub struct Leave {
+    // 3.4.4.3.1.1 Rejoin Sub-Field
+    #[bits(5)]
+    pub rejoin: bool,
+    // 3.4.4.3.1.2 Request Sub-Field
+    #[bits(6)]
+    pub request: bool,
+    // 3.4.4.3.1.3 Remove Children Sub-Field
+    #[bits(7)]
+    pub remove_children: bool,
+}
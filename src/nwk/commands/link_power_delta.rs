@@ -0,0 +1,103 @@
+use crate::nwk::address::ShortAddress;
+use crate::nwk::constants::MAX_NSDU_LENGTH;
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    SerdeErrorKind,
+    Writer,
+};
+use zigbee_rs_derive::{
+    Bitfield,
+    BitfieldEnum,
+};
+
+/// 3.4.13.3.1.1 Type Sub-Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq, BitfieldEnum)]
+pub enum PowerDeltaType {
+    Notification = 0b00,
+    Request = 0b01,
+    Response = 0b10,
+}
+
+/// 3.4.13.3.1 Command Options Field
+#[derive(Debug, Bitfield)]
+struct CommandOptions {
+    #[bits(0..2)]
+    power_delta_type: PowerDeltaType,
+}
+
+/// 3.4.13.3.3 Power List Field Entry
+#[derive(Debug)]
+pub struct PowerDeltaEntry {
+    pub device_address: ShortAddress,
+    /// The change of transmit power in dBm the device is asked to apply.
+    pub power_delta: i8,
+}
+
+const ENTRY_NUM_BYTES: usize = 3;
+
+/// Number of power list entries fitting into a frame besides the command identifier,
+/// options and count.
+pub const MAX_ENTRY_COUNT: usize = (MAX_NSDU_LENGTH - 3) / ENTRY_NUM_BYTES;
+
+impl Serde for PowerDeltaEntry {
+    fn encoded_len(&self) -> usize {
+        ENTRY_NUM_BYTES
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&self.device_address)?;
+        writer.write_i8(self.power_delta)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(PowerDeltaEntry {
+            device_address: reader.read()?,
+            power_delta: reader.read_i8()?,
+        })
+    }
+}
+
+/// 3.4.13 Link Power Delta Command
+#[derive(Debug)]
+pub struct LinkPowerDelta {
+    pub power_delta_type: PowerDeltaType,
+    pub entries: heapless::Vec<PowerDeltaEntry, MAX_ENTRY_COUNT>,
+}
+
+impl Serde for LinkPowerDelta {
+    fn encoded_len(&self) -> usize {
+        2 + self.entries.len() * ENTRY_NUM_BYTES
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&CommandOptions {
+            power_delta_type: self.power_delta_type,
+        })?;
+        writer.write_u8(self.entries.len() as u8)?;
+        for entry in self.entries.iter() {
+            writer.write(entry)?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let power_delta_type = reader.read::<CommandOptions>()?.power_delta_type;
+        let offset = reader.position();
+        let count = reader.read_u8()?;
+        if count as usize > MAX_ENTRY_COUNT {
+            return Err(SerdeError::new(
+                SerdeErrorKind::CapacityExceeded { length: count as usize, capacity: MAX_ENTRY_COUNT },
+                offset,
+            ).context("list_count"));
+        }
+        let entries = (0..count)
+            .map(|_| reader.read())
+            .collect::<Result<_, _>>()?;
+        Ok(LinkPowerDelta {
+            power_delta_type,
+            entries,
+        })
+    }
+}
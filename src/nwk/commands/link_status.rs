@@ -0,0 +1,149 @@
+use crate::nwk::address::ShortAddress;
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+use zigbee_rs_derive::Bitfield;
+
+/// Number of link status entries the entry count sub-field can announce.
+pub const MAX_ENTRY_COUNT: usize = 0x1f;
+
+/// 3.4.8.3.2 Link Status Information Fields
+#[derive(Debug)]
+pub struct LinkStatusEntry {
+    pub neighbor_network_address: ShortAddress,
+    pub incoming_cost: u8,
+    pub outgoing_cost: u8,
+}
+
+/// 3.4.8.3.2 Link Status Information Fields, the cost byte of an entry.
+#[derive(Debug, Bitfield)]
+struct LinkCosts {
+    #[bits(0..3)]
+    incoming_cost: u8,
+    #[bits(4..7)]
+    outgoing_cost: u8,
+}
+
+const ENTRY_NUM_BYTES: usize = 3;
+
+impl Serde for LinkStatusEntry {
+    fn encoded_len(&self) -> usize {
+        ENTRY_NUM_BYTES
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&self.neighbor_network_address)?;
+        writer.write(&LinkCosts {
+            incoming_cost: self.incoming_cost,
+            outgoing_cost: self.outgoing_cost,
+        })
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let neighbor_network_address = reader.read()?;
+        let costs: LinkCosts = reader.read()?;
+        Ok(LinkStatusEntry {
+            neighbor_network_address,
+            incoming_cost: costs.incoming_cost,
+            outgoing_cost: costs.outgoing_cost,
+        })
+    }
+}
+
+/// 3.4.8.3.1 Command Options Field
+#[derive(Debug, Bitfield)]
+struct CommandOptions {
+    #[bits(0..5)]
+    entry_count: u8,
+    #[bits(5)]
+    first_frame: bool,
+    #[bits(6)]
+    last_frame: bool,
+}
+
+/// 3.4.8 Link Status Command
+#[derive(Debug)]
+pub struct LinkStatus {
+    pub first_frame: bool,
+    pub last_frame: bool,
+    pub entries: heapless::Vec<LinkStatusEntry, MAX_ENTRY_COUNT>,
+}
+
+impl Serde for LinkStatus {
+    fn encoded_len(&self) -> usize {
+        1 + self.entries.len() * ENTRY_NUM_BYTES
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&CommandOptions {
+            entry_count: self.entries.len() as u8,
+            first_frame: self.first_frame,
+            last_frame: self.last_frame,
+        })?;
+        for entry in self.entries.iter() {
+            writer.write(entry)?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let options: CommandOptions = reader.read()?;
+        let entries = (0..options.entry_count)
+            .map(|_| reader.read())
+            .collect::<Result<_, _>>()?;
+        Ok(LinkStatus {
+            first_frame: options.first_frame,
+            last_frame: options.last_frame,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde::SerdeErrorKind;
+
+    fn link_status(incoming_cost: u8, outgoing_cost: u8) -> LinkStatus {
+        let mut entries = heapless::Vec::new();
+        entries.push(LinkStatusEntry {
+            neighbor_network_address: ShortAddress(0x796f),
+            incoming_cost,
+            outgoing_cost,
+        }).unwrap();
+        LinkStatus {
+            first_frame: true,
+            last_frame: false,
+            entries,
+        }
+    }
+
+    #[test]
+    fn costs_share_a_byte() {
+        let mut data = [0; 4];
+        link_status(1, 7).serialize(&mut data).unwrap();
+        assert_eq!(data, [0b0010_0001, 0x6f, 0x79, 0b0111_0001]);
+
+        // The reserved bits are ignored.
+        let decoded = LinkStatus::deserialize(&[0b0010_0001, 0x6f, 0x79, 0b1111_1001]).unwrap();
+        assert!(decoded.first_frame && !decoded.last_frame);
+        assert_eq!(decoded.entries[0].incoming_cost, 1);
+        assert_eq!(decoded.entries[0].outgoing_cost, 7);
+    }
+
+    #[test]
+    fn costs_wider_than_their_sub_field_are_rejected() {
+        let mut data = [0; 4];
+        let error = link_status(8, 1).serialize(&mut data).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 8 });
+        assert_eq!(error.offset, 3);
+        assert_eq!(
+            error.path().collect::<heapless::Vec<_, 4>>(),
+            ["LinkStatus", "LinkStatusEntry", "LinkCosts", "incoming_cost"],
+        );
+        assert!(link_status(1, 8).serialize(&mut data).is_err());
+    }
+}
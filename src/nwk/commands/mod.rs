@@ -1 +1,13 @@
-pub mod route_request;
\ No newline at end of file
+pub mod route_request;
+pub mod route_reply;
+pub mod network_status;
+pub mod leave;
+pub mod route_record;
+pub mod rejoin_request;
+pub mod rejoin_response;
+pub mod link_status;
+pub mod network_report;
+pub mod network_update;
+pub mod end_device_timeout_request;
+pub mod end_device_timeout_response;
+pub mod link_power_delta;
@@ -0,0 +1,88 @@
+use crate::nwk::address::{
+    ExtendedPanId,
+    PanId,
+};
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+use zigbee_rs_derive::{
+    Bitfield,
+    BitfieldEnum,
+};
+
+/// Number of report information entries the count sub-field can announce.
+pub const MAX_REPORT_INFORMATION_COUNT: usize = 0x1f;
+
+/// 3.4.9.3.1.2 Report Command Identifier Sub-Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq, BitfieldEnum)]
+pub enum ReportCommandIdentifier {
+    PanIdentifierConflict = 0x00,
+}
+
+/// 3.4.9.3.3 Report Information
+#[derive(Debug)]
+pub enum ReportInformation {
+    /// The PAN identifiers heard on the channel which conflict with ours.
+    PanIdentifierConflict(heapless::Vec<PanId, MAX_REPORT_INFORMATION_COUNT>),
+}
+
+impl ReportInformation {
+    pub fn report_command_identifier(&self) -> ReportCommandIdentifier {
+        match self {
+            ReportInformation::PanIdentifierConflict(_) => ReportCommandIdentifier::PanIdentifierConflict,
+        }
+    }
+}
+
+/// 3.4.9.3.1 Command Options Field
+#[derive(Debug, Bitfield)]
+struct CommandOptions {
+    #[bits(0..5)]
+    report_information_count: u8,
+    #[bits(5..8)]
+    report_command_identifier: ReportCommandIdentifier,
+}
+
+/// 3.4.9 Network Report Command
+#[derive(Debug)]
+pub struct NetworkReport {
+    pub extended_pan_id: ExtendedPanId,
+    pub report_information: ReportInformation,
+}
+
+const MIN_NUM_BYTES: usize = 9;
+
+impl Serde for NetworkReport {
+    fn encoded_len(&self) -> usize {
+        let ReportInformation::PanIdentifierConflict(pan_ids) = &self.report_information;
+        MIN_NUM_BYTES + pan_ids.len() * 2
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        let ReportInformation::PanIdentifierConflict(pan_ids) = &self.report_information;
+        writer.write(&CommandOptions {
+            report_information_count: pan_ids.len() as u8,
+            report_command_identifier: self.report_information.report_command_identifier(),
+        })?;
+        writer.write(&self.extended_pan_id)?;
+        for pan_id in pan_ids.iter() {
+            writer.write(pan_id)?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let options: CommandOptions = reader.read()?;
+        let extended_pan_id = reader.read()?;
+        let pan_ids = (0..options.report_information_count)
+            .map(|_| reader.read())
+            .collect::<Result<_, _>>()?;
+        Ok(NetworkReport {
+            extended_pan_id,
+            report_information: ReportInformation::PanIdentifierConflict(pan_ids),
+        })
+    }
+}
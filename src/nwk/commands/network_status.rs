@@ -0,0 +1,147 @@
+use crate::nwk::address::ShortAddress;
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+
+/// 3.4.3.3.1 Status Code Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StatusCode {
+    NoRouteAvailable = 0x00,
+    TreeLinkFailure = 0x01,
+    NonTreeLinkFailure = 0x02,
+    LowBatteryLevel = 0x03,
+    NoRoutingCapacity = 0x04,
+    NoIndirectCapacity = 0x05,
+    IndirectTransactionExpiry = 0x06,
+    TargetDeviceUnavailable = 0x07,
+    TargetAddressUnallocated = 0x08,
+    ParentLinkFailure = 0x09,
+    ValidateRoute = 0x0a,
+    SourceRouteFailure = 0x0b,
+    ManyToOneRouteFailure = 0x0c,
+    AddressConflict = 0x0d,
+    VerifyAddresses = 0x0e,
+    PanIdentifierUpdate = 0x0f,
+    NetworkAddressUpdate = 0x10,
+    BadFrameCounter = 0x11,
+    BadKeySequenceNumber = 0x12,
+    UnknownCommand = 0x13,
+}
+
+impl Serde for StatusCode {
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(*self as u8)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        match reader.read_u8()? {
+            0x00 => Ok(StatusCode::NoRouteAvailable),
+            0x01 => Ok(StatusCode::TreeLinkFailure),
+            0x02 => Ok(StatusCode::NonTreeLinkFailure),
+            0x03 => Ok(StatusCode::LowBatteryLevel),
+            0x04 => Ok(StatusCode::NoRoutingCapacity),
+            0x05 => Ok(StatusCode::NoIndirectCapacity),
+            0x06 => Ok(StatusCode::IndirectTransactionExpiry),
+            0x07 => Ok(StatusCode::TargetDeviceUnavailable),
+            0x08 => Ok(StatusCode::TargetAddressUnallocated),
+            0x09 => Ok(StatusCode::ParentLinkFailure),
+            0x0a => Ok(StatusCode::ValidateRoute),
+            0x0b => Ok(StatusCode::SourceRouteFailure),
+            0x0c => Ok(StatusCode::ManyToOneRouteFailure),
+            0x0d => Ok(StatusCode::AddressConflict),
+            0x0e => Ok(StatusCode::VerifyAddresses),
+            0x0f => Ok(StatusCode::PanIdentifierUpdate),
+            0x10 => Ok(StatusCode::NetworkAddressUpdate),
+            0x11 => Ok(StatusCode::BadFrameCounter),
+            0x12 => Ok(StatusCode::BadKeySequenceNumber),
+            0x13 => Ok(StatusCode::UnknownCommand),
+            value => Err(SerdeError::invalid_value(value, offset)),
+        }
+    }
+}
+
+impl StatusCode {
+    /// Whether the status reports a broken route that has to be rediscovered.
+    pub fn is_route_failure(&self) -> bool {
+        matches!(self,
+            StatusCode::NoRouteAvailable
+            | StatusCode::TreeLinkFailure
+            | StatusCode::NonTreeLinkFailure
+            | StatusCode::SourceRouteFailure
+            | StatusCode::ManyToOneRouteFailure)
+    }
+}
+
+/// 3.4.3 Network Status Command
+#[derive(Debug)]
+pub struct NetworkStatus {
+    pub status_code: StatusCode,
+    pub destination_address: ShortAddress,
+}
+
+impl Serde for NetworkStatus {
+    fn encoded_len(&self) -> usize {
+        self.status_code.encoded_len() + self.destination_address.encoded_len()
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&self.status_code)?;
+        writer.write(&self.destination_address)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(NetworkStatus {
+            status_code: reader.read()?,
+            destination_address: reader.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde::SerdeErrorKind;
+
+    #[test]
+    fn network_status_round_trips() {
+        let status = NetworkStatus {
+            status_code: StatusCode::SourceRouteFailure,
+            destination_address: ShortAddress(0x796f),
+        };
+        let mut data = [0; 3];
+        assert_eq!(status.serialize(&mut data).unwrap(), 3);
+        assert_eq!(data, [0x0b, 0x6f, 0x79]);
+
+        let decoded = NetworkStatus::deserialize(&data).unwrap();
+        assert_eq!(decoded.status_code, StatusCode::SourceRouteFailure);
+        assert_eq!(decoded.destination_address, ShortAddress(0x796f));
+    }
+
+    #[test]
+    fn unknown_status_codes_are_rejected() {
+        for code in 0x00..=0x13 {
+            let status_code = StatusCode::deserialize(&[code]).unwrap();
+            assert_eq!(status_code as u8, code);
+        }
+        let error = NetworkStatus::deserialize(&[0x14, 0x6f, 0x79]).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 0x14 });
+        assert_eq!(error.offset, 0);
+        assert_eq!(error.path().collect::<heapless::Vec<_, 4>>(), ["NetworkStatus", "StatusCode"]);
+    }
+
+    #[test]
+    fn route_failures() {
+        assert!(StatusCode::NoRouteAvailable.is_route_failure());
+        assert!(StatusCode::ManyToOneRouteFailure.is_route_failure());
+        assert!(!StatusCode::LowBatteryLevel.is_route_failure());
+        assert!(!StatusCode::AddressConflict.is_route_failure());
+    }
+}
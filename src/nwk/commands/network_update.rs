@@ -0,0 +1,88 @@
+use crate::nwk::address::{
+    ExtendedPanId,
+    PanId,
+};
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+use zigbee_rs_derive::{
+    Bitfield,
+    BitfieldEnum,
+};
+
+/// 3.4.10.3.1.2 Update Command Identifier Sub-Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq, BitfieldEnum)]
+pub enum UpdateCommandIdentifier {
+    PanIdentifierUpdate = 0x00,
+}
+
+/// 3.4.10.3.4 Update Information
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UpdateInformation {
+    /// The PAN identifier the network moves to.
+    PanIdentifierUpdate(PanId),
+}
+
+impl UpdateInformation {
+    pub fn update_command_identifier(&self) -> UpdateCommandIdentifier {
+        match self {
+            UpdateInformation::PanIdentifierUpdate(_) => UpdateCommandIdentifier::PanIdentifierUpdate,
+        }
+    }
+}
+
+/// 3.4.10.3.1 Command Options Field
+#[derive(Debug, Bitfield)]
+struct CommandOptions {
+    #[bits(0..5)]
+    update_information_count: u8,
+    #[bits(5..8)]
+    update_command_identifier: UpdateCommandIdentifier,
+}
+
+/// 3.4.10 Network Update Command
+#[derive(Debug)]
+pub struct NetworkUpdate {
+    pub extended_pan_id: ExtendedPanId,
+    pub update_id: u8,
+    pub update_information: UpdateInformation,
+}
+
+const NUM_BYTES: usize = 12;
+
+impl Serde for NetworkUpdate {
+    fn encoded_len(&self) -> usize {
+        NUM_BYTES
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        let UpdateInformation::PanIdentifierUpdate(pan_id) = self.update_information;
+        writer.write(&CommandOptions {
+            update_information_count: 1,
+            update_command_identifier: self.update_information.update_command_identifier(),
+        })?;
+        writer.write(&self.extended_pan_id)?;
+        writer.write_u8(self.update_id)?;
+        writer.write(&pan_id)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        let options: CommandOptions = reader.read()?;
+        if options.update_information_count != 1 {
+            return Err(
+                SerdeError::invalid_value(options.update_information_count, offset)
+                    .context("update_information_count")
+                    .context("CommandOptions")
+            );
+        }
+        Ok(NetworkUpdate {
+            extended_pan_id: reader.read()?,
+            update_id: reader.read_u8()?,
+            update_information: UpdateInformation::PanIdentifierUpdate(reader.read()?),
+        })
+    }
+}
@@ -0,0 +1,45 @@
+use bitflags::bitflags;
+
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+
+bitflags! {
+    /// 3.4.6.3.1 Capability Information Field
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct CapabilityInformation: u8 {
+        const AlternatePanCoordinator = 0b0000_0001;
+        /// Set for full function devices which may become routers.
+        const DeviceType              = 0b0000_0010;
+        /// Set if the device is mains powered.
+        const PowerSource             = 0b0000_0100;
+        const ReceiverOnWhenIdle      = 0b0000_1000;
+        const SecurityCapability      = 0b0100_0000;
+        const AllocateAddress         = 0b1000_0000;
+    }
+}
+
+/// 3.4.6 Rejoin Request Command
+#[derive(Debug)]
+pub struct RejoinRequest {
+    pub capability_information: CapabilityInformation,
+}
+
+impl Serde for RejoinRequest {
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(self.capability_information.bits())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(RejoinRequest {
+            capability_information: CapabilityInformation::from_bits_truncate(reader.read_u8()?),
+        })
+    }
+}
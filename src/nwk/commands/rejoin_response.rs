@@ -0,0 +1,60 @@
+use crate::nwk::address::ShortAddress;
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+
+/// 3.4.7.3.2 Rejoin Status Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RejoinStatus {
+    Success = 0x00,
+    PanAtCapacity = 0x01,
+    PanAccessDenied = 0x02,
+}
+
+impl Serde for RejoinStatus {
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(*self as u8)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        match reader.read_u8()? {
+            0x00 => Ok(RejoinStatus::Success),
+            0x01 => Ok(RejoinStatus::PanAtCapacity),
+            0x02 => Ok(RejoinStatus::PanAccessDenied),
+            value => Err(SerdeError::invalid_value(value, offset)),
+        }
+    }
+}
+
+/// 3.4.7 Rejoin Response Command
+#[derive(Debug)]
+pub struct RejoinResponse {
+    pub network_address: ShortAddress,
+    pub rejoin_status: RejoinStatus,
+}
+
+impl Serde for RejoinResponse {
+    fn encoded_len(&self) -> usize {
+        self.network_address.encoded_len() + self.rejoin_status.encoded_len()
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write(&self.network_address)?;
+        writer.write(&self.rejoin_status)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        Ok(RejoinResponse {
+            network_address: reader.read()?,
+            rejoin_status: reader.read()?,
+        })
+    }
+}
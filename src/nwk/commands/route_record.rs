@@ -0,0 +1,54 @@
+use crate::nwk::address::ShortAddress;
+use crate::nwk::constants::MAX_RELAY_COUNT;
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    SerdeErrorKind,
+    Writer,
+};
+
+/// 3.4.5 Route Record Command
+#[derive(Debug)]
+pub struct RouteRecord {
+    /// 3.4.5.3.2 Relay List Field, starting with the relay closest to the originator.
+    pub relay_list: heapless::Vec<ShortAddress, MAX_RELAY_COUNT>,
+}
+
+impl RouteRecord {
+    /// 3.4.5.3.1 Relay Count Field
+    pub fn relay_count(&self) -> u8 {
+        self.relay_list.len() as u8
+    }
+}
+
+impl Serde for RouteRecord {
+    fn encoded_len(&self) -> usize {
+        1 + self.relay_list.len() * 2
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(self.relay_count())?;
+        for address in self.relay_list.iter() {
+            writer.write(address)?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        let relay_count = reader.read_u8()?;
+        if relay_count as usize > MAX_RELAY_COUNT {
+            return Err(SerdeError::new(
+                SerdeErrorKind::CapacityExceeded { length: relay_count as usize, capacity: MAX_RELAY_COUNT },
+                offset,
+            ).context("relay_count"));
+        }
+        let relay_list = (0..relay_count)
+            .map(|_| reader.read())
+            .collect::<Result<_, _>>()?;
+        Ok(RouteRecord {
+            relay_list
+        })
+    }
+}
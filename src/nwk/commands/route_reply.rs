@@ -0,0 +1,149 @@
+use crate::nwk::address::{
+    AddressType,
+    IEEEAddress,
+    ShortAddress,
+};
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    Writer,
+};
+use zigbee_rs_derive::Bitfield;
+
+/// 3.4.2.3.1 Command Options Field
+#[derive(Debug, Bitfield)]
+pub struct CommandOptions {
+    #[bits(4)]
+    pub contains_originator_ieee_address: bool,
+    #[bits(5)]
+    pub contains_responder_ieee_address: bool,
+    #[bits(6)]
+    pub is_multicast: bool,
+}
+
+/// 3.4.2 Route Reply Command
+#[derive(Debug)]
+pub struct RouteReply {
+    pub command_options: CommandOptions,
+    pub route_request_identifier: u8,
+    pub originator_address: ShortAddress,
+    pub responder_address: AddressType,
+    pub path_cost: u8,
+    pub originator_ieee_address: Option<IEEEAddress>,
+    pub responder_ieee_address: Option<IEEEAddress>,
+}
+
+const MIN_NUM_BYTES: usize = 7;
+
+impl Serde for RouteReply {
+    fn encoded_len(&self) -> usize {
+        MIN_NUM_BYTES
+            + if self.originator_ieee_address.is_some() { 8 } else { 0 }
+            + if self.responder_ieee_address.is_some() { 8 } else { 0 }
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        let command_options = CommandOptions {
+            contains_originator_ieee_address: self.originator_ieee_address.is_some(),
+            contains_responder_ieee_address: self.responder_ieee_address.is_some(),
+            is_multicast: self.responder_address.is_multicast(),
+        };
+        writer.write(&command_options)?;
+        writer.write_u8(self.route_request_identifier)?;
+        writer.write(&self.originator_address)?;
+        self.responder_address.write(writer)?;
+        writer.write_u8(self.path_cost)?;
+        if let Some(address) = &self.originator_ieee_address {
+            writer.write(address)?;
+        }
+        if let Some(address) = &self.responder_ieee_address {
+            writer.write(address)?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let command_options: CommandOptions = reader.read()?;
+        let route_request_identifier = reader.read_u8()?;
+        let originator_address = reader.read()?;
+        let responder_address = AddressType::read(reader, command_options.is_multicast)?;
+        let path_cost = reader.read_u8()?;
+        let originator_ieee_address =
+            if command_options.contains_originator_ieee_address {
+                Some(reader.read()?)
+            } else {
+                None
+            };
+        let responder_ieee_address =
+            if command_options.contains_responder_ieee_address {
+                Some(reader.read()?)
+            } else {
+                None
+            };
+        Ok(RouteReply {
+            command_options,
+            route_request_identifier,
+            originator_address,
+            responder_address,
+            path_cost,
+            originator_ieee_address,
+            responder_ieee_address,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::address::GroupIdentifier;
+    use crate::serde::SerdeErrorKind;
+
+    fn route_reply(originator_ieee_address: Option<IEEEAddress>, responder_address: AddressType) -> RouteReply {
+        RouteReply {
+            command_options: CommandOptions {
+                contains_originator_ieee_address: false,
+                contains_responder_ieee_address: false,
+                is_multicast: false,
+            },
+            route_request_identifier: 0x2a,
+            originator_address: ShortAddress(0x0001),
+            responder_address,
+            path_cost: 5,
+            originator_ieee_address,
+            responder_ieee_address: None,
+        }
+    }
+
+    #[test]
+    fn command_options_follow_the_fields() {
+        let reply = route_reply(Some(IEEEAddress(0x0012_4b00_0102_0304)), AddressType::Multicast(GroupIdentifier(0x1234)));
+        let mut data = [0; 15];
+        assert_eq!(reply.serialize(&mut data).unwrap(), 15);
+        assert_eq!(data, [
+            0b0101_0000, 0x2a, 0x01, 0x00, 0x34, 0x12, 0x05,
+            0x04, 0x03, 0x02, 0x01, 0x00, 0x4b, 0x12, 0x00,
+        ]);
+
+        let decoded = RouteReply::deserialize(&data).unwrap();
+        assert!(decoded.command_options.contains_originator_ieee_address);
+        assert!(!decoded.command_options.contains_responder_ieee_address);
+        assert_eq!(decoded.responder_address, AddressType::Multicast(GroupIdentifier(0x1234)));
+        assert_eq!(decoded.originator_ieee_address, Some(IEEEAddress(0x0012_4b00_0102_0304)));
+        assert_eq!(decoded.responder_ieee_address, None);
+        assert_eq!(decoded.path_cost, 5);
+    }
+
+    #[test]
+    fn announced_ieee_address_has_to_be_present() {
+        let data = [0b0010_0000, 0x2a, 0x01, 0x00, 0x6f, 0x79, 0x05, 0x04, 0x03];
+        let error = RouteReply::deserialize(&data).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::UnexpectedEnd { expected: 8, actual: 2 });
+        assert_eq!(error.offset, 7);
+
+        // Without the flag the reply ends after the path cost.
+        let decoded = RouteReply::deserialize(&[0, 0x2a, 0x01, 0x00, 0x6f, 0x79, 0x05]).unwrap();
+        assert_eq!(decoded.responder_address, AddressType::Singlecast(ShortAddress(0x796f)));
+        assert_eq!(decoded.responder_ieee_address, None);
+    }
+}
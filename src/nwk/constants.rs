@@ -0,0 +1,20 @@
+//! Table 3.57 NWK Layer Constants
+
+/// nwkcProtocolVersion, the version of the ZigBee NWK protocol in the device.
+pub const PROTOCOL_VERSION: u8 = 0x02;
+
+/// Radius for unicasts across the network, twice the default nwkMaxDepth.
+pub const DEFAULT_RADIUS: u8 = 2 * 0x0f;
+
+/// nwkcRouteDiscoveryTime, the duration in milliseconds until a route discovery expires.
+pub const ROUTE_DISCOVERY_TIME_MS: u32 = 0x2710;
+
+/// aMaxPHYPacketSize, no NPDU can be longer than a PHY packet.
+pub const MAX_NPDU_LENGTH: usize = 127;
+
+/// Upper bound for the NWK payload, an NPDU without any optional header sections.
+pub const MAX_NSDU_LENGTH: usize = MAX_NPDU_LENGTH - 8;
+
+/// Upper bound for the relays of a source route or route record, which both need two
+/// bytes besides the relay list.
+pub const MAX_RELAY_COUNT: usize = (MAX_NSDU_LENGTH - 2) / 2;
@@ -0,0 +1,310 @@
+use crate::nwk::address::ShortAddress;
+use crate::nwk::commands::end_device_timeout_request::{
+    EndDeviceTimeoutRequest,
+    RequestedTimeout,
+};
+use crate::nwk::commands::end_device_timeout_response::{
+    EndDeviceTimeoutResponse,
+    ParentInformation,
+    TimeoutStatus,
+};
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::neighbor_table::NeighborTable;
+use crate::nwk::payload::NWKCommandFrame;
+use crate::state::DeviceType;
+
+/// Upon receipt of an end device timeout request command, on the parent.
+///
+/// Stores the requested timeout for the child, restarts its timer and returns the response
+/// to send back. A timeout out of range or a non-zero end device configuration is answered
+/// with INCORRECT_VALUE and leaves the child untouched. Requests from devices which are not
+/// our end device children are ignored.
+pub fn timeout_request_indication<const N: usize>(
+    neighbor_table: &mut NeighborTable<N>,
+    frame: &NPDUFrame,
+    request: &EndDeviceTimeoutRequest,
+    parent_information: ParentInformation,
+    own_address: ShortAddress,
+    now_ms: u64,
+    sequence_number: u8,
+) -> Option<NPDUFrame> {
+    let child_address = frame.source_address;
+    let child = neighbor_table.get_mut(&child_address)
+        .filter(|entry| entry.is_child() && entry.device_type == DeviceType::EndDevice)?;
+
+    let status = match request.timeout() {
+        Some(timeout) if request.end_device_configuration == 0 => {
+            child.end_device_timeout = timeout;
+            child.timeout_expiry_ms = Some(now_ms + timeout.as_millis());
+            TimeoutStatus::Success
+        },
+        _ => TimeoutStatus::IncorrectValue,
+    };
+
+    Some(command_frame(
+        child_address,
+        own_address,
+        1,
+        sequence_number,
+        NWKCommandFrame::EndDeviceTimeoutResponse(EndDeviceTimeoutResponse {
+            status,
+            parent_information,
+        }),
+    ))
+}
+
+/// Restarts the timeout of an end device child after it was heard from, for example
+/// through a MAC data poll. Returns `false` if the device is not our child.
+pub fn child_keepalive<const N: usize>(neighbor_table: &mut NeighborTable<N>, child_address: &ShortAddress, now_ms: u64) -> bool {
+    match neighbor_table.get_mut(child_address) {
+        Some(child) if child.is_child() => {
+            child.timeout_expiry_ms = Some(now_ms + child.end_device_timeout.as_millis());
+            true
+        },
+        _ => false,
+    }
+}
+
+/// Removes all end device children whose timeout expired and returns their addresses.
+///
+/// Children which are not tracked yet start their timeout now.
+pub fn age_children<const N: usize>(neighbor_table: &mut NeighborTable<N>, now_ms: u64) -> heapless::Vec<ShortAddress, N> {
+    let mut expired = heapless::Vec::new();
+    for child in neighbor_table.iter_mut()
+        .filter(|entry| entry.is_child() && entry.device_type == DeviceType::EndDevice)
+    {
+        match child.timeout_expiry_ms {
+            Some(expiry_ms) if expiry_ms <= now_ms => {
+                // There are at most N entries, so this always fits.
+                expired.push(child.network_address).ok();
+            },
+            Some(_) => (),
+            None => child.timeout_expiry_ms = Some(now_ms + child.end_device_timeout.as_millis()),
+        }
+    }
+    for address in expired.iter() {
+        neighbor_table.remove(address);
+    }
+    expired
+}
+
+/// How an end device keeps its parent entry alive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeepAlive {
+    /// The next higher layer has to issue a MAC data poll to the parent.
+    MacDataPoll,
+    /// An end device timeout request has to be sent to the parent, built by
+    /// `KeepAliveScheduler::timeout_request`.
+    TimeoutRequest,
+}
+
+/// Keeps the entry of an end device alive on its parent.
+///
+/// Keepalives are sent three times per timeout so a single lost one does not make the
+/// parent drop the child.
+pub struct KeepAliveScheduler {
+    pub timeout: RequestedTimeout,
+    /// What the parent announced in its end device timeout response.
+    pub parent_information: ParentInformation,
+    next_keepalive_ms: u64,
+}
+
+impl KeepAliveScheduler {
+    pub fn new(timeout: RequestedTimeout, now_ms: u64) -> Self {
+        Self {
+            timeout,
+            parent_information: ParentInformation::empty(),
+            next_keepalive_ms: now_ms,
+        }
+    }
+
+    fn interval_ms(&self) -> u64 {
+        self.timeout.as_millis() / 3
+    }
+
+    /// Builds the end device timeout request negotiating our timeout with the parent.
+    pub fn timeout_request(&self, parent_address: ShortAddress, own_address: ShortAddress, sequence_number: u8) -> NPDUFrame {
+        command_frame(
+            parent_address,
+            own_address,
+            1,
+            sequence_number,
+            NWKCommandFrame::EndDeviceTimeoutRequest(EndDeviceTimeoutRequest::new(self.timeout)),
+        )
+    }
+
+    /// Processes the end device timeout response of the parent. Returns whether the parent
+    /// accepted the timeout.
+    pub fn timeout_response_indication(&mut self, response: &EndDeviceTimeoutResponse, now_ms: u64) -> bool {
+        self.parent_information = response.parent_information;
+        if response.status == TimeoutStatus::Success {
+            self.next_keepalive_ms = now_ms + self.interval_ms();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the keepalive to send if one is due. MAC data polls are preferred if the
+    /// parent supports them.
+    pub fn poll(&mut self, now_ms: u64) -> Option<KeepAlive> {
+        if now_ms < self.next_keepalive_ms {
+            return None;
+        }
+        self.next_keepalive_ms = now_ms + self.interval_ms();
+
+        if self.parent_information.contains(ParentInformation::MacDataPollKeepalive) {
+            Some(KeepAlive::MacDataPoll)
+        } else {
+            Some(KeepAlive::TimeoutRequest)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::neighbor_table::{
+        NeighborTableEntry,
+        Relationship,
+    };
+    use crate::serde::Serde;
+
+    const PARENT_ADDRESS: ShortAddress = ShortAddress(0x0001);
+    const CHILD_ADDRESS: ShortAddress = ShortAddress(0x796f);
+
+    fn neighbor_table() -> NeighborTable<4> {
+        let mut neighbor_table = NeighborTable::new();
+        for entry in [
+            NeighborTableEntry::new(CHILD_ADDRESS, None, DeviceType::EndDevice, Relationship::Child),
+            NeighborTableEntry::new(ShortAddress(0x0200), None, DeviceType::Router, Relationship::Child),
+            NeighborTableEntry::new(ShortAddress(0x0300), None, DeviceType::EndDevice, Relationship::Sibling),
+        ] {
+            neighbor_table.insert(entry).unwrap();
+        }
+        neighbor_table
+    }
+
+    /// The request as received over the air, decoded from its bytes.
+    fn request(source_address: ShortAddress, requested_timeout: u8, end_device_configuration: u8) -> (NPDUFrame, EndDeviceTimeoutRequest) {
+        let frame = command_frame(
+            PARENT_ADDRESS,
+            source_address,
+            1,
+            1,
+            NWKCommandFrame::EndDeviceTimeoutRequest(EndDeviceTimeoutRequest {
+                requested_timeout,
+                end_device_configuration,
+            }),
+        );
+        let mut data = [0; 32];
+        let length = frame.serialize(&mut data).unwrap();
+        let frame = NPDUFrame::deserialize(&data[..length]).unwrap();
+        let request = EndDeviceTimeoutRequest { ..*frame.command::<EndDeviceTimeoutRequest>().unwrap() };
+        (frame, request)
+    }
+
+    fn indication<const N: usize>(
+        neighbor_table: &mut NeighborTable<N>,
+        request: (NPDUFrame, EndDeviceTimeoutRequest),
+        now_ms: u64,
+    ) -> Option<EndDeviceTimeoutResponse> {
+        let (frame, request) = request;
+        let parent_information = ParentInformation::MacDataPollKeepalive;
+        let response = timeout_request_indication(neighbor_table, &frame, &request, parent_information, PARENT_ADDRESS, now_ms, 2)?;
+        assert_eq!(response.destination_address, frame.source_address);
+        Some(EndDeviceTimeoutResponse { ..*response.command::<EndDeviceTimeoutResponse>().unwrap() })
+    }
+
+    #[test]
+    fn requested_timeout_is_stored() {
+        let mut neighbor_table = neighbor_table();
+        let response = indication(&mut neighbor_table, request(CHILD_ADDRESS, RequestedTimeout::Minutes2 as u8, 0), 1000).unwrap();
+        assert!(response.status == TimeoutStatus::Success);
+        assert!(response.parent_information == ParentInformation::MacDataPollKeepalive);
+        let child = neighbor_table.get(&CHILD_ADDRESS).unwrap();
+        assert!(child.end_device_timeout == RequestedTimeout::Minutes2);
+        assert_eq!(child.timeout_expiry_ms, Some(1000 + 2 * 60 * 1000));
+    }
+
+    #[test]
+    fn timeout_out_of_range_is_an_incorrect_value() {
+        let mut neighbor_table = neighbor_table();
+        let response = indication(&mut neighbor_table, request(CHILD_ADDRESS, 15, 0), 1000).unwrap();
+        assert!(response.status == TimeoutStatus::IncorrectValue);
+        let response = indication(&mut neighbor_table, request(CHILD_ADDRESS, 0xff, 0), 1000).unwrap();
+        assert!(response.status == TimeoutStatus::IncorrectValue);
+        let response = indication(&mut neighbor_table, request(CHILD_ADDRESS, 0, 1), 1000).unwrap();
+        assert!(response.status == TimeoutStatus::IncorrectValue);
+
+        let child = neighbor_table.get(&CHILD_ADDRESS).unwrap();
+        assert!(child.end_device_timeout == RequestedTimeout::Minutes256);
+        assert_eq!(child.timeout_expiry_ms, None);
+    }
+
+    #[test]
+    fn requests_of_other_devices_are_ignored() {
+        let mut neighbor_table = neighbor_table();
+        assert!(indication(&mut neighbor_table, request(ShortAddress(0x0200), 0, 0), 0).is_none());
+        assert!(indication(&mut neighbor_table, request(ShortAddress(0x0300), 0, 0), 0).is_none());
+        assert!(indication(&mut neighbor_table, request(ShortAddress(0x0400), 0, 0), 0).is_none());
+    }
+
+    #[test]
+    fn children_are_aged_out_unless_kept_alive() {
+        let mut neighbor_table = neighbor_table();
+        indication(&mut neighbor_table, request(CHILD_ADDRESS, RequestedTimeout::Seconds10 as u8, 0), 0).unwrap();
+
+        assert!(age_children(&mut neighbor_table, 9_999).is_empty());
+        assert!(child_keepalive(&mut neighbor_table, &CHILD_ADDRESS, 5_000));
+        assert!(age_children(&mut neighbor_table, 14_999).is_empty());
+        assert_eq!(age_children(&mut neighbor_table, 15_000), [CHILD_ADDRESS]);
+        assert!(neighbor_table.get(&CHILD_ADDRESS).is_none());
+        assert!(!child_keepalive(&mut neighbor_table, &CHILD_ADDRESS, 15_000));
+
+        // Neither routers nor devices which are not our children are aged.
+        assert!(age_children(&mut neighbor_table, u64::MAX).is_empty());
+        assert_eq!(neighbor_table.len(), 2);
+    }
+
+    #[test]
+    fn untracked_children_start_their_timeout_when_aged() {
+        let mut neighbor_table = neighbor_table();
+        assert!(age_children(&mut neighbor_table, 1000).is_empty());
+        let expiry_ms = 1000 + RequestedTimeout::Minutes256.as_millis();
+        assert_eq!(neighbor_table.get(&CHILD_ADDRESS).unwrap().timeout_expiry_ms, Some(expiry_ms));
+        assert!(age_children(&mut neighbor_table, expiry_ms - 1).is_empty());
+        assert_eq!(age_children(&mut neighbor_table, expiry_ms), [CHILD_ADDRESS]);
+    }
+
+    #[test]
+    fn keepalives_are_sent_three_times_per_timeout() {
+        let mut scheduler = KeepAliveScheduler::new(RequestedTimeout::Minutes2, 0);
+        assert_eq!(scheduler.poll(0), Some(KeepAlive::TimeoutRequest));
+        let request = scheduler.timeout_request(PARENT_ADDRESS, CHILD_ADDRESS, 1);
+        assert_eq!(request.destination_address, PARENT_ADDRESS);
+        assert_eq!(request.command::<EndDeviceTimeoutRequest>().unwrap().timeout(), Some(RequestedTimeout::Minutes2));
+
+        let response = EndDeviceTimeoutResponse {
+            status: TimeoutStatus::Success,
+            parent_information: ParentInformation::MacDataPollKeepalive,
+        };
+        assert!(scheduler.timeout_response_indication(&response, 100));
+        assert_eq!(scheduler.poll(40_099), None);
+        assert_eq!(scheduler.poll(40_100), Some(KeepAlive::MacDataPoll));
+        assert_eq!(scheduler.poll(80_099), None);
+        assert_eq!(scheduler.poll(80_100), Some(KeepAlive::MacDataPoll));
+    }
+
+    #[test]
+    fn refused_timeout_is_reported() {
+        let mut scheduler = KeepAliveScheduler::new(RequestedTimeout::Minutes2, 0);
+        let response = EndDeviceTimeoutResponse {
+            status: TimeoutStatus::IncorrectValue,
+            parent_information: ParentInformation::EndDeviceTimeoutRequestKeepalive,
+        };
+        assert!(!scheduler.timeout_response_indication(&response, 0));
+        assert_eq!(scheduler.poll(0), Some(KeepAlive::TimeoutRequest));
+    }
+}
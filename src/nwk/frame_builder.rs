@@ -0,0 +1,350 @@
+use crate::nwk::address::{
+    GroupIdentifier,
+    IEEEAddress,
+    ShortAddress,
+};
+use crate::nwk::constants::{
+    DEFAULT_RADIUS,
+    PROTOCOL_VERSION,
+};
+use crate::nwk::frame::{
+    DiscoverRoute,
+    FrameControl,
+    FrameTypeEnum,
+    MulticastControl,
+    NPDUFrame,
+    SourceRouteFrame,
+};
+use crate::nwk::payload::{
+    NWKCommandFrame,
+    Payload,
+};
+
+/// Largest radius which still fits into the nonmember radius sub-fields.
+const MAX_NONMEMBER_RADIUS: u8 = 0b111;
+
+#[derive(Debug)]
+pub enum FrameBuildError {
+    MissingDestinationAddress,
+    MissingSourceAddress,
+    /// The addresses 0xfff8 to 0xfffa and 0xfffe are reserved and address no device.
+    ReservedDestinationAddress,
+    /// Inter-PAN frames are not built by the NWK layer.
+    InterPanPayload,
+    /// A radius of zero would be dropped by the first relay.
+    ZeroRadius,
+    /// Multicasts are flooded or routed towards the group, never source routed.
+    MulticastWithSourceRoute,
+    /// Broadcasts are never source routed.
+    BroadcastWithSourceRoute,
+    /// The destination IEEE address is only allowed for unicasts.
+    DestinationIEEEAddressNotUnicast,
+    /// A nonmember radius does not fit into its three bit sub-field.
+    InvalidNonmemberRadius,
+}
+
+/// Builds an `NPDUFrame` and derives its frame control field.
+///
+/// The frame type follows the payload, the flags for the optional header sections are
+/// set from the sections that were added, and `build` rejects combinations which would
+/// produce a malformed frame.
+pub struct NPDUFrameBuilder {
+    payload: Payload,
+    discover_route: DiscoverRoute,
+    security_enabled: bool,
+    destination_address: Option<ShortAddress>,
+    source_address: Option<ShortAddress>,
+    radius: u8,
+    destination_ieee_address: Option<IEEEAddress>,
+    source_ieee_address: Option<IEEEAddress>,
+    multicast_control: Option<MulticastControl>,
+    source_route_frame: Option<SourceRouteFrame>,
+}
+
+impl NPDUFrameBuilder {
+    pub fn new(payload: Payload) -> Self {
+        Self {
+            payload,
+            discover_route: DiscoverRoute::SurpressDiscovery,
+            security_enabled: false,
+            destination_address: None,
+            source_address: None,
+            radius: DEFAULT_RADIUS,
+            destination_ieee_address: None,
+            source_ieee_address: None,
+            multicast_control: None,
+            source_route_frame: None,
+        }
+    }
+
+    /// Sets a unicast or broadcast destination.
+    pub fn destination(mut self, destination_address: ShortAddress) -> Self {
+        self.destination_address = Some(destination_address);
+        self.multicast_control = None;
+        self
+    }
+
+    /// Sets a group as destination, which makes the frame a multicast.
+    pub fn multicast(mut self, group_id: GroupIdentifier, multicast_control: MulticastControl) -> Self {
+        self.destination_address = Some(ShortAddress(group_id.0));
+        self.multicast_control = Some(multicast_control);
+        self
+    }
+
+    pub fn source(mut self, source_address: ShortAddress) -> Self {
+        self.source_address = Some(source_address);
+        self
+    }
+
+    /// Defaults to `DEFAULT_RADIUS`.
+    pub fn radius(mut self, radius: u8) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn destination_ieee_address(mut self, destination_ieee_address: IEEEAddress) -> Self {
+        self.destination_ieee_address = Some(destination_ieee_address);
+        self
+    }
+
+    pub fn source_ieee_address(mut self, source_ieee_address: IEEEAddress) -> Self {
+        self.source_ieee_address = Some(source_ieee_address);
+        self
+    }
+
+    pub fn source_route(mut self, source_route_frame: SourceRouteFrame) -> Self {
+        self.source_route_frame = Some(source_route_frame);
+        self
+    }
+
+    /// Defaults to `DiscoverRoute::SurpressDiscovery`.
+    pub fn discover_route(mut self, discover_route: DiscoverRoute) -> Self {
+        self.discover_route = discover_route;
+        self
+    }
+
+    pub fn security(mut self, security_enabled: bool) -> Self {
+        self.security_enabled = security_enabled;
+        self
+    }
+
+    fn validate(&self) -> Result<(ShortAddress, ShortAddress, FrameTypeEnum), FrameBuildError> {
+        let frame_type = match self.payload {
+            Payload::Data(_) => FrameTypeEnum::Data,
+            Payload::NWKCommand(_) => FrameTypeEnum::NWKCommand,
+            Payload::InterPan(_) => return Err(FrameBuildError::InterPanPayload),
+        };
+        let destination_address = self.destination_address.ok_or(FrameBuildError::MissingDestinationAddress)?;
+        let source_address = self.source_address.ok_or(FrameBuildError::MissingSourceAddress)?;
+        if self.radius == 0 {
+            return Err(FrameBuildError::ZeroRadius);
+        }
+
+        let is_broadcast = self.multicast_control.is_none() && destination_address.is_broadcast();
+        if self.multicast_control.is_none() && destination_address.is_reserved() {
+            return Err(FrameBuildError::ReservedDestinationAddress);
+        }
+        if let Some(multicast_control) = &self.multicast_control {
+            if multicast_control.nonmember_radius > MAX_NONMEMBER_RADIUS
+                || multicast_control.max_nonmember_radius > MAX_NONMEMBER_RADIUS
+            {
+                return Err(FrameBuildError::InvalidNonmemberRadius);
+            }
+            if self.source_route_frame.is_some() {
+                return Err(FrameBuildError::MulticastWithSourceRoute);
+            }
+        }
+        if is_broadcast && self.source_route_frame.is_some() {
+            return Err(FrameBuildError::BroadcastWithSourceRoute);
+        }
+        if (is_broadcast || self.multicast_control.is_some()) && self.destination_ieee_address.is_some() {
+            return Err(FrameBuildError::DestinationIEEEAddressNotUnicast);
+        }
+
+        Ok((destination_address, source_address, frame_type))
+    }
+
+    /// Builds the frame with the next sequence number, incrementing `sequence_number`.
+    pub fn build(self, sequence_number: &mut u8) -> Result<NPDUFrame, FrameBuildError> {
+        let (destination_address, source_address, frame_type) = self.validate()?;
+
+        let mut control = FrameControl::new(frame_type, PROTOCOL_VERSION, self.discover_route);
+        control.set_security_enabled(self.security_enabled);
+
+        let mut frame = NPDUFrame {
+            control,
+            destination_address,
+            source_address,
+            radius: self.radius,
+            sequence_number: *sequence_number,
+            destination_ieee_address: self.destination_ieee_address,
+            source_ieee_address: self.source_ieee_address,
+            multicast_control: self.multicast_control,
+            source_route_frame: self.source_route_frame,
+            payload: self.payload,
+        };
+        frame.update_control();
+        *sequence_number = sequence_number.wrapping_add(1);
+        Ok(frame)
+    }
+}
+
+/// Builds an NWK command frame generated by the NWK layer itself, without route discovery
+/// and optional header sections.
+///
+/// Unlike `NPDUFrameBuilder::build`, the sequence number is taken as is, so relayed
+/// commands keep the one of their originator. Optional sections added afterwards have to
+/// be announced with `NPDUFrame::update_control`.
+pub(crate) fn command_frame(
+    destination_address: ShortAddress,
+    source_address: ShortAddress,
+    radius: u8,
+    sequence_number: u8,
+    command: NWKCommandFrame,
+) -> NPDUFrame {
+    NPDUFrame {
+        control: FrameControl::new(FrameTypeEnum::NWKCommand, PROTOCOL_VERSION, DiscoverRoute::SurpressDiscovery),
+        destination_address,
+        source_address,
+        radius,
+        sequence_number,
+        destination_ieee_address: None,
+        source_ieee_address: None,
+        multicast_control: None,
+        source_route_frame: None,
+        payload: Payload::NWKCommand(command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::address::BROADCAST_ALL_DEVICES;
+    use crate::nwk::commands::leave::Leave;
+    use crate::nwk::frame::MulticastMode;
+    use crate::nwk::payload::DataFrame;
+
+    const GROUP: GroupIdentifier = GroupIdentifier(0x1234);
+
+    fn data() -> NPDUFrameBuilder {
+        NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::new() }))
+            .destination(ShortAddress(0x0002))
+            .source(ShortAddress(0x0001))
+    }
+
+    fn multicast_control(nonmember_radius: u8, max_nonmember_radius: u8) -> MulticastControl {
+        MulticastControl {
+            multicast_mode: MulticastMode::MemberMode,
+            nonmember_radius,
+            max_nonmember_radius,
+        }
+    }
+
+    fn source_route() -> SourceRouteFrame {
+        SourceRouteFrame::new([ShortAddress(0x0003)].iter().copied().collect()).unwrap()
+    }
+
+    fn build_error(builder: NPDUFrameBuilder) -> FrameBuildError {
+        let mut sequence_number = 7;
+        let error = builder.build(&mut sequence_number).unwrap_err();
+        // Rejected frames do not use up a sequence number.
+        assert_eq!(sequence_number, 7);
+        error
+    }
+
+    #[test]
+    fn frame_control_follows_the_frame() {
+        let mut sequence_number = 0xff;
+        let frame = data()
+            .destination_ieee_address(IEEEAddress(2))
+            .source_ieee_address(IEEEAddress(1))
+            .source_route(source_route())
+            .discover_route(DiscoverRoute::EnableDiscovery)
+            .security(true)
+            .build(&mut sequence_number)
+            .unwrap();
+        assert_eq!(frame.sequence_number, 0xff);
+        assert_eq!(sequence_number, 0);
+        assert_eq!(frame.radius, DEFAULT_RADIUS);
+
+        let control = frame.control;
+        assert!(matches!(control.frame_type, FrameTypeEnum::Data));
+        assert_eq!(control.protocol_version, PROTOCOL_VERSION);
+        assert!(matches!(control.discover_route, DiscoverRoute::EnableDiscovery));
+        assert!(control.security_enabled());
+        assert!(control.contains_source_route_frame());
+        assert!(control.contains_destination_ieee_address());
+        assert!(control.contains_source_ieee_address());
+        assert!(!control.is_multicast());
+    }
+
+    #[test]
+    fn multicast_sets_the_group_as_destination() {
+        let command = Payload::NWKCommand(NWKCommandFrame::Leave(Leave { rejoin: false, request: false, remove_children: false }));
+        let frame = NPDUFrameBuilder::new(command)
+            .multicast(GROUP, multicast_control(1, 2))
+            .source(ShortAddress(0x0001))
+            .radius(3)
+            .build(&mut 0)
+            .unwrap();
+        assert_eq!(frame.destination_address, ShortAddress(GROUP.0));
+        assert_eq!(frame.radius, 3);
+        assert!(matches!(frame.control.frame_type, FrameTypeEnum::NWKCommand));
+        assert!(frame.control.is_multicast());
+        assert!(!frame.control.contains_source_route_frame());
+
+        // A later unicast destination replaces the group.
+        let frame = data().multicast(GROUP, multicast_control(1, 2)).destination(ShortAddress(0x0002)).build(&mut 0).unwrap();
+        assert!(frame.multicast_control.is_none());
+        assert!(!frame.control.is_multicast());
+    }
+
+    #[test]
+    fn incomplete_frames_are_rejected() {
+        let builder = NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::new() }));
+        assert!(matches!(build_error(builder.source(ShortAddress(0x0001))), FrameBuildError::MissingDestinationAddress));
+        let builder = NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::new() }));
+        assert!(matches!(build_error(builder.destination(ShortAddress(0x0002))), FrameBuildError::MissingSourceAddress));
+        let builder = NPDUFrameBuilder::new(Payload::InterPan(DataFrame { data: heapless::Vec::new() }))
+            .destination(ShortAddress(0x0002))
+            .source(ShortAddress(0x0001));
+        assert!(matches!(build_error(builder), FrameBuildError::InterPanPayload));
+        assert!(matches!(build_error(data().radius(0)), FrameBuildError::ZeroRadius));
+    }
+
+    #[test]
+    fn reserved_destinations_are_rejected() {
+        for address in [0xfff8, 0xfff9, 0xfffa, 0xfffe] {
+            let builder = data().destination(ShortAddress(address));
+            assert!(matches!(build_error(builder), FrameBuildError::ReservedDestinationAddress));
+        }
+        // Groups use the whole address space.
+        assert!(data().multicast(GroupIdentifier(0xfffe), multicast_control(1, 2)).build(&mut 0).is_ok());
+        assert!(data().destination(ShortAddress(0xfff7)).build(&mut 0).is_ok());
+    }
+
+    #[test]
+    fn source_routes_are_only_allowed_for_unicasts() {
+        let builder = data().multicast(GROUP, multicast_control(1, 2)).source_route(source_route());
+        assert!(matches!(build_error(builder), FrameBuildError::MulticastWithSourceRoute));
+        let builder = data().destination(BROADCAST_ALL_DEVICES).source_route(source_route());
+        assert!(matches!(build_error(builder), FrameBuildError::BroadcastWithSourceRoute));
+    }
+
+    #[test]
+    fn destination_ieee_address_is_only_allowed_for_unicasts() {
+        let builder = data().destination(BROADCAST_ALL_DEVICES).destination_ieee_address(IEEEAddress(2));
+        assert!(matches!(build_error(builder), FrameBuildError::DestinationIEEEAddressNotUnicast));
+        let builder = data().multicast(GROUP, multicast_control(1, 2)).destination_ieee_address(IEEEAddress(2));
+        assert!(matches!(build_error(builder), FrameBuildError::DestinationIEEEAddressNotUnicast));
+    }
+
+    #[test]
+    fn nonmember_radius_has_to_fit_its_sub_field() {
+        assert!(data().multicast(GROUP, multicast_control(7, 7)).build(&mut 0).is_ok());
+        let builder = data().multicast(GROUP, multicast_control(8, 2));
+        assert!(matches!(build_error(builder), FrameBuildError::InvalidNonmemberRadius));
+        let builder = data().multicast(GROUP, multicast_control(1, 8));
+        assert!(matches!(build_error(builder), FrameBuildError::InvalidNonmemberRadius));
+    }
+}
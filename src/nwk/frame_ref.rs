@@ -0,0 +1,433 @@
+use crate::nwk::address::{
+    IEEEAddress,
+    ShortAddress,
+};
+use crate::nwk::frame::{
+    FrameControl,
+    MulticastControl,
+    NPDUFrame,
+    SerdeError,
+};
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeErrorKind,
+};
+
+const DESTINATION_ADDRESS: usize = 2;
+const SOURCE_ADDRESS: usize = 4;
+const RADIUS: usize = 6;
+const SEQUENCE_NUMBER: usize = 7;
+const MIN_NUM_BYTES: usize = 8;
+
+/// Offsets of the optional header sections, found by walking the frame control flags once.
+#[derive(Copy, Clone, Debug)]
+struct Layout {
+    control: FrameControl,
+    destination_ieee_address: Option<usize>,
+    source_ieee_address: Option<usize>,
+    multicast_control: Option<usize>,
+    source_route_frame: Option<usize>,
+    payload: usize,
+}
+
+impl Layout {
+    fn parse(data: &[u8]) -> Result<Self, SerdeError> {
+        let mut reader = Reader::new(data);
+        let control: FrameControl = reader.read()?;
+        reader.read_bytes(MIN_NUM_BYTES - 2)?;
+
+        let mut section = |present: bool, length: usize| -> Result<Option<usize>, SerdeError> {
+            if present {
+                let start = reader.position();
+                reader.read_bytes(length)?;
+                Ok(Some(start))
+            } else {
+                Ok(None)
+            }
+        };
+        let destination_ieee_address = section(control.contains_destination_ieee_address(), 8)?;
+        let source_ieee_address = section(control.contains_source_ieee_address(), 8)?;
+        // Decoded here already, so that a reserved multicast mode rejects the frame.
+        let multicast_control = if control.is_multicast() {
+            let start = reader.position();
+            reader.read::<MulticastControl>()?;
+            Some(start)
+        } else {
+            None
+        };
+
+        let source_route_frame = if control.contains_source_route_frame() {
+            let start = reader.position();
+            let relay_count = reader.read_u8()?;
+            let relay_index = reader.read_u8()?;
+            if relay_count == 0 || relay_index >= relay_count {
+                return Err(SerdeError::new(SerdeErrorKind::BrokenRelayList { relay_count, relay_index }, start));
+            }
+            reader.read_bytes(relay_count as usize * 2)?;
+            Some(start)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            control,
+            destination_ieee_address,
+            source_ieee_address,
+            multicast_control,
+            source_route_frame,
+            payload: reader.position(),
+        })
+    }
+}
+
+fn address_at(data: &[u8], offset: usize) -> ShortAddress {
+    ShortAddress::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn ieee_address_at(data: &[u8], offset: usize) -> IEEEAddress {
+    let mut address = [0; 8];
+    address.clone_from_slice(&data[offset..offset + 8]);
+    IEEEAddress::from_le_bytes(address)
+}
+
+/// Borrowed view of a received NPDU
+///
+/// Checks the header once and reads fields directly from the buffer, so forwarding does
+/// not have to deserialize and serialize the whole frame.
+#[derive(Copy, Clone, Debug)]
+pub struct NpduRef<'a> {
+    data: &'a [u8],
+    layout: Layout,
+}
+
+impl<'a> NpduRef<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, SerdeError> {
+        Ok(Self {
+            layout: Layout::parse(data)?,
+            data,
+        })
+    }
+
+    /// The complete frame.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn control(&self) -> FrameControl {
+        self.layout.control
+    }
+
+    pub fn destination_address(&self) -> ShortAddress {
+        address_at(self.data, DESTINATION_ADDRESS)
+    }
+
+    pub fn source_address(&self) -> ShortAddress {
+        address_at(self.data, SOURCE_ADDRESS)
+    }
+
+    pub fn radius(&self) -> u8 {
+        self.data[RADIUS]
+    }
+
+    pub fn sequence_number(&self) -> u8 {
+        self.data[SEQUENCE_NUMBER]
+    }
+
+    pub fn destination_ieee_address(&self) -> Option<IEEEAddress> {
+        self.layout.destination_ieee_address.map(|offset| ieee_address_at(self.data, offset))
+    }
+
+    pub fn source_ieee_address(&self) -> Option<IEEEAddress> {
+        self.layout.source_ieee_address.map(|offset| ieee_address_at(self.data, offset))
+    }
+
+    /// 3.3.1.8 Multicast Control Field, validated by `NpduRef::new`.
+    pub fn multicast_control(&self) -> Option<MulticastControl> {
+        let offset = self.layout.multicast_control?;
+        MulticastControl::deserialize(&self.data[offset..offset + 1]).ok()
+    }
+
+    /// 3.3.1.9.1 Relay Count Sub-Field
+    pub fn relay_count(&self) -> Option<u8> {
+        self.layout.source_route_frame.map(|offset| self.data[offset])
+    }
+
+    /// 3.3.1.9.2 Relay Index Sub-Field
+    pub fn relay_index(&self) -> Option<u8> {
+        self.layout.source_route_frame.map(|offset| self.data[offset + 1])
+    }
+
+    /// The relay at `index` of the relay list, which starts with the relay closest to the
+    /// destination.
+    pub fn relay(&self, index: u8) -> Option<ShortAddress> {
+        let offset = self.layout.source_route_frame?;
+        if index < self.data[offset] {
+            Some(address_at(self.data, offset + 2 + index as usize * 2))
+        } else {
+            None
+        }
+    }
+
+    pub fn relays(&self) -> impl Iterator<Item = ShortAddress> + 'a {
+        let relay_list = match self.layout.source_route_frame {
+            Some(offset) => &self.data[offset + 2..self.layout.payload],
+            None => &[],
+        };
+        relay_list.chunks(2).map(|chunk| ShortAddress::from_le_bytes([chunk[0], chunk[1]]))
+    }
+
+    /// The NWK payload following the header.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.data[self.layout.payload..]
+    }
+
+    pub fn header_length(&self) -> usize {
+        self.layout.payload
+    }
+
+    /// Deserializes the complete frame, including its payload.
+    pub fn to_frame(&self) -> Result<NPDUFrame, SerdeError> {
+        NPDUFrame::deserialize(self.data)
+    }
+}
+
+/// Mutable view of a received NPDU for in-place forwarding.
+#[derive(Debug)]
+pub struct NpduMut<'a> {
+    data: &'a mut [u8],
+    layout: Layout,
+}
+
+impl<'a> NpduMut<'a> {
+    pub fn new(data: &'a mut [u8]) -> Result<Self, SerdeError> {
+        Ok(Self {
+            layout: Layout::parse(data)?,
+            data,
+        })
+    }
+
+    /// Read access to all header fields.
+    pub fn view(&self) -> NpduRef<'_> {
+        NpduRef {
+            data: self.data,
+            layout: self.layout,
+        }
+    }
+
+    pub fn set_destination_address(&mut self, address: ShortAddress) {
+        self.data[DESTINATION_ADDRESS..DESTINATION_ADDRESS + 2].clone_from_slice(&address.to_le_bytes());
+    }
+
+    pub fn set_source_address(&mut self, address: ShortAddress) {
+        self.data[SOURCE_ADDRESS..SOURCE_ADDRESS + 2].clone_from_slice(&address.to_le_bytes());
+    }
+
+    pub fn set_radius(&mut self, radius: u8) {
+        self.data[RADIUS] = radius;
+    }
+
+    /// Decrements the radius, returns `false` if it was already used up.
+    pub fn decrement_radius(&mut self) -> bool {
+        match self.data[RADIUS] {
+            0 => false,
+            radius => {
+                self.data[RADIUS] = radius - 1;
+                true
+            },
+        }
+    }
+
+    /// Sets the relay index of the source route, fails if the frame is not source routed or
+    /// the index is outside of the relay list.
+    pub fn set_relay_index(&mut self, relay_index: u8) -> Result<(), SerdeError> {
+        let offset = self.layout.source_route_frame
+            .ok_or_else(|| SerdeError::new(SerdeErrorKind::MissingSection, self.layout.payload))?;
+        let relay_count = self.data[offset];
+        if relay_index >= relay_count {
+            return Err(SerdeError::new(SerdeErrorKind::BrokenRelayList { relay_count, relay_index }, offset));
+        }
+        self.data[offset + 1] = relay_index;
+        Ok(())
+    }
+
+    /// Sets the multicast control field, fails if the frame is not a multicast.
+    pub fn set_multicast_control(&mut self, multicast_control: &MulticastControl) -> Result<(), SerdeError> {
+        let offset = self.layout.multicast_control
+            .ok_or_else(|| SerdeError::new(SerdeErrorKind::MissingSection, self.layout.payload))?;
+        multicast_control.serialize(&mut self.data[offset..offset + 1])?;
+        Ok(())
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.data[self.layout.payload..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::address::GroupIdentifier;
+    use crate::nwk::constants::MAX_NPDU_LENGTH;
+    use crate::nwk::frame::{
+        MulticastMode,
+        SourceRouteFrame,
+    };
+    use crate::nwk::frame_builder::NPDUFrameBuilder;
+    use crate::nwk::payload::{
+        DataFrame,
+        Payload,
+    };
+
+    const RELAYS: [ShortAddress; 3] = [ShortAddress(0x0003), ShortAddress(0x0004), ShortAddress(0x0005)];
+
+    fn encode(frame: &NPDUFrame) -> heapless::Vec<u8, MAX_NPDU_LENGTH> {
+        let mut data = [0; MAX_NPDU_LENGTH];
+        let length = frame.serialize(&mut data).unwrap();
+        data[..length].iter().copied().collect()
+    }
+
+    fn payload() -> Payload {
+        Payload::Data(DataFrame { data: [0xde, 0xad, 0xbe, 0xef].iter().copied().collect() })
+    }
+
+    /// A source routed unicast carrying both IEEE addresses.
+    fn source_routed() -> heapless::Vec<u8, MAX_NPDU_LENGTH> {
+        let frame = NPDUFrameBuilder::new(payload())
+            .destination(ShortAddress(0x0002))
+            .source(ShortAddress(0x0001))
+            .radius(10)
+            .destination_ieee_address(IEEEAddress(0x0012_4b00_0000_0002))
+            .source_ieee_address(IEEEAddress(0x0012_4b00_0000_0001))
+            .source_route(SourceRouteFrame::new(RELAYS.iter().copied().collect()).unwrap())
+            .build(&mut 0x42)
+            .unwrap();
+        encode(&frame)
+    }
+
+    fn multicast_control(nonmember_radius: u8) -> MulticastControl {
+        MulticastControl {
+            multicast_mode: MulticastMode::MemberMode,
+            nonmember_radius,
+            max_nonmember_radius: 3,
+        }
+    }
+
+    fn multicast() -> heapless::Vec<u8, MAX_NPDU_LENGTH> {
+        let frame = NPDUFrameBuilder::new(payload())
+            .multicast(GroupIdentifier(0x1234), multicast_control(2))
+            .source(ShortAddress(0x0001))
+            .build(&mut 0)
+            .unwrap();
+        encode(&frame)
+    }
+
+    #[test]
+    fn view_matches_the_decoded_frame() {
+        let data = source_routed();
+        let view = NpduRef::new(&data).unwrap();
+        let frame = view.to_frame().unwrap();
+
+        assert_eq!(view.destination_address(), frame.destination_address);
+        assert_eq!(view.source_address(), frame.source_address);
+        assert_eq!(view.radius(), 10);
+        assert_eq!(view.sequence_number(), 0x42);
+        assert_eq!(view.destination_ieee_address(), frame.destination_ieee_address);
+        assert_eq!(view.source_ieee_address(), frame.source_ieee_address);
+        assert!(view.multicast_control().is_none());
+
+        let source_route_frame = frame.source_route_frame.as_ref().unwrap();
+        assert_eq!(view.relay_count(), Some(3));
+        assert_eq!(view.relay_index(), Some(source_route_frame.get_index()));
+        assert!(view.relays().eq(source_route_frame.relay_list().iter().copied()));
+        assert_eq!(view.relay(2), Some(ShortAddress(0x0005)));
+        assert_eq!(view.relay(3), None);
+        assert_eq!(view.payload(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(view.header_length(), data.len() - 4);
+    }
+
+    #[test]
+    fn in_place_edits_match_a_re_encode() {
+        let mut data = source_routed();
+        let mut frame = NpduRef::new(&data).unwrap().to_frame().unwrap();
+
+        let mut npdu = NpduMut::new(&mut data).unwrap();
+        npdu.set_destination_address(ShortAddress(0x0006));
+        npdu.set_source_address(ShortAddress(0x0007));
+        assert!(npdu.decrement_radius());
+        npdu.set_relay_index(1).unwrap();
+        npdu.payload_mut()[0] = 0xca;
+
+        frame.destination_address = ShortAddress(0x0006);
+        frame.source_address = ShortAddress(0x0007);
+        frame.radius -= 1;
+        frame.source_route_frame.as_mut().unwrap().decrement_index();
+        frame.payload = Payload::Data(DataFrame { data: [0xca, 0xad, 0xbe, 0xef].iter().copied().collect() });
+        assert_eq!(data, encode(&frame));
+    }
+
+    #[test]
+    fn multicast_control_is_edited_in_place() {
+        let mut data = multicast();
+        let mut frame = NpduRef::new(&data).unwrap().to_frame().unwrap();
+
+        let mut npdu = NpduMut::new(&mut data).unwrap();
+        assert_eq!(npdu.view().multicast_control().unwrap().nonmember_radius, 2);
+        npdu.set_multicast_control(&multicast_control(1)).unwrap();
+        npdu.set_radius(3);
+
+        frame.multicast_control = Some(multicast_control(1));
+        frame.radius = 3;
+        assert_eq!(data, encode(&frame));
+    }
+
+    #[test]
+    fn edits_of_missing_sections_fail() {
+        let mut data = multicast();
+        let mut npdu = NpduMut::new(&mut data).unwrap();
+        let error = npdu.set_relay_index(0).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::MissingSection);
+
+        let mut data = source_routed();
+        let mut npdu = NpduMut::new(&mut data).unwrap();
+        let error = npdu.set_multicast_control(&multicast_control(1)).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::MissingSection);
+        let error = npdu.set_relay_index(3).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::BrokenRelayList { relay_count: 3, relay_index: 3 });
+
+        npdu.set_radius(0);
+        assert!(!npdu.decrement_radius());
+        assert_eq!(npdu.view().radius(), 0);
+    }
+
+    #[test]
+    fn reserved_multicast_mode_is_rejected() {
+        let mut data = multicast();
+        data[MIN_NUM_BYTES] = (data[MIN_NUM_BYTES] & !0b11) | 0b10;
+        let error = NpduRef::new(&data).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 0b10 });
+        assert_eq!(error.offset, MIN_NUM_BYTES);
+        assert_eq!(error.path().collect::<heapless::Vec<_, 4>>(), ["MulticastControl", "multicast_mode"]);
+        assert!(NpduMut::new(&mut data).is_err());
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        let data = source_routed();
+        let relay_count_offset = NpduRef::new(&data).unwrap().header_length() - 2 - RELAYS.len() * 2;
+
+        // Truncated inside the relay list.
+        assert!(matches!(
+            NpduRef::new(&data[..relay_count_offset + 3]).unwrap_err().kind,
+            SerdeErrorKind::UnexpectedEnd { .. }
+        ));
+
+        let mut broken = data.clone();
+        broken[relay_count_offset + 1] = 3;
+        let error = NpduRef::new(&broken).unwrap_err();
+        assert_eq!(error.kind, SerdeErrorKind::BrokenRelayList { relay_count: 3, relay_index: 3 });
+        assert_eq!(error.offset, relay_count_offset);
+
+        assert!(NpduRef::new(&data[..MIN_NUM_BYTES - 1]).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use crate::nwk::address::{
+    ShortAddress,
+    BROADCAST_RX_ON_WHEN_IDLE,
+};
+use crate::nwk::commands::link_power_delta::{
+    LinkPowerDelta,
+    PowerDeltaEntry,
+    PowerDeltaType,
+    MAX_ENTRY_COUNT,
+};
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::neighbor_table::{
+    NeighborTable,
+    NeighborTableEntry,
+};
+use crate::nwk::payload::NWKCommandFrame;
+
+/// Hook into the radio to transmit with a different power per neighbor.
+pub trait TransmitPower {
+    /// Sets the change of transmit power in dBm, relative to the default transmit power,
+    /// for all frames sent to `neighbor`.
+    fn set_transmit_power_delta(&mut self, neighbor: ShortAddress, power_delta: i8);
+}
+
+/// The change of transmit power a neighbor has to apply so its frames reach us with
+/// `target_rssi`.
+pub fn requested_power_delta(rssi: i8, target_rssi: i8) -> i8 {
+    (i16::from(target_rssi) - i16::from(rssi)).clamp(i16::from(i8::MIN), i16::from(i8::MAX)) as i8
+}
+
+fn power_delta_entry(entry: &NeighborTableEntry, target_rssi: i8) -> Option<PowerDeltaEntry> {
+    entry.rssi.map(|rssi| PowerDeltaEntry {
+        device_address: entry.network_address,
+        power_delta: requested_power_delta(rssi, target_rssi),
+    })
+}
+
+/// Builds the link power delta notification routers periodically broadcast to tell all
+/// neighbors we heard how to adjust their transmit power.
+pub fn link_power_delta_notification<const N: usize>(
+    neighbor_table: &NeighborTable<N>,
+    target_rssi: i8,
+    own_address: ShortAddress,
+    sequence_number: u8,
+) -> NPDUFrame {
+    command_frame(
+        BROADCAST_RX_ON_WHEN_IDLE,
+        own_address,
+        1,
+        sequence_number,
+        NWKCommandFrame::LinkPowerDelta(LinkPowerDelta {
+            power_delta_type: PowerDeltaType::Notification,
+            entries: neighbor_table.iter()
+                .filter_map(|entry| power_delta_entry(entry, target_rssi))
+                .take(MAX_ENTRY_COUNT)
+                .collect(),
+        }),
+    )
+}
+
+/// Builds the link power delta request an end device sends to its parent. It carries the
+/// power delta for the parent, if the end device heard it already.
+pub fn link_power_delta_request<const N: usize>(
+    neighbor_table: &NeighborTable<N>,
+    target_rssi: i8,
+    own_address: ShortAddress,
+    sequence_number: u8,
+) -> Option<NPDUFrame> {
+    let parent = neighbor_table.parent()?;
+    Some(command_frame(
+        parent.network_address,
+        own_address,
+        1,
+        sequence_number,
+        NWKCommandFrame::LinkPowerDelta(LinkPowerDelta {
+            power_delta_type: PowerDeltaType::Request,
+            entries: power_delta_entry(parent, target_rssi).into_iter().collect(),
+        }),
+    ))
+}
+
+/// Upon receipt of a link power delta command.
+///
+/// Applies the power delta the sender lists for us to the radio. A request is answered with
+/// a response which tells the sender how to adjust its own transmit power.
+pub fn link_power_delta_indication<R: TransmitPower, const N: usize>(
+    neighbor_table: &mut NeighborTable<N>,
+    radio: &mut R,
+    frame: &NPDUFrame,
+    command: &LinkPowerDelta,
+    own_address: ShortAddress,
+    target_rssi: i8,
+    sequence_number: u8,
+) -> Option<NPDUFrame> {
+    let source_address = frame.source_address;
+    let neighbor = neighbor_table.get_mut(&source_address)?;
+
+    if let Some(entry) = command.entries.iter().find(|entry| entry.device_address == own_address) {
+        neighbor.transmit_power_delta = neighbor.transmit_power_delta.saturating_add(entry.power_delta);
+        radio.set_transmit_power_delta(source_address, neighbor.transmit_power_delta);
+    }
+
+    if command.power_delta_type == PowerDeltaType::Request {
+        Some(command_frame(
+            source_address,
+            own_address,
+            1,
+            sequence_number,
+            NWKCommandFrame::LinkPowerDelta(LinkPowerDelta {
+                power_delta_type: PowerDeltaType::Response,
+                entries: power_delta_entry(neighbor, target_rssi).into_iter().collect(),
+            }),
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::neighbor_table::Relationship;
+    use crate::state::DeviceType;
+
+    const OWN_ADDRESS: ShortAddress = ShortAddress(0x0100);
+    const TARGET_RSSI: i8 = -60;
+
+    #[derive(Default)]
+    struct Radio {
+        power_deltas: heapless::Vec<(ShortAddress, i8), 4>,
+    }
+
+    impl TransmitPower for Radio {
+        fn set_transmit_power_delta(&mut self, neighbor: ShortAddress, power_delta: i8) {
+            self.power_deltas.push((neighbor, power_delta)).unwrap();
+        }
+    }
+
+    fn neighbor(network_address: u16, relationship: Relationship, rssi: Option<i8>) -> NeighborTableEntry {
+        let mut entry = NeighborTableEntry::new(ShortAddress(network_address), None, DeviceType::Router, relationship);
+        entry.rssi = rssi;
+        entry
+    }
+
+    fn command(source_address: ShortAddress, power_delta_type: PowerDeltaType, entries: &[(ShortAddress, i8)]) -> NPDUFrame {
+        command_frame(
+            OWN_ADDRESS,
+            source_address,
+            1,
+            1,
+            NWKCommandFrame::LinkPowerDelta(LinkPowerDelta {
+                power_delta_type,
+                entries: entries.iter()
+                    .map(|&(device_address, power_delta)| PowerDeltaEntry { device_address, power_delta })
+                    .collect(),
+            }),
+        )
+    }
+
+    #[test]
+    fn power_delta_saturates() {
+        assert_eq!(requested_power_delta(-80, TARGET_RSSI), 20);
+        assert_eq!(requested_power_delta(-40, TARGET_RSSI), -20);
+        assert_eq!(requested_power_delta(i8::MIN, i8::MAX), i8::MAX);
+        assert_eq!(requested_power_delta(i8::MAX, i8::MIN), i8::MIN);
+    }
+
+    #[test]
+    fn notification_lists_neighbors_we_heard() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        for entry in [
+            neighbor(0x0001, Relationship::Sibling, Some(-70)),
+            neighbor(0x0002, Relationship::Sibling, None),
+            neighbor(0x0003, Relationship::Child, Some(-50)),
+        ] {
+            neighbor_table.insert(entry).unwrap();
+        }
+
+        let frame = link_power_delta_notification(&neighbor_table, TARGET_RSSI, OWN_ADDRESS, 7);
+        assert_eq!(frame.destination_address, BROADCAST_RX_ON_WHEN_IDLE);
+        assert_eq!(frame.source_address, OWN_ADDRESS);
+        assert_eq!(frame.radius, 1);
+        assert_eq!(frame.sequence_number, 7);
+        let command = frame.command::<LinkPowerDelta>().unwrap();
+        assert!(command.power_delta_type == PowerDeltaType::Notification);
+        let entries: heapless::Vec<_, 4> = command.entries.iter()
+            .map(|entry| (entry.device_address, entry.power_delta))
+            .collect();
+        assert_eq!(entries, [(ShortAddress(0x0001), 10), (ShortAddress(0x0003), -10)]);
+    }
+
+    #[test]
+    fn request_is_sent_to_the_parent() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        assert!(link_power_delta_request(&neighbor_table, TARGET_RSSI, OWN_ADDRESS, 1).is_none());
+
+        neighbor_table.insert(neighbor(0x0001, Relationship::Parent, Some(-66))).unwrap();
+        let frame = link_power_delta_request(&neighbor_table, TARGET_RSSI, OWN_ADDRESS, 1).unwrap();
+        assert_eq!(frame.destination_address, ShortAddress(0x0001));
+        let command = frame.command::<LinkPowerDelta>().unwrap();
+        assert!(command.power_delta_type == PowerDeltaType::Request);
+        assert_eq!(command.entries.len(), 1);
+        assert_eq!(command.entries[0].device_address, ShortAddress(0x0001));
+        assert_eq!(command.entries[0].power_delta, 6);
+    }
+
+    #[test]
+    fn request_is_applied_and_answered() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table.insert(neighbor(0x0300, Relationship::Child, Some(-64))).unwrap();
+        let mut radio = Radio::default();
+        let entries = [(ShortAddress(0x0002), 9), (OWN_ADDRESS, -3)];
+        let frame = command(ShortAddress(0x0300), PowerDeltaType::Request, &entries);
+
+        let response = link_power_delta_indication(
+            &mut neighbor_table, &mut radio, &frame, frame.command::<LinkPowerDelta>().unwrap(), OWN_ADDRESS, TARGET_RSSI, 2,
+        ).unwrap();
+        assert_eq!(response.destination_address, ShortAddress(0x0300));
+        assert_eq!(response.sequence_number, 2);
+        let command = response.command::<LinkPowerDelta>().unwrap();
+        assert!(command.power_delta_type == PowerDeltaType::Response);
+        assert_eq!(command.entries.len(), 1);
+        assert_eq!(command.entries[0].device_address, ShortAddress(0x0300));
+        assert_eq!(command.entries[0].power_delta, 4);
+
+        // Deltas accumulate and only the entry addressed to us is applied.
+        link_power_delta_indication(
+            &mut neighbor_table, &mut radio, &frame, frame.command::<LinkPowerDelta>().unwrap(), OWN_ADDRESS, TARGET_RSSI, 3,
+        ).unwrap();
+        assert_eq!(radio.power_deltas, [(ShortAddress(0x0300), -3), (ShortAddress(0x0300), -6)]);
+        assert_eq!(neighbor_table.get(&ShortAddress(0x0300)).unwrap().transmit_power_delta, -6);
+    }
+
+    #[test]
+    fn notification_is_not_answered() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table.insert(neighbor(0x0002, Relationship::Sibling, Some(-64))).unwrap();
+        let mut radio = Radio::default();
+
+        let frame = command(ShortAddress(0x0002), PowerDeltaType::Notification, &[]);
+        assert!(link_power_delta_indication(
+            &mut neighbor_table, &mut radio, &frame, frame.command::<LinkPowerDelta>().unwrap(), OWN_ADDRESS, TARGET_RSSI, 1,
+        ).is_none());
+        assert!(radio.power_deltas.is_empty());
+
+        // Nor are commands from devices which are not our neighbors.
+        let entries = [(OWN_ADDRESS, 5)];
+        let frame = command(ShortAddress(0x0004), PowerDeltaType::Request, &entries);
+        assert!(link_power_delta_indication(
+            &mut neighbor_table, &mut radio, &frame, frame.command::<LinkPowerDelta>().unwrap(), OWN_ADDRESS, TARGET_RSSI, 1,
+        ).is_none());
+        assert!(radio.power_deltas.is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+use crate::nwk::address::{
+    ShortAddress,
+    BROADCAST_ROUTERS_AND_COORDINATOR,
+};
+use crate::nwk::commands::link_status::{
+    LinkStatus,
+    LinkStatusEntry,
+};
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::neighbor_table::{
+    NeighborTable,
+    NeighborTableEntry,
+    Relationship,
+};
+use crate::nwk::payload::NWKCommandFrame;
+use crate::state::DeviceType;
+
+/// Default of nwkLinkStatusPeriod in milliseconds.
+pub const DEFAULT_LINK_STATUS_PERIOD_MS: u32 = 0x0f * 1000;
+/// Default of nwkRouterAgeLimit in link status periods.
+pub const DEFAULT_ROUTER_AGE_LIMIT: u8 = 3;
+/// Upper bound of the random jitter added to every link status period, so that routers
+/// which started together do not keep sending in lockstep.
+pub const MAX_LINK_STATUS_JITTER_MS: u16 = 1000;
+/// Number of entries per link status command which still fits into a secured frame.
+const MAX_ENTRIES_PER_FRAME: usize = 28;
+/// Number of link status commands sent per period, router neighbors beyond
+/// `MAX_LINK_STATUS_FRAMES * 28` are not announced and reported as omitted.
+pub const MAX_LINK_STATUS_FRAMES: usize = 8;
+
+/// The link status commands of one period.
+pub struct LinkStatusFrames {
+    pub frames: heapless::Vec<NPDUFrame, MAX_LINK_STATUS_FRAMES>,
+    /// Router neighbors which did not fit into the frames and were not announced.
+    pub omitted: usize,
+}
+
+impl LinkStatusFrames {
+    fn new() -> Self {
+        Self {
+            frames: heapless::Vec::new(),
+            omitted: 0,
+        }
+    }
+}
+
+fn jitter_ms(random: u16) -> u64 {
+    u64::from(random % (MAX_LINK_STATUS_JITTER_MS + 1))
+}
+
+/// Periodically broadcasts the link status of a router and keeps track of the outgoing
+/// costs its neighbors report back.
+pub struct LinkStatusScheduler {
+    pub link_status_period_ms: u32,
+    pub router_age_limit: u8,
+    next_link_status_ms: u64,
+}
+
+impl LinkStatusScheduler {
+    /// The first link status is due after one period plus a jitter drawn from `random`.
+    pub fn new(now_ms: u64, random: u16) -> Self {
+        Self {
+            link_status_period_ms: DEFAULT_LINK_STATUS_PERIOD_MS,
+            router_age_limit: DEFAULT_ROUTER_AGE_LIMIT,
+            next_link_status_ms: now_ms + u64::from(DEFAULT_LINK_STATUS_PERIOD_MS) + jitter_ms(random),
+        }
+    }
+
+    /// Returns the link status commands to broadcast if a link status period elapsed. The
+    /// next period is extended by up to `MAX_LINK_STATUS_JITTER_MS`, drawn from `random`.
+    ///
+    /// Each elapsed period ages all router neighbors. Neighbors which have not been heard
+    /// of for more than nwkRouterAgeLimit periods lose their outgoing cost, and those
+    /// without a parent or child relationship are dropped from the table.
+    pub fn poll<const N: usize>(
+        &mut self,
+        now_ms: u64,
+        random: u16,
+        neighbor_table: &mut NeighborTable<N>,
+        own_address: ShortAddress,
+        sequence_number: &mut u8,
+    ) -> LinkStatusFrames {
+        if now_ms < self.next_link_status_ms {
+            return LinkStatusFrames::new();
+        }
+        self.next_link_status_ms = now_ms + u64::from(self.link_status_period_ms) + jitter_ms(random);
+
+        self.age_neighbors(neighbor_table);
+        link_status_frames(neighbor_table, own_address, sequence_number)
+    }
+
+    fn age_neighbors<const N: usize>(&self, neighbor_table: &mut NeighborTable<N>) {
+        let mut stale = heapless::Vec::<ShortAddress, N>::new();
+        for entry in neighbor_table.iter_mut().filter(|entry| entry.device_type != DeviceType::EndDevice) {
+            entry.age = entry.age.saturating_add(1);
+            if entry.age > self.router_age_limit {
+                entry.outgoing_cost = 0;
+                if !entry.is_child() && entry.relationship != Relationship::Parent {
+                    // There are at most N entries, so this always fits.
+                    stale.push(entry.network_address).ok();
+                }
+            }
+        }
+        for address in stale {
+            neighbor_table.remove(&address);
+        }
+    }
+}
+
+/// Builds the link status commands announcing all router neighbors, sorted by their
+/// network address and split over as many frames as needed.
+///
+/// Neighbors beyond `MAX_LINK_STATUS_FRAMES` frames are counted in `omitted`.
+pub fn link_status_frames<const N: usize>(
+    neighbor_table: &NeighborTable<N>,
+    own_address: ShortAddress,
+    sequence_number: &mut u8,
+) -> LinkStatusFrames {
+    let mut neighbors: heapless::Vec<&NeighborTableEntry, N> = neighbor_table.iter()
+        .filter(|entry| entry.device_type != DeviceType::EndDevice)
+        .collect();
+    neighbors.sort_unstable_by_key(|entry| entry.network_address.0);
+
+    let mut chunks: heapless::Vec<&[&NeighborTableEntry], MAX_LINK_STATUS_FRAMES> = neighbors
+        .chunks(MAX_ENTRIES_PER_FRAME)
+        .take(MAX_LINK_STATUS_FRAMES)
+        .collect();
+    if chunks.is_empty() {
+        chunks.push(&[]).ok();
+    }
+    let count = chunks.len();
+    let omitted = neighbors.len().saturating_sub(MAX_LINK_STATUS_FRAMES * MAX_ENTRIES_PER_FRAME);
+
+    let frames = chunks.into_iter().enumerate().map(|(i, chunk)| {
+        let link_status = LinkStatus {
+            first_frame: i == 0,
+            last_frame: i == count - 1,
+            entries: chunk.iter().map(|entry| LinkStatusEntry {
+                neighbor_network_address: entry.network_address,
+                incoming_cost: entry.incoming_cost(),
+                outgoing_cost: entry.outgoing_cost,
+            }).collect(),
+        };
+        let frame = command_frame(
+            BROADCAST_ROUTERS_AND_COORDINATOR,
+            own_address,
+            1,
+            *sequence_number,
+            NWKCommandFrame::LinkStatus(link_status),
+        );
+        *sequence_number = sequence_number.wrapping_add(1);
+        frame
+    }).collect();
+    LinkStatusFrames { frames, omitted }
+}
+
+/// Upon receipt of a link status command frame.
+///
+/// Refreshes the sender in the neighbor table and takes over the cost it reports for the
+/// link towards us as our outgoing cost. `lqi` is the link quality of the received frame.
+pub fn link_status_indication<const N: usize>(
+    neighbor_table: &mut NeighborTable<N>,
+    frame: &NPDUFrame,
+    link_status: &LinkStatus,
+    own_address: ShortAddress,
+    lqi: u8,
+) {
+    let source_address = frame.source_address;
+    let own = own_address.0;
+    // The entries are sorted by address, so this frame is responsible for us if we fall
+    // into its range of addresses.
+    let covers_own_address =
+        (link_status.first_frame || link_status.entries.first()
+            .is_some_and(|entry| entry.neighbor_network_address.0 <= own))
+        && (link_status.last_frame || link_status.entries.last()
+            .is_some_and(|entry| own <= entry.neighbor_network_address.0));
+    let reported_cost = link_status.entries.iter()
+        .find(|entry| entry.neighbor_network_address == own_address)
+        .map(|entry| entry.incoming_cost);
+
+    if neighbor_table.get(&source_address).is_none() {
+        let device_type = if source_address.is_coordinator() {
+            DeviceType::Coordinator
+        } else {
+            DeviceType::Router
+        };
+        let entry = NeighborTableEntry::new(source_address, None, device_type, Relationship::NoRelationship);
+        if neighbor_table.insert(entry).is_err() {
+            return;
+        }
+    }
+
+    if let Some(entry) = neighbor_table.get_mut(&source_address) {
+        entry.lqi = lqi;
+        entry.age = 0;
+        if let Some(cost) = reported_cost {
+            entry.outgoing_cost = cost;
+        } else if covers_own_address {
+            // The neighbor does not list us, so it cannot hear us.
+            entry.outgoing_cost = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN_ADDRESS: ShortAddress = ShortAddress(0x0100);
+
+    fn router(network_address: u16, relationship: Relationship) -> NeighborTableEntry {
+        NeighborTableEntry::new(ShortAddress(network_address), None, DeviceType::Router, relationship)
+    }
+
+    #[test]
+    fn link_status_is_sent_once_per_jittered_period() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        let mut sequence_number = 0;
+        let mut scheduler = LinkStatusScheduler::new(0, 400);
+        let period = u64::from(DEFAULT_LINK_STATUS_PERIOD_MS);
+
+        assert!(scheduler.poll(period + 399, 0, &mut neighbor_table, OWN_ADDRESS, &mut sequence_number).frames.is_empty());
+        let frames = scheduler.poll(period + 400, 2500, &mut neighbor_table, OWN_ADDRESS, &mut sequence_number);
+        assert_eq!(frames.frames.len(), 1);
+        assert_eq!(sequence_number, 1);
+
+        // 2500 % 1001 = 498 ms of jitter for the next period.
+        let next = 2 * period + 400 + 498;
+        assert!(scheduler.poll(next - 1, 0, &mut neighbor_table, OWN_ADDRESS, &mut sequence_number).frames.is_empty());
+        assert_eq!(scheduler.poll(next, 0, &mut neighbor_table, OWN_ADDRESS, &mut sequence_number).frames.len(), 1);
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        for random in [0, 1, MAX_LINK_STATUS_JITTER_MS, MAX_LINK_STATUS_JITTER_MS + 1, u16::MAX] {
+            assert!(jitter_ms(random) <= u64::from(MAX_LINK_STATUS_JITTER_MS));
+        }
+        assert_eq!(jitter_ms(MAX_LINK_STATUS_JITTER_MS), u64::from(MAX_LINK_STATUS_JITTER_MS));
+    }
+
+    #[test]
+    fn stale_routers_are_aged_out() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        for entry in [router(0x0001, Relationship::Parent), router(0x0002, Relationship::Sibling)] {
+            neighbor_table.insert(entry).unwrap();
+        }
+        neighbor_table.get_mut(&ShortAddress(0x0001)).unwrap().outgoing_cost = 3;
+        let mut sequence_number = 0;
+        let mut scheduler = LinkStatusScheduler::new(0, 0);
+        let period = u64::from(DEFAULT_LINK_STATUS_PERIOD_MS);
+
+        for i in 1..=u64::from(DEFAULT_ROUTER_AGE_LIMIT) {
+            scheduler.poll(i * period, 0, &mut neighbor_table, OWN_ADDRESS, &mut sequence_number);
+        }
+        assert_eq!(neighbor_table.len(), 2);
+        assert_eq!(neighbor_table.get(&ShortAddress(0x0001)).unwrap().outgoing_cost, 3);
+
+        scheduler.poll(4 * period, 0, &mut neighbor_table, OWN_ADDRESS, &mut sequence_number);
+        assert!(neighbor_table.get(&ShortAddress(0x0002)).is_none());
+        let parent = neighbor_table.get(&ShortAddress(0x0001)).unwrap();
+        assert_eq!((parent.age, parent.outgoing_cost), (4, 0));
+    }
+
+    #[test]
+    fn frames_are_sorted_split_and_report_omitted_neighbors() {
+        let count = MAX_LINK_STATUS_FRAMES * MAX_ENTRIES_PER_FRAME + 3;
+        let mut neighbor_table = NeighborTable::<{ MAX_LINK_STATUS_FRAMES * MAX_ENTRIES_PER_FRAME + 3 }>::new();
+        for address in (1..=count as u16).rev() {
+            neighbor_table.insert(router(address, Relationship::Sibling)).unwrap();
+        }
+
+        let mut sequence_number = 0xff;
+        let frames = link_status_frames(&neighbor_table, OWN_ADDRESS, &mut sequence_number);
+        assert_eq!(frames.frames.len(), MAX_LINK_STATUS_FRAMES);
+        assert_eq!(frames.omitted, 3);
+        assert_eq!(sequence_number, MAX_LINK_STATUS_FRAMES as u8 - 1);
+
+        let first = frames.frames[0].command::<LinkStatus>().unwrap();
+        assert!(first.first_frame && !first.last_frame);
+        assert_eq!(first.entries.len(), MAX_ENTRIES_PER_FRAME);
+        assert_eq!(first.entries[0].neighbor_network_address, ShortAddress(0x0001));
+        assert_eq!(first.entries[1].neighbor_network_address, ShortAddress(0x0002));
+        let last = frames.frames.last().unwrap().command::<LinkStatus>().unwrap();
+        assert!(!last.first_frame && last.last_frame);
+        assert_eq!(frames.frames[0].destination_address, BROADCAST_ROUTERS_AND_COORDINATOR);
+        assert_eq!(frames.frames[0].radius, 1);
+    }
+
+    #[test]
+    fn without_routers_an_empty_link_status_is_sent() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table
+            .insert(NeighborTableEntry::new(ShortAddress(0x0200), None, DeviceType::EndDevice, Relationship::Child))
+            .unwrap();
+        let frames = link_status_frames(&neighbor_table, OWN_ADDRESS, &mut 0);
+        assert_eq!((frames.frames.len(), frames.omitted), (1, 0));
+        let link_status = frames.frames[0].command::<LinkStatus>().unwrap();
+        assert!(link_status.first_frame && link_status.last_frame && link_status.entries.is_empty());
+    }
+
+    #[test]
+    fn indication_updates_the_outgoing_cost() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        let mut sender = NeighborTable::<4>::new();
+        sender.insert(router(OWN_ADDRESS.0, Relationship::Sibling)).unwrap();
+        sender.get_mut(&OWN_ADDRESS).unwrap().lqi = 255;
+        let frames = link_status_frames(&sender, ShortAddress(0x0200), &mut 0);
+        let frame = &frames.frames[0];
+
+        link_status_indication(&mut neighbor_table, frame, frame.command::<LinkStatus>().unwrap(), OWN_ADDRESS, 255);
+        let entry = neighbor_table.get(&ShortAddress(0x0200)).unwrap();
+        assert_eq!((entry.outgoing_cost, entry.lqi, entry.age), (1, 255, 0));
+        assert!(entry.relationship == Relationship::NoRelationship);
+
+        // A link status which covers our address but does not list us means the neighbor
+        // cannot hear us.
+        let frames = link_status_frames(&NeighborTable::<4>::new(), ShortAddress(0x0200), &mut 0);
+        let frame = &frames.frames[0];
+        link_status_indication(&mut neighbor_table, frame, frame.command::<LinkStatus>().unwrap(), OWN_ADDRESS, 255);
+        assert_eq!(neighbor_table.get(&ShortAddress(0x0200)).unwrap().outgoing_cost, 0);
+    }
+}
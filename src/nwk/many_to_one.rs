@@ -0,0 +1,269 @@
+use crate::nwk::address::ShortAddress;
+use crate::nwk::commands::network_status::NetworkStatus;
+use crate::nwk::commands::route_record::RouteRecord;
+use crate::nwk::commands::route_request::ManyToOne;
+use crate::nwk::constants::{
+    DEFAULT_RADIUS,
+    ROUTE_DISCOVERY_TIME_MS,
+};
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::payload::NWKCommandFrame;
+use crate::nwk::route_discovery::RouteDiscovery;
+use crate::nwk::routing_table::RoutingTable;
+
+/// Default of nwkConcentratorRadius.
+pub const DEFAULT_CONCENTRATOR_RADIUS: u8 = 0x05;
+/// Default of nwkConcentratorDiscoveryTime in milliseconds.
+pub const DEFAULT_CONCENTRATOR_DISCOVERY_TIME_MS: u32 = 60 * 1000;
+
+/// Concentrator mode
+///
+/// Periodically broadcasts many-to-one route requests so that all routers learn a route
+/// back to this device. Route errors reported by routers trigger an early re-issue.
+pub struct Concentrator {
+    /// nwkConcentratorRadius
+    pub radius: u8,
+    /// nwkConcentratorDiscoveryTime, zero disables the periodic requests.
+    pub discovery_time_ms: u32,
+    /// Whether this device keeps a source route table and wants route records.
+    pub route_record_table: bool,
+    next_route_request_ms: u64,
+    last_route_request_ms: Option<u64>,
+}
+
+impl Concentrator {
+    /// The first many-to-one route request is sent on the first poll.
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            radius: DEFAULT_CONCENTRATOR_RADIUS,
+            discovery_time_ms: DEFAULT_CONCENTRATOR_DISCOVERY_TIME_MS,
+            route_record_table: true,
+            next_route_request_ms: now_ms,
+            last_route_request_ms: None,
+        }
+    }
+
+    fn many_to_one(&self) -> ManyToOne {
+        if self.route_record_table {
+            ManyToOne::SupportForRRTable
+        } else {
+            ManyToOne::NoSupportForRRTable
+        }
+    }
+
+    /// Returns the many-to-one route request to broadcast if one is due.
+    pub fn poll<const D: usize>(
+        &mut self,
+        now_ms: u64,
+        route_discovery: &mut RouteDiscovery<D>,
+        own_address: ShortAddress,
+        sequence_number: u8,
+    ) -> Option<NPDUFrame> {
+        if now_ms < self.next_route_request_ms {
+            return None;
+        }
+        self.next_route_request_ms = if self.discovery_time_ms == 0 {
+            u64::MAX
+        } else {
+            now_ms + u64::from(self.discovery_time_ms)
+        };
+        self.last_route_request_ms = Some(now_ms);
+
+        Some(route_discovery.many_to_one_route_request(self.many_to_one(), own_address, self.radius, sequence_number))
+    }
+
+    /// Upon receipt of a network status command reporting a route error
+    ///
+    /// Schedules a new many-to-one route request. Requests are spaced by at least
+    /// nwkcRouteDiscoveryTime so that a burst of errors does not flood the network.
+    pub fn route_error_indication(&mut self, status: &NetworkStatus) {
+        if !status.status_code.is_route_failure() {
+            return;
+        }
+        let earliest = self.last_route_request_ms
+            .map_or(0, |last| last + u64::from(ROUTE_DISCOVERY_TIME_MS));
+        self.next_route_request_ms = self.next_route_request_ms.min(earliest);
+    }
+}
+
+/// The route record to send ahead of the next data frame to `destination`
+///
+/// Returns a route record if the route to `destination` leads to a concentrator which
+/// asked for one, and clears the request.
+pub fn route_record_required<const R: usize>(
+    routing_table: &mut RoutingTable<R>,
+    destination: &ShortAddress,
+    own_address: ShortAddress,
+    sequence_number: u8,
+) -> Option<NPDUFrame> {
+    let route = routing_table.get_mut(destination)
+        .filter(|route| route.many_to_one && route.route_record_required)?;
+    route.route_record_required = false;
+
+    Some(command_frame(
+        *destination,
+        own_address,
+        DEFAULT_RADIUS,
+        sequence_number,
+        NWKCommandFrame::RouteRecord(RouteRecord { relay_list: heapless::Vec::new() }),
+    ))
+}
+
+/// Relaying a route record command frame
+///
+/// Every router on the way to the concentrator adds itself to the relay list. Returns the
+/// frame to forward, or `None` if the radius is exhausted or the relay list is full.
+pub fn relay_route_record(frame: &NPDUFrame, route_record: &RouteRecord, own_address: ShortAddress) -> Option<NPDUFrame> {
+    if frame.radius <= 1 {
+        return None;
+    }
+    let mut relay_list = route_record.relay_list.clone();
+    relay_list.push(own_address).ok()?;
+
+    let mut forward = command_frame(
+        frame.destination_address,
+        frame.source_address,
+        frame.radius - 1,
+        frame.sequence_number,
+        NWKCommandFrame::RouteRecord(RouteRecord { relay_list }),
+    );
+    forward.destination_ieee_address = frame.destination_ieee_address;
+    forward.source_ieee_address = frame.source_ieee_address;
+    forward.update_control();
+    Some(forward)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::commands::network_status::StatusCode;
+    use crate::nwk::commands::route_request::RouteRequest;
+    use crate::nwk::constants::MAX_RELAY_COUNT;
+    use crate::nwk::neighbor_table::NeighborTable;
+    use crate::nwk::route_discovery::ReceivedFrame;
+
+    const CONCENTRATOR: ShortAddress = ShortAddress::COORDINATOR;
+    const OWN_ADDRESS: ShortAddress = ShortAddress(0x0100);
+
+    fn route_error(status_code: StatusCode) -> NetworkStatus {
+        NetworkStatus { status_code, destination_address: CONCENTRATOR }
+    }
+
+    #[test]
+    fn many_to_one_route_request_is_sent_every_discovery_time() {
+        let mut concentrator = Concentrator::new(100);
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let discovery_time_ms = u64::from(DEFAULT_CONCENTRATOR_DISCOVERY_TIME_MS);
+
+        assert!(concentrator.poll(99, &mut route_discovery, CONCENTRATOR, 1).is_none());
+        let frame = concentrator.poll(100, &mut route_discovery, CONCENTRATOR, 1).unwrap();
+        assert_eq!(frame.radius, DEFAULT_CONCENTRATOR_RADIUS);
+        assert!(frame.command::<RouteRequest>().unwrap().command_options.many_to_one == ManyToOne::SupportForRRTable);
+
+        assert!(concentrator.poll(100 + discovery_time_ms - 1, &mut route_discovery, CONCENTRATOR, 2).is_none());
+        concentrator.route_record_table = false;
+        let frame = concentrator.poll(100 + discovery_time_ms, &mut route_discovery, CONCENTRATOR, 2).unwrap();
+        assert!(frame.command::<RouteRequest>().unwrap().command_options.many_to_one == ManyToOne::NoSupportForRRTable);
+        assert_eq!(frame.command::<RouteRequest>().unwrap().route_request_identifier, 2);
+    }
+
+    #[test]
+    fn zero_discovery_time_sends_a_single_request() {
+        let mut concentrator = Concentrator::new(0);
+        concentrator.discovery_time_ms = 0;
+        let mut route_discovery = RouteDiscovery::<4>::new();
+
+        assert!(concentrator.poll(0, &mut route_discovery, CONCENTRATOR, 1).is_some());
+        assert!(concentrator.poll(u64::MAX - 1, &mut route_discovery, CONCENTRATOR, 2).is_none());
+
+        // Route errors still trigger a new request.
+        concentrator.route_error_indication(&route_error(StatusCode::ManyToOneRouteFailure));
+        assert!(concentrator.poll(u64::from(ROUTE_DISCOVERY_TIME_MS), &mut route_discovery, CONCENTRATOR, 3).is_some());
+    }
+
+    #[test]
+    fn route_error_reissues_the_request() {
+        let mut concentrator = Concentrator::new(0);
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let route_discovery_time_ms = u64::from(ROUTE_DISCOVERY_TIME_MS);
+        concentrator.poll(0, &mut route_discovery, CONCENTRATOR, 1).unwrap();
+
+        // Status codes which are no route failures are ignored.
+        concentrator.route_error_indication(&route_error(StatusCode::LowBatteryLevel));
+        assert!(concentrator.poll(route_discovery_time_ms, &mut route_discovery, CONCENTRATOR, 2).is_none());
+
+        // The new request waits until nwkcRouteDiscoveryTime passed since the last one.
+        concentrator.route_error_indication(&route_error(StatusCode::SourceRouteFailure));
+        assert!(concentrator.poll(route_discovery_time_ms - 1, &mut route_discovery, CONCENTRATOR, 2).is_none());
+        assert!(concentrator.poll(route_discovery_time_ms, &mut route_discovery, CONCENTRATOR, 2).is_some());
+
+        // Once sent, the regular period continues from there.
+        let next_ms = route_discovery_time_ms + u64::from(DEFAULT_CONCENTRATOR_DISCOVERY_TIME_MS);
+        assert!(concentrator.poll(next_ms - 1, &mut route_discovery, CONCENTRATOR, 3).is_none());
+        assert!(concentrator.poll(next_ms, &mut route_discovery, CONCENTRATOR, 3).is_some());
+    }
+
+    #[test]
+    fn route_record_is_sent_before_the_next_data_frame() {
+        let mut concentrator = Concentrator::new(0);
+        let mut concentrator_discovery = RouteDiscovery::<4>::new();
+        let request = concentrator.poll(0, &mut concentrator_discovery, CONCENTRATOR, 1).unwrap();
+
+        // A router one hop away from the concentrator learns the route.
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        let received = ReceivedFrame { frame: &request, previous_hop: CONCENTRATOR, now_ms: 0 };
+        route_discovery.route_request_indication(
+            &mut routing_table, &NeighborTable::<4>::new(), received, request.command::<RouteRequest>().unwrap(), OWN_ADDRESS, 1,
+        );
+        assert_eq!(routing_table.next_hop(&CONCENTRATOR), Some(CONCENTRATOR));
+        assert!(route_record_required(&mut routing_table, &ShortAddress(0x0200), OWN_ADDRESS, 2).is_none());
+
+        let frame = route_record_required(&mut routing_table, &CONCENTRATOR, OWN_ADDRESS, 2).unwrap();
+        assert_eq!(frame.destination_address, CONCENTRATOR);
+        assert_eq!(frame.source_address, OWN_ADDRESS);
+        assert!(frame.command::<RouteRecord>().unwrap().relay_list.is_empty());
+        // Only the first data frame after the many-to-one route request needs one.
+        assert!(route_record_required(&mut routing_table, &CONCENTRATOR, OWN_ADDRESS, 3).is_none());
+    }
+
+    #[test]
+    fn no_route_record_for_concentrators_without_route_record_table() {
+        let mut concentrator = Concentrator::new(0);
+        concentrator.route_record_table = false;
+        let request = concentrator.poll(0, &mut RouteDiscovery::<4>::new(), CONCENTRATOR, 1).unwrap();
+
+        let mut routing_table = RoutingTable::<4>::new();
+        let received = ReceivedFrame { frame: &request, previous_hop: CONCENTRATOR, now_ms: 0 };
+        RouteDiscovery::<4>::new().route_request_indication(
+            &mut routing_table, &NeighborTable::<4>::new(), received, request.command::<RouteRequest>().unwrap(), OWN_ADDRESS, 1,
+        );
+        assert!(routing_table.get(&CONCENTRATOR).unwrap().no_route_cache);
+        assert!(route_record_required(&mut routing_table, &CONCENTRATOR, OWN_ADDRESS, 2).is_none());
+    }
+
+    #[test]
+    fn relays_add_themselves_to_the_route_record() {
+        let frame = command_frame(
+            CONCENTRATOR,
+            ShortAddress(0x0200),
+            2,
+            7,
+            NWKCommandFrame::RouteRecord(RouteRecord { relay_list: heapless::Vec::new() }),
+        );
+        let forward = relay_route_record(&frame, frame.command::<RouteRecord>().unwrap(), OWN_ADDRESS).unwrap();
+        assert_eq!(forward.destination_address, CONCENTRATOR);
+        assert_eq!(forward.source_address, ShortAddress(0x0200));
+        assert_eq!(forward.radius, 1);
+        assert_eq!(forward.sequence_number, 7);
+        assert_eq!(forward.command::<RouteRecord>().unwrap().relay_list, [OWN_ADDRESS]);
+
+        // The radius is exhausted.
+        assert!(relay_route_record(&forward, forward.command::<RouteRecord>().unwrap(), ShortAddress(0x0300)).is_none());
+
+        let relay_list = (0..MAX_RELAY_COUNT as u16).map(ShortAddress).collect();
+        let full = command_frame(CONCENTRATOR, ShortAddress(0x0200), 2, 8, NWKCommandFrame::RouteRecord(RouteRecord { relay_list }));
+        assert!(relay_route_record(&full, full.command::<RouteRecord>().unwrap(), OWN_ADDRESS).is_none());
+    }
+}
@@ -1,4 +1,21 @@
 pub mod payload;
 pub mod frame;
+pub mod frame_builder;
+pub mod frame_ref;
 pub mod address;
-pub mod commands;
\ No newline at end of file
+pub mod commands;
+pub mod constants;
+pub mod neighbor_table;
+pub mod nlme;
+pub mod source_route_table;
+pub mod relay;
+pub mod link_status;
+pub mod network_manager;
+pub mod nib;
+pub mod end_device_timeout;
+pub mod link_power;
+pub mod routing_table;
+pub mod route_discovery;
+pub mod many_to_one;
+pub mod broadcast;
+pub mod multicast;
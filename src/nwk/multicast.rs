@@ -0,0 +1,280 @@
+use crate::nwk::address::{
+    GroupIdentifier,
+    ShortAddress,
+};
+use crate::nwk::frame::{
+    MulticastControl,
+    MulticastMode,
+    NPDUFrame,
+};
+use crate::nwk::routing_table::RoutingTable;
+use crate::state::DeviceType;
+
+/// A nonmember radius of 0x07 is never decremented and floods the whole network.
+pub const INFINITE_NONMEMBER_RADIUS: u8 = 0x07;
+/// Default of the maximum nonmember radius of multicasts originated by this device.
+pub const DEFAULT_MAX_NONMEMBER_RADIUS: u8 = 0x02;
+
+/// Group membership of the device, usually backed by the APS group table.
+pub trait GroupMembership {
+    fn is_member(&self, group_id: &GroupIdentifier) -> bool;
+}
+
+impl GroupMembership for [GroupIdentifier] {
+    fn is_member(&self, group_id: &GroupIdentifier) -> bool {
+        self.contains(group_id)
+    }
+}
+
+impl<const N: usize> GroupMembership for heapless::Vec<GroupIdentifier, N> {
+    fn is_member(&self, group_id: &GroupIdentifier) -> bool {
+        self.contains(group_id)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl GroupMembership for alloc::vec::Vec<GroupIdentifier> {
+    fn is_member(&self, group_id: &GroupIdentifier) -> bool {
+        self.contains(group_id)
+    }
+}
+
+/// How a multicast frame leaves this device.
+#[derive(Debug)]
+pub enum MulticastForward {
+    /// Member mode, flooded by MAC broadcast and the broadcast transaction table.
+    Broadcast,
+    /// Nonmember mode, unicast to the given next hop towards a member of the group.
+    Unicast(ShortAddress),
+}
+
+/// What to do with a received multicast.
+#[derive(Debug)]
+pub struct MulticastIndication {
+    /// The device is a member of the group and delivers the frame.
+    pub deliver: bool,
+    pub forward: Option<MulticastForward>,
+}
+
+fn group_id(frame: &NPDUFrame) -> GroupIdentifier {
+    GroupIdentifier(frame.destination_address.0)
+}
+
+/// The next hop of an active route towards a member of `group_id`.
+fn group_next_hop<const R: usize>(routing_table: &mut RoutingTable<R>, group_id: &GroupIdentifier) -> Option<ShortAddress> {
+    let destination = ShortAddress(group_id.0);
+    routing_table.get(&destination)
+        .filter(|route| route.group_id_flag)?;
+    routing_table.use_route(&destination)
+}
+
+/// Switches a multicast frame to member mode with a fresh nonmember radius.
+fn to_member_mode(multicast_control: &mut MulticastControl) {
+    multicast_control.multicast_mode = MulticastMode::MemberMode;
+    multicast_control.nonmember_radius = multicast_control.max_nonmember_radius;
+}
+
+/// Originating a multicast
+///
+/// Members of the group flood the frame in member mode. Other devices unicast it in
+/// nonmember mode towards the group, which requires an active group route, otherwise
+/// `None` is returned and a route discovery for the group has to be started.
+pub fn multicast_request<G: GroupMembership + ?Sized, const R: usize>(
+    frame: &mut NPDUFrame,
+    groups: &G,
+    routing_table: &mut RoutingTable<R>,
+    max_nonmember_radius: u8,
+) -> Option<MulticastForward> {
+    let group_id = group_id(frame);
+    let mut multicast_control = MulticastControl {
+        multicast_mode: MulticastMode::NonmemberMode,
+        nonmember_radius: max_nonmember_radius,
+        max_nonmember_radius,
+    };
+
+    let forward = if groups.is_member(&group_id) {
+        to_member_mode(&mut multicast_control);
+        MulticastForward::Broadcast
+    } else {
+        MulticastForward::Unicast(group_next_hop(routing_table, &group_id)?)
+    };
+    frame.multicast_control = Some(multicast_control);
+    Some(forward)
+}
+
+/// Upon receipt of a multicast frame
+///
+/// Updates the multicast control field of `frame` for forwarding:
+/// - Member mode frames are delivered by members, which rebroadcast them with the
+///   nonmember radius reset to its maximum. Nonmembers rebroadcast them as long as the
+///   decremented nonmember radius allows.
+/// - Nonmember mode frames reaching a member switch to member mode and are flooded,
+///   otherwise they are unicast on towards the group.
+///
+/// Only routers forward multicasts. Returns `None` for frames which are not multicasts.
+pub fn multicast_indication<G: GroupMembership + ?Sized, const R: usize>(
+    frame: &mut NPDUFrame,
+    groups: &G,
+    routing_table: &mut RoutingTable<R>,
+    device_type: DeviceType,
+) -> Option<MulticastIndication> {
+    let group_id = group_id(frame);
+    let is_router = device_type != DeviceType::EndDevice;
+    let deliver = groups.is_member(&group_id);
+    let multicast_control = frame.multicast_control.as_mut()?;
+
+    let forward = match multicast_control.multicast_mode {
+        MulticastMode::MemberMode if deliver => {
+            to_member_mode(multicast_control);
+            Some(MulticastForward::Broadcast)
+        },
+        MulticastMode::MemberMode => match multicast_control.nonmember_radius {
+            0 => None,
+            INFINITE_NONMEMBER_RADIUS => Some(MulticastForward::Broadcast),
+            _ => {
+                multicast_control.nonmember_radius -= 1;
+                Some(MulticastForward::Broadcast)
+            },
+        },
+        MulticastMode::NonmemberMode if deliver => {
+            to_member_mode(multicast_control);
+            Some(MulticastForward::Broadcast)
+        },
+        MulticastMode::NonmemberMode => {
+            group_next_hop(routing_table, &group_id).map(MulticastForward::Unicast)
+        },
+    };
+
+    Some(MulticastIndication {
+        deliver,
+        forward: forward.filter(|_| is_router),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::frame_builder::NPDUFrameBuilder;
+    use crate::nwk::payload::{
+        DataFrame,
+        Payload,
+    };
+
+    const GROUP: GroupIdentifier = GroupIdentifier(0x1234);
+    const NEXT_HOP: ShortAddress = ShortAddress(0x0002);
+
+    fn multicast(multicast_mode: MulticastMode, nonmember_radius: u8) -> NPDUFrame {
+        let multicast_control = MulticastControl {
+            multicast_mode,
+            nonmember_radius,
+            max_nonmember_radius: 3,
+        };
+        NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::new() }))
+            .multicast(GROUP, multicast_control)
+            .source(ShortAddress(0x0001))
+            .build(&mut 0)
+            .unwrap()
+    }
+
+    fn group_route() -> RoutingTable<2> {
+        let mut routing_table = RoutingTable::new();
+        routing_table.activate(ShortAddress(GROUP.0), NEXT_HOP).unwrap();
+        routing_table.get_mut(&ShortAddress(GROUP.0)).unwrap().group_id_flag = true;
+        routing_table
+    }
+
+    fn multicast_control(frame: &NPDUFrame) -> MulticastControl {
+        frame.multicast_control.unwrap()
+    }
+
+    #[test]
+    fn members_originate_in_member_mode() {
+        let mut frame = multicast(MulticastMode::NonmemberMode, 0);
+        frame.multicast_control = None;
+        let forward = multicast_request(&mut frame, &[GROUP][..], &mut RoutingTable::<2>::new(), DEFAULT_MAX_NONMEMBER_RADIUS);
+        assert!(matches!(forward, Some(MulticastForward::Broadcast)));
+        let multicast_control = multicast_control(&frame);
+        assert!(matches!(multicast_control.multicast_mode, MulticastMode::MemberMode));
+        assert_eq!(multicast_control.nonmember_radius, DEFAULT_MAX_NONMEMBER_RADIUS);
+        assert_eq!(multicast_control.max_nonmember_radius, DEFAULT_MAX_NONMEMBER_RADIUS);
+    }
+
+    #[test]
+    fn nonmembers_originate_over_a_group_route() {
+        let mut frame = multicast(MulticastMode::MemberMode, 0);
+        assert!(multicast_request(&mut frame, &[][..], &mut RoutingTable::<2>::new(), 2).is_none());
+        // The frame is left untouched without a route.
+        assert!(matches!(multicast_control(&frame).multicast_mode, MulticastMode::MemberMode));
+
+        // Routes to the same address which do not lead to a group are not used.
+        let mut routing_table = group_route();
+        routing_table.get_mut(&ShortAddress(GROUP.0)).unwrap().group_id_flag = false;
+        assert!(multicast_request(&mut frame, &[][..], &mut routing_table, 2).is_none());
+
+        let forward = multicast_request(&mut frame, &[][..], &mut group_route(), 2);
+        assert!(matches!(forward, Some(MulticastForward::Unicast(NEXT_HOP))));
+        assert!(matches!(multicast_control(&frame).multicast_mode, MulticastMode::NonmemberMode));
+    }
+
+    #[test]
+    fn members_reset_the_nonmember_radius() {
+        let mut frame = multicast(MulticastMode::MemberMode, 0);
+        let indication = multicast_indication(&mut frame, &[GROUP][..], &mut RoutingTable::<2>::new(), DeviceType::Router).unwrap();
+        assert!(indication.deliver);
+        assert!(matches!(indication.forward, Some(MulticastForward::Broadcast)));
+        assert_eq!(multicast_control(&frame).nonmember_radius, 3);
+    }
+
+    #[test]
+    fn nonmembers_decrement_the_nonmember_radius() {
+        let mut frame = multicast(MulticastMode::MemberMode, 2);
+        let mut routing_table = RoutingTable::<2>::new();
+        for nonmember_radius in [1, 0] {
+            let indication = multicast_indication(&mut frame, &[][..], &mut routing_table, DeviceType::Router).unwrap();
+            assert!(!indication.deliver);
+            assert!(matches!(indication.forward, Some(MulticastForward::Broadcast)));
+            assert_eq!(multicast_control(&frame).nonmember_radius, nonmember_radius);
+        }
+        let indication = multicast_indication(&mut frame, &[][..], &mut routing_table, DeviceType::Router).unwrap();
+        assert!(indication.forward.is_none());
+
+        let mut frame = multicast(MulticastMode::MemberMode, INFINITE_NONMEMBER_RADIUS);
+        let indication = multicast_indication(&mut frame, &[][..], &mut routing_table, DeviceType::Router).unwrap();
+        assert!(matches!(indication.forward, Some(MulticastForward::Broadcast)));
+        assert_eq!(multicast_control(&frame).nonmember_radius, INFINITE_NONMEMBER_RADIUS);
+    }
+
+    #[test]
+    fn nonmember_mode_switches_to_member_mode_at_a_member() {
+        let mut frame = multicast(MulticastMode::NonmemberMode, 1);
+        let indication = multicast_indication(&mut frame, &[GROUP][..], &mut RoutingTable::<2>::new(), DeviceType::Router).unwrap();
+        assert!(indication.deliver);
+        assert!(matches!(indication.forward, Some(MulticastForward::Broadcast)));
+        let multicast_control = multicast_control(&frame);
+        assert!(matches!(multicast_control.multicast_mode, MulticastMode::MemberMode));
+        assert_eq!(multicast_control.nonmember_radius, 3);
+    }
+
+    #[test]
+    fn nonmember_mode_is_unicast_towards_the_group() {
+        let mut frame = multicast(MulticastMode::NonmemberMode, 1);
+        let indication = multicast_indication(&mut frame, &[][..], &mut group_route(), DeviceType::Router).unwrap();
+        assert!(!indication.deliver);
+        assert!(matches!(indication.forward, Some(MulticastForward::Unicast(NEXT_HOP))));
+        assert_eq!(multicast_control(&frame).nonmember_radius, 1);
+
+        let indication = multicast_indication(&mut frame, &[][..], &mut RoutingTable::<2>::new(), DeviceType::Router).unwrap();
+        assert!(indication.forward.is_none());
+    }
+
+    #[test]
+    fn end_devices_only_deliver() {
+        let mut frame = multicast(MulticastMode::MemberMode, 1);
+        let indication = multicast_indication(&mut frame, &[GROUP][..], &mut group_route(), DeviceType::EndDevice).unwrap();
+        assert!(indication.deliver);
+        assert!(indication.forward.is_none());
+
+        frame.multicast_control = None;
+        assert!(multicast_indication(&mut frame, &[GROUP][..], &mut group_route(), DeviceType::Router).is_none());
+    }
+}
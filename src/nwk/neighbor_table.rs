@@ -0,0 +1,189 @@
+use crate::nwk::address::{
+    IEEEAddress,
+    ShortAddress,
+};
+use crate::nwk::commands::end_device_timeout_request::RequestedTimeout;
+use crate::state::DeviceType;
+
+/// Default of nwkEndDeviceTimeoutDefault.
+pub const DEFAULT_END_DEVICE_TIMEOUT: RequestedTimeout = RequestedTimeout::Minutes256;
+
+/// Relationship Field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Relationship {
+    Parent = 0x00,
+    Child = 0x01,
+    Sibling = 0x02,
+    NoRelationship = 0x03,
+    PreviousChild = 0x04,
+    UnauthenticatedChild = 0x05,
+}
+
+/// 3.6.1.5 Neighbor Table Entry
+#[derive(Debug)]
+pub struct NeighborTableEntry {
+    pub network_address: ShortAddress,
+    pub extended_address: Option<IEEEAddress>,
+    pub device_type: DeviceType,
+    pub rx_on_when_idle: bool,
+    pub relationship: Relationship,
+    pub lqi: u8,
+    /// The cost of the link towards the neighbor as reported by its link status, 0 if unknown.
+    pub outgoing_cost: u8,
+    /// Number of link status periods since the last link status from the neighbor.
+    pub age: u8,
+    /// The timeout negotiated with an end device child.
+    pub end_device_timeout: RequestedTimeout,
+    /// Time in milliseconds at which an end device child is considered gone, `None` until
+    /// the parent started to track it.
+    pub timeout_expiry_ms: Option<u64>,
+    /// Signal strength in dBm of the last frame received from the neighbor.
+    pub rssi: Option<i8>,
+    /// The change of transmit power in dBm applied for frames to the neighbor.
+    pub transmit_power_delta: i8,
+}
+
+impl NeighborTableEntry {
+    pub fn new(
+        network_address: ShortAddress,
+        extended_address: Option<IEEEAddress>,
+        device_type: DeviceType,
+        relationship: Relationship,
+    ) -> Self {
+        Self {
+            network_address,
+            extended_address,
+            device_type,
+            rx_on_when_idle: device_type != DeviceType::EndDevice,
+            relationship,
+            lqi: 0,
+            outgoing_cost: 0,
+            age: 0,
+            end_device_timeout: DEFAULT_END_DEVICE_TIMEOUT,
+            timeout_expiry_ms: None,
+            rssi: None,
+            transmit_power_delta: 0,
+        }
+    }
+
+    /// 3.6.3.1 Routing Cost, estimated from the link quality of received frames.
+    pub fn incoming_cost(&self) -> u8 {
+        link_cost(self.lqi)
+    }
+
+    /// The cost of the link to use for routing. Once the neighbor reported its view of the
+    /// link the worse of both directions is used, which keeps the link costs symmetric.
+    pub fn link_cost(&self) -> u8 {
+        if self.outgoing_cost == 0 {
+            self.incoming_cost()
+        } else {
+            self.incoming_cost().max(self.outgoing_cost)
+        }
+    }
+
+    pub fn is_child(&self) -> bool {
+        self.relationship == Relationship::Child
+            || self.relationship == Relationship::UnauthenticatedChild
+    }
+}
+
+/// 3.6.3.1 Routing Cost
+///
+/// Maps a link quality indication onto the link cost `min(7, round(1 / p^4))`, where the
+/// delivery probability `p` is approximated by `lqi / 255`.
+pub fn link_cost(lqi: u8) -> u8 {
+    if lqi == 0 {
+        return 7;
+    }
+    let lqi = u64::from(lqi).pow(4);
+    let max = 255u64.pow(4);
+    ((max + lqi / 2) / lqi).clamp(1, 7) as u8
+}
+
+/// The table is full and the entry could not be added.
+#[derive(Debug)]
+pub struct NeighborTableFull;
+
+/// 3.6.1.5 Neighbor Table
+///
+/// Holds up to `N` neighbors without allocating.
+pub struct NeighborTable<const N: usize> {
+    entries: heapless::Vec<NeighborTableEntry, N>,
+}
+
+impl<const N: usize> NeighborTable<N> {
+    pub fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &NeighborTableEntry> {
+        self.entries.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut NeighborTableEntry> {
+        self.entries.iter_mut()
+    }
+
+    pub fn get(&self, network_address: &ShortAddress) -> Option<&NeighborTableEntry> {
+        self.entries.iter().find(|entry| entry.network_address == *network_address)
+    }
+
+    pub fn get_mut(&mut self, network_address: &ShortAddress) -> Option<&mut NeighborTableEntry> {
+        self.entries.iter_mut().find(|entry| entry.network_address == *network_address)
+    }
+
+    pub fn get_by_extended_address(&self, extended_address: &IEEEAddress) -> Option<&NeighborTableEntry> {
+        self.entries.iter().find(|entry| entry.extended_address.as_ref() == Some(extended_address))
+    }
+
+    /// Returns the entry of our parent, if we have joined through one.
+    pub fn parent(&self) -> Option<&NeighborTableEntry> {
+        self.entries.iter().find(|entry| entry.relationship == Relationship::Parent)
+    }
+
+    /// Adds an entry or replaces the one with the same network address.
+    pub fn insert(&mut self, entry: NeighborTableEntry) -> Result<(), NeighborTableFull> {
+        if let Some(existing) = self.get_mut(&entry.network_address) {
+            *existing = entry;
+            Ok(())
+        } else {
+            self.entries.push(entry).map_err(|_| NeighborTableFull)
+        }
+    }
+
+    pub fn remove(&mut self, network_address: &ShortAddress) -> Option<NeighborTableEntry> {
+        let index = self.entries.iter().position(|entry| entry.network_address == *network_address)?;
+        Some(self.entries.swap_remove(index))
+    }
+
+    /// Removes all children and returns how many entries were dropped.
+    pub fn remove_children(&mut self) -> usize {
+        let length = self.entries.len();
+        self.entries.retain(|entry| !entry.is_child());
+        length - self.entries.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<const N: usize> Default for NeighborTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,279 @@
+use crate::nwk::address::{
+    PanId,
+    BROADCAST_ALL_DEVICES,
+};
+use crate::nwk::commands::network_report::{
+    NetworkReport,
+    ReportInformation,
+    MAX_REPORT_INFORMATION_COUNT,
+};
+use crate::nwk::commands::network_update::{
+    NetworkUpdate,
+    UpdateInformation,
+};
+use crate::nwk::constants::DEFAULT_RADIUS;
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::nib::NIB;
+use crate::nwk::payload::NWKCommandFrame;
+
+/// Whether `update_id` is more recent than `current`, taking wrap around into account.
+fn is_newer_update_id(update_id: u8, current: u8) -> bool {
+    (update_id.wrapping_sub(current) as i8) > 0
+}
+
+/// Builds the network report a device sends to the nwkManagerAddr after it heard beacons
+/// of other networks using our PAN identifier.
+pub fn network_report(nib: &mut NIB, conflicting_pan_ids: &[PanId]) -> NPDUFrame {
+    let pan_ids = conflicting_pan_ids.iter()
+        .take(MAX_REPORT_INFORMATION_COUNT)
+        .copied()
+        .collect();
+    command_frame(
+        nib.manager_address,
+        nib.network_address,
+        DEFAULT_RADIUS,
+        nib.next_sequence_number(),
+        NWKCommandFrame::NetworkReport(NetworkReport {
+            extended_pan_id: nib.extended_pan_id,
+            report_information: ReportInformation::PanIdentifierConflict(pan_ids),
+        }),
+    )
+}
+
+/// Upon receipt of a network update command.
+///
+/// Returns the PAN identifier to switch to if the update belongs to our network and is more
+/// recent than nwkUpdateId, which is advanced to the received one.
+pub fn network_update_indication(nib: &mut NIB, update: &NetworkUpdate) -> Option<PanId> {
+    if update.extended_pan_id != nib.extended_pan_id || !is_newer_update_id(update.update_id, nib.update_id) {
+        return None;
+    }
+    nib.update_id = update.update_id;
+    let UpdateInformation::PanIdentifierUpdate(pan_id) = update.update_information;
+    Some(pan_id)
+}
+
+/// PAN identifier conflict resolution
+///
+/// Collects the PAN identifier conflicts reported to the network manager and moves the
+/// network to a PAN identifier nobody reported. The PAN identifiers and nwkUpdateId are
+/// read from and written to the NIB.
+pub struct NetworkManager {
+    /// Further conflicts than fit into a single network report are dropped.
+    conflicting_pan_ids: heapless::Vec<PanId, MAX_REPORT_INFORMATION_COUNT>,
+    /// The announced PAN identifier and the time in milliseconds at which the manager
+    /// switches to it.
+    pending_pan_id: Option<(PanId, u64)>,
+}
+
+impl NetworkManager {
+    pub fn new() -> Self {
+        Self {
+            conflicting_pan_ids: heapless::Vec::new(),
+            pending_pan_id: None,
+        }
+    }
+
+    /// The PAN identifiers reported since the last conflict resolution.
+    pub fn conflicting_pan_ids(&self) -> &[PanId] {
+        &self.conflicting_pan_ids
+    }
+
+    /// The PAN identifier announced by the last network update, until the manager
+    /// switched to it.
+    pub fn pending_pan_id(&self) -> Option<PanId> {
+        self.pending_pan_id.map(|(pan_id, _)| pan_id)
+    }
+
+    /// Records the PAN identifiers of a network report for our network.
+    pub fn report_indication(&mut self, nib: &NIB, report: &NetworkReport) {
+        if report.extended_pan_id != nib.extended_pan_id {
+            return;
+        }
+        let ReportInformation::PanIdentifierConflict(pan_ids) = &report.report_information;
+        for pan_id in pan_ids {
+            if !self.conflicting_pan_ids.contains(pan_id) {
+                self.conflicting_pan_ids.push(*pan_id).ok();
+            }
+        }
+    }
+
+    /// Picks a new PAN identifier from `random` which neither we nor any reported network
+    /// uses.
+    pub fn select_pan_id(&self, nib: &NIB, random: u16) -> PanId {
+        let mut pan_id = PanId(random & PanId::MAX.0);
+        while pan_id == nib.pan_id || self.conflicting_pan_ids.contains(&pan_id) {
+            pan_id = PanId(pan_id.0.wrapping_add(1) & PanId::MAX.0);
+        }
+        pan_id
+    }
+
+    /// Resolves the reported conflicts, if any, by selecting a new PAN identifier and
+    /// incrementing nwkUpdateId. Returns the network update to broadcast.
+    ///
+    /// nwkPANId keeps its value until `poll` switches to the new PAN identifier, which
+    /// gives the update nwkNetworkBroadcastDeliveryTime to propagate. No new conflict is
+    /// resolved while a switch is pending.
+    pub fn resolve_conflict(&mut self, nib: &mut NIB, now_ms: u64, random: u16) -> Option<NPDUFrame> {
+        if self.conflicting_pan_ids.is_empty() || self.pending_pan_id.is_some() {
+            return None;
+        }
+
+        let pan_id = self.select_pan_id(nib, random);
+        nib.update_id = nib.update_id.wrapping_add(1);
+        self.conflicting_pan_ids.clear();
+        self.pending_pan_id = Some((pan_id, now_ms + u64::from(nib.broadcast_delivery_time_ms)));
+
+        Some(command_frame(
+            BROADCAST_ALL_DEVICES,
+            nib.network_address,
+            DEFAULT_RADIUS,
+            nib.next_sequence_number(),
+            NWKCommandFrame::NetworkUpdate(NetworkUpdate {
+                extended_pan_id: nib.extended_pan_id,
+                update_id: nib.update_id,
+                update_information: UpdateInformation::PanIdentifierUpdate(pan_id),
+            }),
+        ))
+    }
+
+    /// Switches nwkPANId to the announced PAN identifier once the network update had
+    /// nwkNetworkBroadcastDeliveryTime to propagate. Returns the new PAN identifier, which
+    /// the MAC layer has to take over as well.
+    pub fn poll(&mut self, nib: &mut NIB, now_ms: u64) -> Option<PanId> {
+        match self.pending_pan_id {
+            Some((pan_id, switch_ms)) if now_ms >= switch_ms => {
+                self.pending_pan_id = None;
+                nib.pan_id = pan_id;
+                Some(pan_id)
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Default for NetworkManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::address::{
+        ExtendedPanId,
+        IEEEAddress,
+        ShortAddress,
+    };
+
+    const EXTENDED_PAN_ID: ExtendedPanId = ExtendedPanId(0x0011_2233_4455_6677);
+
+    fn nib() -> NIB {
+        let mut nib = NIB::new(IEEEAddress(1), 0);
+        nib.pan_id = PanId(0x1234);
+        nib.extended_pan_id = EXTENDED_PAN_ID;
+        nib.network_address = ShortAddress::COORDINATOR;
+        nib
+    }
+
+    fn report(extended_pan_id: ExtendedPanId, pan_ids: &[u16]) -> NetworkReport {
+        NetworkReport {
+            extended_pan_id,
+            report_information: ReportInformation::PanIdentifierConflict(pan_ids.iter().copied().map(PanId).collect()),
+        }
+    }
+
+    #[test]
+    fn report_is_sent_to_the_manager() {
+        let mut nib = nib();
+        nib.manager_address = ShortAddress(0x0042);
+        nib.network_address = ShortAddress(0x796f);
+        let pan_ids: heapless::Vec<PanId, 32> = (0..32).map(PanId).collect();
+        let frame = network_report(&mut nib, &pan_ids);
+
+        assert_eq!(frame.destination_address, ShortAddress(0x0042));
+        assert_eq!(frame.source_address, ShortAddress(0x796f));
+        assert_eq!(nib.sequence_number, 1);
+        let report = frame.command::<NetworkReport>().unwrap();
+        assert_eq!(report.extended_pan_id, EXTENDED_PAN_ID);
+        let ReportInformation::PanIdentifierConflict(reported) = &report.report_information;
+        assert_eq!(&reported[..], &pan_ids[..MAX_REPORT_INFORMATION_COUNT]);
+    }
+
+    #[test]
+    fn only_reports_of_our_network_are_collected() {
+        let nib = nib();
+        let mut manager = NetworkManager::new();
+        manager.report_indication(&nib, &report(EXTENDED_PAN_ID, &[0x0001, 0x0002]));
+        manager.report_indication(&nib, &report(EXTENDED_PAN_ID, &[0x0002, 0x0003]));
+        manager.report_indication(&nib, &report(ExtendedPanId(1), &[0x0004]));
+        assert_eq!(manager.conflicting_pan_ids(), &[PanId(0x0001), PanId(0x0002), PanId(0x0003)]);
+    }
+
+    #[test]
+    fn selected_pan_id_avoids_own_and_reported_ones() {
+        let nib = nib();
+        let mut manager = NetworkManager::new();
+        manager.report_indication(&nib, &report(EXTENDED_PAN_ID, &[0x1235, 0x3fff]));
+        assert_eq!(manager.select_pan_id(&nib, 0x1234), PanId(0x1236));
+        assert_eq!(manager.select_pan_id(&nib, 0xffff), PanId(0x0000));
+        assert_eq!(manager.select_pan_id(&nib, 0x5000), PanId(0x1000));
+    }
+
+    #[test]
+    fn switch_is_deferred_by_the_broadcast_delivery_time() {
+        let mut nib = nib();
+        nib.update_id = 0xff;
+        let mut manager = NetworkManager::new();
+        assert!(manager.resolve_conflict(&mut nib, 0, 0).is_none());
+
+        manager.report_indication(&nib, &report(EXTENDED_PAN_ID, &[0x1234]));
+        let frame = manager.resolve_conflict(&mut nib, 1000, 0x0042).unwrap();
+        assert_eq!(frame.destination_address, BROADCAST_ALL_DEVICES);
+        let update = frame.command::<NetworkUpdate>().unwrap();
+        assert_eq!((update.extended_pan_id, update.update_id), (EXTENDED_PAN_ID, 0x00));
+        assert!(update.update_information == UpdateInformation::PanIdentifierUpdate(PanId(0x0042)));
+        assert_eq!(nib.update_id, 0x00);
+        assert_eq!(nib.pan_id, PanId(0x1234));
+        assert_eq!(manager.pending_pan_id(), Some(PanId(0x0042)));
+        assert!(manager.conflicting_pan_ids().is_empty());
+
+        // Conflicts reported meanwhile wait for the switch.
+        manager.report_indication(&nib, &report(EXTENDED_PAN_ID, &[0x0042]));
+        assert!(manager.resolve_conflict(&mut nib, 1000, 0).is_none());
+
+        let switch_ms = 1000 + u64::from(nib.broadcast_delivery_time_ms);
+        assert_eq!(manager.poll(&mut nib, switch_ms - 1), None);
+        assert_eq!(nib.pan_id, PanId(0x1234));
+        assert_eq!(manager.poll(&mut nib, switch_ms), Some(PanId(0x0042)));
+        assert_eq!(nib.pan_id, PanId(0x0042));
+        assert_eq!(manager.pending_pan_id(), None);
+        assert!(manager.resolve_conflict(&mut nib, switch_ms, 0).is_some());
+    }
+
+    #[test]
+    fn only_newer_updates_of_our_network_are_followed() {
+        let mut manager_nib = nib();
+        manager_nib.update_id = 0xfe;
+        let mut manager = NetworkManager::new();
+        manager.report_indication(&manager_nib, &report(EXTENDED_PAN_ID, &[0x1234]));
+        let frame = manager.resolve_conflict(&mut manager_nib, 0, 0x0042).unwrap();
+
+        let mut device_nib = nib();
+        device_nib.update_id = 0xfe;
+        assert_eq!(network_update_indication(&mut device_nib, frame.command::<NetworkUpdate>().unwrap()), Some(PanId(0x0042)));
+        assert_eq!(device_nib.update_id, 0xff);
+        assert_eq!(network_update_indication(&mut device_nib, frame.command::<NetworkUpdate>().unwrap()), None);
+
+        let mut other = nib();
+        other.extended_pan_id = ExtendedPanId(1);
+        other.update_id = 0xfe;
+        assert_eq!(network_update_indication(&mut other, frame.command::<NetworkUpdate>().unwrap()), None);
+        assert_eq!(other.update_id, 0xfe);
+
+        assert!(is_newer_update_id(0x00, 0xff));
+        assert!(!is_newer_update_id(0x80, 0x00));
+    }
+}
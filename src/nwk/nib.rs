@@ -0,0 +1,470 @@
+//! 3.5.2 NWK Information Base
+
+use core::convert::TryFrom;
+
+use crate::nwk::address::{
+    ExtendedPanId,
+    IEEEAddress,
+    PanId,
+    ShortAddress,
+};
+use crate::nwk::broadcast::{
+    DEFAULT_BROADCAST_DELIVERY_TIME_MS,
+    DEFAULT_MAX_BROADCAST_RETRIES,
+    DEFAULT_PASSIVE_ACK_TIMEOUT_MS,
+};
+use crate::nwk::commands::end_device_timeout_request::RequestedTimeout;
+use crate::nwk::commands::end_device_timeout_response::ParentInformation;
+use crate::nwk::commands::rejoin_request::CapabilityInformation;
+use crate::nwk::link_status::{
+    DEFAULT_LINK_STATUS_PERIOD_MS,
+    DEFAULT_ROUTER_AGE_LIMIT,
+};
+use crate::nwk::many_to_one::{
+    DEFAULT_CONCENTRATOR_DISCOVERY_TIME_MS,
+    DEFAULT_CONCENTRATOR_RADIUS,
+};
+use crate::nwk::neighbor_table::DEFAULT_END_DEVICE_TIMEOUT;
+use crate::nwk::source_route_table::DEFAULT_MAX_SOURCE_ROUTE;
+
+/// Default of nwkMaxChildren.
+pub const DEFAULT_MAX_CHILDREN: u8 = 0x14;
+/// Default of nwkMaxDepth.
+pub const DEFAULT_MAX_DEPTH: u8 = 0x0f;
+/// Default of nwkMaxRouters.
+pub const DEFAULT_MAX_ROUTERS: u8 = 0x05;
+/// Default of nwkStackProfile, the Zigbee PRO stack profile.
+pub const DEFAULT_STACK_PROFILE: u8 = 0x02;
+/// Default of nwkSecurityLevel, ENC-MIC-32.
+pub const DEFAULT_SECURITY_LEVEL: u8 = 0x05;
+/// Default of nwkRouteDiscoveryRetriesPermitted, nwkcDiscoveryRetryLimit.
+pub const DEFAULT_ROUTE_DISCOVERY_RETRIES_PERMITTED: u8 = 0x03;
+/// Default of nwkTransactionPersistenceTime in superframe periods.
+pub const DEFAULT_TRANSACTION_PERSISTENCE_TIME: u16 = 0x01f4;
+
+/// Table 3.58 NIB Attributes
+///
+/// The tables of the NIB are kept by `NeighborTable`, `RoutingTable` and friends and
+/// have no attribute here.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NIBAttribute {
+    PanId = 0x80,
+    SequenceNumber = 0x81,
+    PassiveAckTimeout = 0x82,
+    MaxBroadcastRetries = 0x83,
+    MaxChildren = 0x84,
+    MaxDepth = 0x85,
+    MaxRouters = 0x86,
+    BroadcastDeliveryTime = 0x88,
+    ReportConstantCost = 0x89,
+    RouteDiscoveryRetriesPermitted = 0x8a,
+    TxTotal = 0x8d,
+    SymLink = 0x8e,
+    CapabilityInformation = 0x8f,
+    UseTreeRouting = 0x91,
+    ManagerAddress = 0x92,
+    MaxSourceRoute = 0x93,
+    UpdateId = 0x94,
+    TransactionPersistenceTime = 0x95,
+    NetworkAddress = 0x96,
+    StackProfile = 0x97,
+    ExtendedPanId = 0x9a,
+    UseMulticast = 0x9b,
+    IsConcentrator = 0x9d,
+    ConcentratorRadius = 0x9e,
+    ConcentratorDiscoveryTime = 0x9f,
+    SecurityLevel = 0xa0,
+    ActiveKeySequenceNumber = 0xa2,
+    AllFresh = 0xa3,
+    SecureAllFrames = 0xa5,
+    LinkStatusPeriod = 0xa6,
+    RouterAgeLimit = 0xa7,
+    LeaveRequestAllowed = 0xaa,
+    ParentInformation = 0xab,
+    EndDeviceTimeoutDefault = 0xac,
+    LeaveRequestWithoutRejoinAllowed = 0xad,
+    IEEEAddress = 0xae,
+}
+
+impl NIBAttribute {
+    pub const ALL: [NIBAttribute; 36] = [
+        NIBAttribute::PanId,
+        NIBAttribute::SequenceNumber,
+        NIBAttribute::PassiveAckTimeout,
+        NIBAttribute::MaxBroadcastRetries,
+        NIBAttribute::MaxChildren,
+        NIBAttribute::MaxDepth,
+        NIBAttribute::MaxRouters,
+        NIBAttribute::BroadcastDeliveryTime,
+        NIBAttribute::ReportConstantCost,
+        NIBAttribute::RouteDiscoveryRetriesPermitted,
+        NIBAttribute::TxTotal,
+        NIBAttribute::SymLink,
+        NIBAttribute::CapabilityInformation,
+        NIBAttribute::UseTreeRouting,
+        NIBAttribute::ManagerAddress,
+        NIBAttribute::MaxSourceRoute,
+        NIBAttribute::UpdateId,
+        NIBAttribute::TransactionPersistenceTime,
+        NIBAttribute::NetworkAddress,
+        NIBAttribute::StackProfile,
+        NIBAttribute::ExtendedPanId,
+        NIBAttribute::UseMulticast,
+        NIBAttribute::IsConcentrator,
+        NIBAttribute::ConcentratorRadius,
+        NIBAttribute::ConcentratorDiscoveryTime,
+        NIBAttribute::SecurityLevel,
+        NIBAttribute::ActiveKeySequenceNumber,
+        NIBAttribute::AllFresh,
+        NIBAttribute::SecureAllFrames,
+        NIBAttribute::LinkStatusPeriod,
+        NIBAttribute::RouterAgeLimit,
+        NIBAttribute::LeaveRequestAllowed,
+        NIBAttribute::ParentInformation,
+        NIBAttribute::EndDeviceTimeoutDefault,
+        NIBAttribute::LeaveRequestWithoutRejoinAllowed,
+        NIBAttribute::IEEEAddress,
+    ];
+
+    /// Whether the attribute describes the network the device joined and has to survive
+    /// a reset, see `NIB::save` and `NIB::restore`.
+    pub fn is_persistent(&self) -> bool {
+        matches!(
+            self,
+            NIBAttribute::PanId
+                | NIBAttribute::MaxDepth
+                | NIBAttribute::ManagerAddress
+                | NIBAttribute::UpdateId
+                | NIBAttribute::NetworkAddress
+                | NIBAttribute::StackProfile
+                | NIBAttribute::ExtendedPanId
+                | NIBAttribute::IsConcentrator
+                | NIBAttribute::SecurityLevel
+                | NIBAttribute::ActiveKeySequenceNumber
+                | NIBAttribute::IEEEAddress
+        )
+    }
+}
+
+impl TryFrom<u8> for NIBAttribute {
+    type Error = NIBStatus;
+
+    fn try_from(identifier: u8) -> Result<Self, Self::Error> {
+        NIBAttribute::ALL
+            .iter()
+            .copied()
+            .find(|attribute| *attribute as u8 == identifier)
+            .ok_or(NIBStatus::UnsupportedAttribute)
+    }
+}
+
+/// Value of a NIB attribute. Times are in milliseconds, like everywhere else in the NWK
+/// layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NIBValue {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    ShortAddress(ShortAddress),
+    IEEEAddress(IEEEAddress),
+    PanId(PanId),
+    ExtendedPanId(ExtendedPanId),
+}
+
+/// 3.2.2.27 NLME-GET.confirm and 3.2.2.29 NLME-SET.confirm Status
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NIBStatus {
+    UnsupportedAttribute,
+    InvalidParameter,
+}
+
+/// Non-volatile storage for the persistent attributes of the NIB, e.g. a flash page.
+pub trait NIBStorage {
+    fn store(&mut self, attribute: NIBAttribute, value: NIBValue);
+    fn load(&mut self, attribute: NIBAttribute) -> Option<NIBValue>;
+}
+
+/// NWK Information Base
+///
+/// The attributes the application configures and inspects through NLME-GET and
+/// NLME-SET. `NetworkManager` works on the NIB directly, other components such as
+/// `LinkStatusScheduler` are configured from these values.
+#[derive(Clone)]
+pub struct NIB {
+    /// nwkPANId
+    pub pan_id: PanId,
+    /// nwkSequenceNumber
+    pub sequence_number: u8,
+    /// nwkPassiveAckTimeout
+    pub passive_ack_timeout_ms: u32,
+    /// nwkMaxBroadcastRetries
+    pub max_broadcast_retries: u8,
+    /// nwkMaxChildren
+    pub max_children: u8,
+    /// nwkMaxDepth
+    pub max_depth: u8,
+    /// nwkMaxRouters
+    pub max_routers: u8,
+    /// nwkNetworkBroadcastDeliveryTime
+    pub broadcast_delivery_time_ms: u32,
+    /// nwkReportConstantCost
+    pub report_constant_cost: bool,
+    /// nwkRouteDiscoveryRetriesPermitted
+    pub route_discovery_retries_permitted: u8,
+    /// nwkTxTotal
+    pub tx_total: u16,
+    /// nwkSymLink
+    pub sym_link: bool,
+    /// nwkCapabilityInformation
+    pub capability_information: CapabilityInformation,
+    /// nwkUseTreeRouting
+    pub use_tree_routing: bool,
+    /// nwkManagerAddr
+    pub manager_address: ShortAddress,
+    /// nwkMaxSourceRoute
+    pub max_source_route: u8,
+    /// nwkUpdateId
+    pub update_id: u8,
+    /// nwkTransactionPersistenceTime
+    pub transaction_persistence_time: u16,
+    /// nwkNetworkAddress, 0xffff while the device is not joined.
+    pub network_address: ShortAddress,
+    /// nwkStackProfile
+    pub stack_profile: u8,
+    /// nwkExtendedPANID
+    pub extended_pan_id: ExtendedPanId,
+    /// nwkUseMulticast
+    pub use_multicast: bool,
+    /// nwkIsConcentrator
+    pub is_concentrator: bool,
+    /// nwkConcentratorRadius
+    pub concentrator_radius: u8,
+    /// nwkConcentratorDiscoveryTime
+    pub concentrator_discovery_time_ms: u32,
+    /// nwkSecurityLevel
+    pub security_level: u8,
+    /// nwkActiveKeySeqNumber
+    pub active_key_sequence_number: u8,
+    /// nwkAllFresh
+    pub all_fresh: bool,
+    /// nwkSecureAllFrames
+    pub secure_all_frames: bool,
+    /// nwkLinkStatusPeriod
+    pub link_status_period_ms: u32,
+    /// nwkRouterAgeLimit
+    pub router_age_limit: u8,
+    /// nwkLeaveRequestAllowed
+    pub leave_request_allowed: bool,
+    /// nwkParentInformation
+    pub parent_information: ParentInformation,
+    /// nwkEndDeviceTimeoutDefault
+    pub end_device_timeout_default: RequestedTimeout,
+    /// nwkLeaveRequestWithoutRejoinAllowed
+    pub leave_request_without_rejoin_allowed: bool,
+    /// nwkIeeeAddress
+    pub ieee_address: IEEEAddress,
+}
+
+impl NIB {
+    /// The NIB with the defaults of table 3.58, `random` seeds nwkSequenceNumber.
+    pub fn new(ieee_address: IEEEAddress, random: u16) -> Self {
+        Self {
+            pan_id: PanId::BROADCAST,
+            sequence_number: random as u8,
+            passive_ack_timeout_ms: DEFAULT_PASSIVE_ACK_TIMEOUT_MS,
+            max_broadcast_retries: DEFAULT_MAX_BROADCAST_RETRIES,
+            max_children: DEFAULT_MAX_CHILDREN,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_routers: DEFAULT_MAX_ROUTERS,
+            broadcast_delivery_time_ms: DEFAULT_BROADCAST_DELIVERY_TIME_MS,
+            report_constant_cost: false,
+            route_discovery_retries_permitted: DEFAULT_ROUTE_DISCOVERY_RETRIES_PERMITTED,
+            tx_total: 0,
+            sym_link: true,
+            capability_information: CapabilityInformation::empty(),
+            use_tree_routing: false,
+            manager_address: ShortAddress::COORDINATOR,
+            max_source_route: DEFAULT_MAX_SOURCE_ROUTE as u8,
+            update_id: 0,
+            transaction_persistence_time: DEFAULT_TRANSACTION_PERSISTENCE_TIME,
+            network_address: ShortAddress(0xffff),
+            stack_profile: DEFAULT_STACK_PROFILE,
+            extended_pan_id: ExtendedPanId(0),
+            use_multicast: true,
+            is_concentrator: false,
+            concentrator_radius: DEFAULT_CONCENTRATOR_RADIUS,
+            concentrator_discovery_time_ms: DEFAULT_CONCENTRATOR_DISCOVERY_TIME_MS,
+            security_level: DEFAULT_SECURITY_LEVEL,
+            active_key_sequence_number: 0,
+            all_fresh: true,
+            secure_all_frames: true,
+            link_status_period_ms: DEFAULT_LINK_STATUS_PERIOD_MS,
+            router_age_limit: DEFAULT_ROUTER_AGE_LIMIT,
+            leave_request_allowed: true,
+            parent_information: ParentInformation::empty(),
+            end_device_timeout_default: DEFAULT_END_DEVICE_TIMEOUT,
+            leave_request_without_rejoin_allowed: true,
+            ieee_address,
+        }
+    }
+
+    /// Returns the current value of nwkSequenceNumber and increments it.
+    pub fn next_sequence_number(&mut self) -> u8 {
+        let sequence_number = self.sequence_number;
+        self.sequence_number = sequence_number.wrapping_add(1);
+        sequence_number
+    }
+
+    /// 3.2.2.26 NLME-GET.request
+    pub fn get(&self, attribute: NIBAttribute) -> NIBValue {
+        match attribute {
+            NIBAttribute::PanId => NIBValue::PanId(self.pan_id),
+            NIBAttribute::SequenceNumber => NIBValue::U8(self.sequence_number),
+            NIBAttribute::PassiveAckTimeout => NIBValue::U32(self.passive_ack_timeout_ms),
+            NIBAttribute::MaxBroadcastRetries => NIBValue::U8(self.max_broadcast_retries),
+            NIBAttribute::MaxChildren => NIBValue::U8(self.max_children),
+            NIBAttribute::MaxDepth => NIBValue::U8(self.max_depth),
+            NIBAttribute::MaxRouters => NIBValue::U8(self.max_routers),
+            NIBAttribute::BroadcastDeliveryTime => NIBValue::U32(self.broadcast_delivery_time_ms),
+            NIBAttribute::ReportConstantCost => NIBValue::Bool(self.report_constant_cost),
+            NIBAttribute::RouteDiscoveryRetriesPermitted => NIBValue::U8(self.route_discovery_retries_permitted),
+            NIBAttribute::TxTotal => NIBValue::U16(self.tx_total),
+            NIBAttribute::SymLink => NIBValue::Bool(self.sym_link),
+            NIBAttribute::CapabilityInformation => NIBValue::U8(self.capability_information.bits()),
+            NIBAttribute::UseTreeRouting => NIBValue::Bool(self.use_tree_routing),
+            NIBAttribute::ManagerAddress => NIBValue::ShortAddress(self.manager_address),
+            NIBAttribute::MaxSourceRoute => NIBValue::U8(self.max_source_route),
+            NIBAttribute::UpdateId => NIBValue::U8(self.update_id),
+            NIBAttribute::TransactionPersistenceTime => NIBValue::U16(self.transaction_persistence_time),
+            NIBAttribute::NetworkAddress => NIBValue::ShortAddress(self.network_address),
+            NIBAttribute::StackProfile => NIBValue::U8(self.stack_profile),
+            NIBAttribute::ExtendedPanId => NIBValue::ExtendedPanId(self.extended_pan_id),
+            NIBAttribute::UseMulticast => NIBValue::Bool(self.use_multicast),
+            NIBAttribute::IsConcentrator => NIBValue::Bool(self.is_concentrator),
+            NIBAttribute::ConcentratorRadius => NIBValue::U8(self.concentrator_radius),
+            NIBAttribute::ConcentratorDiscoveryTime => NIBValue::U32(self.concentrator_discovery_time_ms),
+            NIBAttribute::SecurityLevel => NIBValue::U8(self.security_level),
+            NIBAttribute::ActiveKeySequenceNumber => NIBValue::U8(self.active_key_sequence_number),
+            NIBAttribute::AllFresh => NIBValue::Bool(self.all_fresh),
+            NIBAttribute::SecureAllFrames => NIBValue::Bool(self.secure_all_frames),
+            NIBAttribute::LinkStatusPeriod => NIBValue::U32(self.link_status_period_ms),
+            NIBAttribute::RouterAgeLimit => NIBValue::U8(self.router_age_limit),
+            NIBAttribute::LeaveRequestAllowed => NIBValue::Bool(self.leave_request_allowed),
+            NIBAttribute::ParentInformation => NIBValue::U8(self.parent_information.bits()),
+            NIBAttribute::EndDeviceTimeoutDefault => NIBValue::U8(self.end_device_timeout_default as u8),
+            NIBAttribute::LeaveRequestWithoutRejoinAllowed => {
+                NIBValue::Bool(self.leave_request_without_rejoin_allowed)
+            },
+            NIBAttribute::IEEEAddress => NIBValue::IEEEAddress(self.ieee_address),
+        }
+    }
+
+    /// 3.2.2.28 NLME-SET.request
+    ///
+    /// Values of the wrong type or outside the range of table 3.58 are rejected with
+    /// `NIBStatus::InvalidParameter` and leave the attribute untouched.
+    pub fn set(&mut self, attribute: NIBAttribute, value: NIBValue) -> Result<(), NIBStatus> {
+        match (attribute, value) {
+            (NIBAttribute::PanId, NIBValue::PanId(pan_id)) if pan_id.0 <= PanId::MAX.0 || pan_id == PanId::BROADCAST => {
+                self.pan_id = pan_id
+            },
+            (NIBAttribute::SequenceNumber, NIBValue::U8(value)) => self.sequence_number = value,
+            (NIBAttribute::PassiveAckTimeout, NIBValue::U32(value)) if value <= 10_000 => {
+                self.passive_ack_timeout_ms = value
+            },
+            (NIBAttribute::MaxBroadcastRetries, NIBValue::U8(value)) if value <= 5 => self.max_broadcast_retries = value,
+            (NIBAttribute::MaxChildren, NIBValue::U8(value)) => self.max_children = value,
+            (NIBAttribute::MaxDepth, NIBValue::U8(value)) if value <= 0x0f => self.max_depth = value,
+            (NIBAttribute::MaxRouters, NIBValue::U8(value)) if value >= 1 => self.max_routers = value,
+            (NIBAttribute::BroadcastDeliveryTime, NIBValue::U32(value)) => self.broadcast_delivery_time_ms = value,
+            (NIBAttribute::ReportConstantCost, NIBValue::Bool(value)) => self.report_constant_cost = value,
+            (NIBAttribute::RouteDiscoveryRetriesPermitted, NIBValue::U8(value)) if value <= 0x03 => {
+                self.route_discovery_retries_permitted = value
+            },
+            (NIBAttribute::TxTotal, NIBValue::U16(value)) => self.tx_total = value,
+            (NIBAttribute::SymLink, NIBValue::Bool(value)) => self.sym_link = value,
+            (NIBAttribute::CapabilityInformation, NIBValue::U8(value)) => {
+                self.capability_information = CapabilityInformation::from_bits(value).ok_or(NIBStatus::InvalidParameter)?
+            },
+            (NIBAttribute::UseTreeRouting, NIBValue::Bool(value)) => self.use_tree_routing = value,
+            (NIBAttribute::ManagerAddress, NIBValue::ShortAddress(address)) if address.is_unicast() => {
+                self.manager_address = address
+            },
+            (NIBAttribute::MaxSourceRoute, NIBValue::U8(value)) => self.max_source_route = value,
+            (NIBAttribute::UpdateId, NIBValue::U8(value)) => self.update_id = value,
+            (NIBAttribute::TransactionPersistenceTime, NIBValue::U16(value)) => self.transaction_persistence_time = value,
+            (NIBAttribute::NetworkAddress, NIBValue::ShortAddress(address))
+                if address.is_unicast() || address == ShortAddress(0xffff) =>
+            {
+                self.network_address = address
+            },
+            (NIBAttribute::StackProfile, NIBValue::U8(value)) if value <= 0x0f => self.stack_profile = value,
+            (NIBAttribute::ExtendedPanId, NIBValue::ExtendedPanId(extended_pan_id)) => {
+                self.extended_pan_id = extended_pan_id
+            },
+            (NIBAttribute::UseMulticast, NIBValue::Bool(value)) => self.use_multicast = value,
+            (NIBAttribute::IsConcentrator, NIBValue::Bool(value)) => self.is_concentrator = value,
+            (NIBAttribute::ConcentratorRadius, NIBValue::U8(value)) => self.concentrator_radius = value,
+            (NIBAttribute::ConcentratorDiscoveryTime, NIBValue::U32(value)) if value <= 0xff * 1000 => {
+                self.concentrator_discovery_time_ms = value
+            },
+            (NIBAttribute::SecurityLevel, NIBValue::U8(value)) if value <= 0x07 => self.security_level = value,
+            (NIBAttribute::ActiveKeySequenceNumber, NIBValue::U8(value)) => self.active_key_sequence_number = value,
+            (NIBAttribute::AllFresh, NIBValue::Bool(value)) => self.all_fresh = value,
+            (NIBAttribute::SecureAllFrames, NIBValue::Bool(value)) => self.secure_all_frames = value,
+            (NIBAttribute::LinkStatusPeriod, NIBValue::U32(value)) if value <= 0xff * 1000 => {
+                self.link_status_period_ms = value
+            },
+            (NIBAttribute::RouterAgeLimit, NIBValue::U8(value)) => self.router_age_limit = value,
+            (NIBAttribute::LeaveRequestAllowed, NIBValue::Bool(value)) => self.leave_request_allowed = value,
+            (NIBAttribute::ParentInformation, NIBValue::U8(value)) => {
+                self.parent_information = ParentInformation::from_bits(value).ok_or(NIBStatus::InvalidParameter)?
+            },
+            (NIBAttribute::EndDeviceTimeoutDefault, NIBValue::U8(value)) => {
+                self.end_device_timeout_default = RequestedTimeout::from_u8(value).ok_or(NIBStatus::InvalidParameter)?
+            },
+            (NIBAttribute::LeaveRequestWithoutRejoinAllowed, NIBValue::Bool(value)) => {
+                self.leave_request_without_rejoin_allowed = value
+            },
+            (NIBAttribute::IEEEAddress, NIBValue::IEEEAddress(address)) => self.ieee_address = address,
+            _ => return Err(NIBStatus::InvalidParameter),
+        }
+        Ok(())
+    }
+
+    /// Writes all persistent attributes to `storage`.
+    pub fn save(&self, storage: &mut impl NIBStorage) {
+        for attribute in NIBAttribute::ALL.iter().filter(|attribute| attribute.is_persistent()) {
+            storage.store(*attribute, self.get(*attribute));
+        }
+    }
+
+    /// Sets all persistent attributes found in `storage`, the others keep their value.
+    ///
+    /// If `set` rejects any stored value, none of them is applied.
+    pub fn restore(&mut self, storage: &mut impl NIBStorage) -> Result<(), NIBStatus> {
+        let mut restored = self.clone();
+        for attribute in NIBAttribute::ALL.iter().filter(|attribute| attribute.is_persistent()) {
+            if let Some(value) = storage.load(*attribute) {
+                restored.set(*attribute, value)?;
+            }
+        }
+        *self = restored;
+        Ok(())
+    }
+
+    /// Sets the attribute and, if it is persistent, writes it to `storage` as well.
+    pub fn set_and_store(
+        &mut self,
+        attribute: NIBAttribute,
+        value: NIBValue,
+        storage: &mut impl NIBStorage,
+    ) -> Result<(), NIBStatus> {
+        self.set(attribute, value)?;
+        if attribute.is_persistent() {
+            storage.store(attribute, value);
+        }
+        Ok(())
+    }
+}
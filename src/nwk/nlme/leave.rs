@@ -0,0 +1,249 @@
+use crate::nwk::address::{
+    IEEEAddress,
+    ShortAddress,
+    BROADCAST_RX_ON_WHEN_IDLE,
+};
+use crate::nwk::commands::leave::Leave;
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::neighbor_table::{
+    NeighborTable,
+    Relationship,
+};
+use crate::nwk::payload::NWKCommandFrame;
+use crate::state::State;
+
+/// 3.2.2.16 NLME-LEAVE.request
+pub struct LeaveRequest {
+    /// The device to remove from the network, `None` if the device itself leaves.
+    pub device_address: Option<IEEEAddress>,
+    pub remove_children: bool,
+    pub rejoin: bool,
+}
+
+/// 3.2.2.18 NLME-LEAVE.confirm Status
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LeaveStatus {
+    InvalidRequest,
+    UnknownDevice,
+}
+
+/// 3.2.2.17 NLME-LEAVE.indication
+#[derive(Debug)]
+pub struct LeaveIndication {
+    /// The device which left the network, `None` if this device was asked to leave.
+    pub device_address: Option<IEEEAddress>,
+    pub remove_children: bool,
+    pub rejoin: bool,
+}
+
+/// 3.6.1.10.1 Method for a Device to Initiate Its Own Removal from the Network
+/// 3.6.1.10.2 Method for a Device to Remove Its Child from the Network
+///
+/// Returns the leave command NPDU which has to be handed to the MAC layer.
+pub fn leave_request<const N: usize>(
+    state: &mut State,
+    neighbor_table: &mut NeighborTable<N>,
+    request: &LeaveRequest,
+    source_address: ShortAddress,
+    source_ieee_address: IEEEAddress,
+    sequence_number: u8,
+) -> Result<NPDUFrame, LeaveStatus> {
+    if !state.bdbNodeIsOnANetwork {
+        return Err(LeaveStatus::InvalidRequest);
+    }
+
+    match request.device_address {
+        None => {
+            let mut frame = command_frame(
+                BROADCAST_RX_ON_WHEN_IDLE,
+                source_address,
+                1,
+                sequence_number,
+                NWKCommandFrame::Leave(Leave {
+                    rejoin: request.rejoin,
+                    request: false,
+                    remove_children: request.remove_children,
+                }),
+            );
+            frame.source_ieee_address = Some(source_ieee_address);
+            frame.update_control();
+            // Leaving invalidates every neighbor relationship, children included. The
+            // children themselves are told to leave through the remove children flag.
+            neighbor_table.clear();
+            state.bdbNodeIsOnANetwork = false;
+            Ok(frame)
+        },
+        Some(device_address) => {
+            let child_address = match neighbor_table.get_by_extended_address(&device_address) {
+                Some(entry) if entry.is_child() => entry.network_address,
+                _ => return Err(LeaveStatus::UnknownDevice),
+            };
+            neighbor_table.remove(&child_address);
+            let mut frame = command_frame(
+                child_address,
+                source_address,
+                1,
+                sequence_number,
+                NWKCommandFrame::Leave(Leave {
+                    rejoin: request.rejoin,
+                    request: true,
+                    remove_children: request.remove_children,
+                }),
+            );
+            frame.destination_ieee_address = Some(device_address);
+            frame.source_ieee_address = Some(source_ieee_address);
+            frame.update_control();
+            Ok(frame)
+        },
+    }
+}
+
+/// 3.6.1.10.3 Upon Receipt of the Leave Command Frame
+///
+/// If the indication carries no device address, this device was asked to leave by its
+/// parent and the next higher layer has to issue a `leave_request` for itself.
+pub fn leave_indication<const N: usize>(
+    neighbor_table: &mut NeighborTable<N>,
+    frame: &NPDUFrame,
+    leave: &Leave,
+) -> Option<LeaveIndication> {
+    let source_address = frame.source_address;
+
+    if leave.request {
+        match neighbor_table.get(&source_address) {
+            Some(entry) if entry.relationship == Relationship::Parent => Some(LeaveIndication {
+                device_address: None,
+                remove_children: leave.remove_children,
+                rejoin: leave.rejoin,
+            }),
+            _ => None,
+        }
+    } else {
+        let entry = neighbor_table.remove(&source_address);
+        let device_address = frame.source_ieee_address
+            .or_else(|| entry.and_then(|entry| entry.extended_address))?;
+        Some(LeaveIndication {
+            device_address: Some(device_address),
+            remove_children: leave.remove_children,
+            rejoin: leave.rejoin,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::neighbor_table::NeighborTableEntry;
+    use crate::state::DeviceType;
+
+    const OWN_ADDRESS: ShortAddress = ShortAddress(0x1234);
+    const OWN_IEEE_ADDRESS: IEEEAddress = IEEEAddress(0x0012_4b00_0000_0001);
+    const CHILD_IEEE_ADDRESS: IEEEAddress = IEEEAddress(0x0012_4b00_0000_0002);
+
+    fn neighbor_table() -> NeighborTable<4> {
+        let mut neighbor_table = NeighborTable::new();
+        for entry in [
+            NeighborTableEntry::new(ShortAddress(0x0000), None, DeviceType::Coordinator, Relationship::Parent),
+            NeighborTableEntry::new(ShortAddress(0x2001), Some(CHILD_IEEE_ADDRESS), DeviceType::EndDevice, Relationship::Child),
+            NeighborTableEntry::new(ShortAddress(0x3001), None, DeviceType::Router, Relationship::Sibling),
+        ] {
+            neighbor_table.insert(entry).unwrap();
+        }
+        neighbor_table
+    }
+
+    fn on_network() -> State {
+        let mut state = State::new();
+        state.bdbNodeIsOnANetwork = true;
+        state
+    }
+
+    fn leave(device_address: Option<IEEEAddress>) -> LeaveRequest {
+        LeaveRequest { device_address, remove_children: true, rejoin: false }
+    }
+
+    fn received_leave(
+        destination_address: ShortAddress,
+        source_address: ShortAddress,
+        source_ieee_address: IEEEAddress,
+        leave: Leave,
+    ) -> NPDUFrame {
+        let mut frame = command_frame(destination_address, source_address, 1, 1, NWKCommandFrame::Leave(leave));
+        frame.source_ieee_address = Some(source_ieee_address);
+        frame.update_control();
+        frame
+    }
+
+    #[test]
+    fn leaving_clears_neighbors_and_network_state() {
+        let mut state = on_network();
+        let mut neighbor_table = neighbor_table();
+        let frame = leave_request(&mut state, &mut neighbor_table, &leave(None), OWN_ADDRESS, OWN_IEEE_ADDRESS, 7)
+            .unwrap();
+
+        assert!(!state.bdbNodeIsOnANetwork);
+        assert!(neighbor_table.is_empty());
+        assert_eq!(frame.destination_address, BROADCAST_RX_ON_WHEN_IDLE);
+        assert_eq!(frame.source_ieee_address, Some(OWN_IEEE_ADDRESS));
+        assert_eq!((frame.radius, frame.sequence_number), (1, 7));
+        let command = frame.command::<Leave>().unwrap();
+        assert!(!command.request && command.remove_children && !command.rejoin);
+    }
+
+    #[test]
+    fn leave_requires_a_network() {
+        let mut state = State::new();
+        let mut neighbor_table = neighbor_table();
+        let status = leave_request(&mut state, &mut neighbor_table, &leave(None), OWN_ADDRESS, OWN_IEEE_ADDRESS, 7).unwrap_err();
+        assert_eq!(status, LeaveStatus::InvalidRequest);
+        assert_eq!(neighbor_table.len(), 3);
+    }
+
+    #[test]
+    fn removing_a_child() {
+        let mut state = on_network();
+        let mut neighbor_table = neighbor_table();
+        let request = leave(Some(CHILD_IEEE_ADDRESS));
+        let frame = leave_request(&mut state, &mut neighbor_table, &request, OWN_ADDRESS, OWN_IEEE_ADDRESS, 7)
+            .unwrap();
+
+        assert!(state.bdbNodeIsOnANetwork);
+        assert!(neighbor_table.get(&ShortAddress(0x2001)).is_none());
+        assert_eq!(neighbor_table.len(), 2);
+        assert_eq!(frame.destination_address, ShortAddress(0x2001));
+        assert_eq!(frame.destination_ieee_address, Some(CHILD_IEEE_ADDRESS));
+        assert!(frame.command::<Leave>().unwrap().request);
+
+        // The child is gone now, and only children can be asked to leave.
+        let status = leave_request(&mut state, &mut neighbor_table, &request, OWN_ADDRESS, OWN_IEEE_ADDRESS, 8).unwrap_err();
+        assert_eq!(status, LeaveStatus::UnknownDevice);
+    }
+
+    #[test]
+    fn indication_of_a_leaving_neighbor() {
+        let mut neighbor_table = neighbor_table();
+        let leave = Leave { rejoin: true, request: false, remove_children: false };
+        let frame = received_leave(BROADCAST_RX_ON_WHEN_IDLE, ShortAddress(0x2001), CHILD_IEEE_ADDRESS, leave);
+        let indication = leave_indication(&mut neighbor_table, &frame, frame.command::<Leave>().unwrap()).unwrap();
+
+        assert_eq!(indication.device_address, Some(CHILD_IEEE_ADDRESS));
+        assert!(indication.rejoin);
+        assert!(neighbor_table.get(&ShortAddress(0x2001)).is_none());
+    }
+
+    #[test]
+    fn leave_requests_are_only_accepted_from_the_parent() {
+        let mut neighbor_table = neighbor_table();
+        let leave = || Leave { rejoin: false, request: true, remove_children: true };
+
+        let frame = received_leave(OWN_ADDRESS, ShortAddress(0x3001), IEEEAddress(3), leave());
+        assert!(leave_indication(&mut neighbor_table, &frame, frame.command::<Leave>().unwrap()).is_none());
+
+        let frame = received_leave(OWN_ADDRESS, ShortAddress(0x0000), IEEEAddress(1), leave());
+        let indication = leave_indication(&mut neighbor_table, &frame, frame.command::<Leave>().unwrap()).unwrap();
+        assert_eq!(indication.device_address, None);
+        assert!(indication.remove_children);
+        assert_eq!(neighbor_table.len(), 3);
+    }
+}
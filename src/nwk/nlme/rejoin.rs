@@ -0,0 +1,313 @@
+use crate::nwk::address::{
+    IEEEAddress,
+    ShortAddress,
+};
+use crate::nwk::commands::rejoin_request::{
+    CapabilityInformation,
+    RejoinRequest,
+};
+use crate::nwk::commands::rejoin_response::{
+    RejoinResponse,
+    RejoinStatus,
+};
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::neighbor_table::{
+    NeighborTable,
+    NeighborTableEntry,
+    Relationship,
+};
+use crate::nwk::payload::NWKCommandFrame;
+use crate::state::{
+    DeviceType,
+    State,
+};
+
+/// Why a rejoin failed on the child.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RejoinError {
+    /// The parent refused the device.
+    Refused(RejoinStatus),
+    /// The parent accepted the device but there is no room to record it in the neighbor table.
+    NeighborTableFull,
+}
+
+/// Highest address which may be handed out by stochastic address assignment.
+const MAX_STOCHASTIC_ADDRESS: u16 = 0xfff7;
+
+/// 3.6.1.4.3.1 Child Procedure
+///
+/// Builds the rejoin request a device sends to its prospective parent. `source_address`
+/// is the network address the device used before.
+pub fn rejoin_request(
+    parent_address: ShortAddress,
+    capability_information: CapabilityInformation,
+    source_address: ShortAddress,
+    source_ieee_address: IEEEAddress,
+    sequence_number: u8,
+) -> NPDUFrame {
+    let mut frame = command_frame(
+        parent_address,
+        source_address,
+        1,
+        sequence_number,
+        NWKCommandFrame::RejoinRequest(RejoinRequest {
+            capability_information,
+        }),
+    );
+    frame.source_ieee_address = Some(source_ieee_address);
+    frame.update_control();
+    frame
+}
+
+/// 3.6.1.4.3.1 Child Procedure
+///
+/// Processes the rejoin response of the parent. On success the parent is recorded in the
+/// neighbor table, the node is marked as being on a network and its new network address
+/// is returned.
+pub fn rejoin_confirm<const N: usize>(
+    state: &mut State,
+    neighbor_table: &mut NeighborTable<N>,
+    frame: &NPDUFrame,
+    response: &RejoinResponse,
+) -> Result<ShortAddress, RejoinError> {
+    if response.rejoin_status != RejoinStatus::Success {
+        return Err(RejoinError::Refused(response.rejoin_status));
+    }
+
+    let parent_address = frame.source_address;
+    let device_type = if parent_address.is_coordinator() {
+        DeviceType::Coordinator
+    } else {
+        DeviceType::Router
+    };
+    let previous_parent = neighbor_table.parent().map(|entry| entry.network_address);
+    if let Some(previous_parent) = previous_parent {
+        neighbor_table.remove(&previous_parent);
+    }
+    let parent = NeighborTableEntry::new(
+        parent_address,
+        frame.source_ieee_address,
+        device_type,
+        Relationship::Parent,
+    );
+    neighbor_table.insert(parent).map_err(|_| RejoinError::NeighborTableFull)?;
+
+    state.bdbNodeIsOnANetwork = true;
+    Ok(response.network_address)
+}
+
+fn is_free_address<const N: usize>(neighbor_table: &NeighborTable<N>, own_address: ShortAddress, address: ShortAddress) -> bool {
+    !address.is_coordinator()
+        && address.is_unicast()
+        && address != own_address
+        && neighbor_table.get(&address).is_none()
+}
+
+/// Stochastic address assignment, seeded with a `random` value.
+fn allocate_address<const N: usize>(neighbor_table: &NeighborTable<N>, own_address: ShortAddress, random: u16) -> ShortAddress {
+    let mut candidate = random % MAX_STOCHASTIC_ADDRESS + 1;
+    while !is_free_address(neighbor_table, own_address, ShortAddress(candidate)) {
+        candidate = candidate % MAX_STOCHASTIC_ADDRESS + 1;
+    }
+    ShortAddress(candidate)
+}
+
+/// 3.6.1.4.3.2 Parent Procedure
+///
+/// Decides whether a rejoining device is accepted as child. It keeps its previous network
+/// address unless that address is taken by another device, in which case a new one is
+/// assigned from `random`. Returns the rejoin response to send, or `None` if the request
+/// does not identify the device by its IEEE address.
+pub fn rejoin_indication<const N: usize>(
+    neighbor_table: &mut NeighborTable<N>,
+    frame: &NPDUFrame,
+    request: &RejoinRequest,
+    own_address: ShortAddress,
+    own_ieee_address: IEEEAddress,
+    random: u16,
+    sequence_number: u8,
+) -> Option<NPDUFrame> {
+    let child_ieee_address = frame.source_ieee_address?;
+    let previous_address = frame.source_address;
+
+    let known_address = neighbor_table.get_by_extended_address(&child_ieee_address)
+        .map(|entry| entry.network_address);
+    let network_address = match neighbor_table.get(&previous_address) {
+        Some(entry) if entry.extended_address == Some(child_ieee_address) => previous_address,
+        None if is_free_address(neighbor_table, own_address, previous_address) => previous_address,
+        _ => known_address.unwrap_or_else(|| allocate_address(neighbor_table, own_address, random)),
+    };
+
+    let capability_information = request.capability_information;
+    let mut child = NeighborTableEntry::new(
+        network_address,
+        Some(child_ieee_address),
+        if capability_information.contains(CapabilityInformation::DeviceType) {
+            DeviceType::Router
+        } else {
+            DeviceType::EndDevice
+        },
+        Relationship::Child,
+    );
+    child.rx_on_when_idle = capability_information.contains(CapabilityInformation::ReceiverOnWhenIdle);
+
+    if let Some(known_address) = known_address {
+        neighbor_table.remove(&known_address);
+    }
+    let rejoin_status = match neighbor_table.insert(child) {
+        Ok(()) => RejoinStatus::Success,
+        Err(_) => RejoinStatus::PanAtCapacity,
+    };
+
+    let mut frame = command_frame(
+        previous_address,
+        own_address,
+        1,
+        sequence_number,
+        NWKCommandFrame::RejoinResponse(RejoinResponse {
+            network_address: if rejoin_status == RejoinStatus::Success {
+                network_address
+            } else {
+                ShortAddress(0xffff)
+            },
+            rejoin_status,
+        }),
+    );
+    frame.destination_ieee_address = Some(child_ieee_address);
+    frame.source_ieee_address = Some(own_ieee_address);
+    frame.update_control();
+    Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT_ADDRESS: ShortAddress = ShortAddress(0x0001);
+    const PARENT_IEEE_ADDRESS: IEEEAddress = IEEEAddress(0x0012_4b00_0000_0001);
+    const CHILD_IEEE_ADDRESS: IEEEAddress = IEEEAddress(0x0012_4b00_0000_0002);
+
+    fn request(previous_address: ShortAddress) -> (NPDUFrame, RejoinRequest) {
+        let capability_information = CapabilityInformation::AllocateAddress;
+        let frame = rejoin_request(PARENT_ADDRESS, capability_information, previous_address, CHILD_IEEE_ADDRESS, 1);
+        (frame, RejoinRequest { capability_information })
+    }
+
+    fn indication<const N: usize>(neighbor_table: &mut NeighborTable<N>, previous_address: ShortAddress) -> RejoinResponse {
+        let (frame, request) = request(previous_address);
+        let response = rejoin_indication(neighbor_table, &frame, &request, PARENT_ADDRESS, PARENT_IEEE_ADDRESS, 0x1233, 2)
+            .unwrap();
+        assert_eq!(response.destination_address, previous_address);
+        assert_eq!(response.destination_ieee_address, Some(CHILD_IEEE_ADDRESS));
+        RejoinResponse { ..*response.command::<RejoinResponse>().unwrap() }
+    }
+
+    fn neighbor(network_address: u16, extended_address: u64, relationship: Relationship) -> NeighborTableEntry {
+        NeighborTableEntry::new(
+            ShortAddress(network_address),
+            Some(IEEEAddress(extended_address)),
+            DeviceType::EndDevice,
+            relationship,
+        )
+    }
+
+    #[test]
+    fn free_previous_address_is_kept() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        let response = indication(&mut neighbor_table, ShortAddress(0x796f));
+        assert!(response.rejoin_status == RejoinStatus::Success);
+        assert_eq!(response.network_address, ShortAddress(0x796f));
+        let child = neighbor_table.get(&ShortAddress(0x796f)).unwrap();
+        assert!(child.is_child() && child.extended_address == Some(CHILD_IEEE_ADDRESS));
+        assert!(!child.rx_on_when_idle);
+    }
+
+    #[test]
+    fn returning_child_keeps_its_address() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table.insert(neighbor(0x796f, CHILD_IEEE_ADDRESS.0, Relationship::Child)).unwrap();
+        let response = indication(&mut neighbor_table, ShortAddress(0x796f));
+        assert_eq!(response.network_address, ShortAddress(0x796f));
+        assert_eq!(neighbor_table.len(), 1);
+    }
+
+    #[test]
+    fn taken_address_is_reassigned() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table.insert(neighbor(0x796f, 0x0012_4b00_0000_0003, Relationship::Child)).unwrap();
+        let response = indication(&mut neighbor_table, ShortAddress(0x796f));
+        assert!(response.rejoin_status == RejoinStatus::Success);
+        assert_eq!(response.network_address, ShortAddress(0x1233 % MAX_STOCHASTIC_ADDRESS + 1));
+        assert!(neighbor_table.get(&ShortAddress(0x796f)).unwrap().extended_address != Some(CHILD_IEEE_ADDRESS));
+
+        // Neither our own nor any reserved address is handed out.
+        let response = indication(&mut neighbor_table, PARENT_ADDRESS);
+        assert_eq!(response.network_address, ShortAddress(0x1234));
+        let response = indication(&mut neighbor_table, ShortAddress(0xfffe));
+        assert_eq!(response.network_address, ShortAddress(0x1234));
+    }
+
+    #[test]
+    fn known_child_moving_to_a_taken_address_gets_its_known_address() {
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table.insert(neighbor(0x0100, CHILD_IEEE_ADDRESS.0, Relationship::Child)).unwrap();
+        neighbor_table.insert(neighbor(0x796f, 0x0012_4b00_0000_0003, Relationship::Child)).unwrap();
+        let response = indication(&mut neighbor_table, ShortAddress(0x796f));
+        assert_eq!(response.network_address, ShortAddress(0x0100));
+        assert_eq!(neighbor_table.len(), 2);
+    }
+
+    #[test]
+    fn full_neighbor_table_refuses_the_child() {
+        let mut neighbor_table = NeighborTable::<1>::new();
+        neighbor_table.insert(neighbor(0x0100, 0x0012_4b00_0000_0003, Relationship::Child)).unwrap();
+        let response = indication(&mut neighbor_table, ShortAddress(0x796f));
+        assert!(response.rejoin_status == RejoinStatus::PanAtCapacity);
+        assert_eq!(response.network_address, ShortAddress(0xffff));
+    }
+
+    #[test]
+    fn child_records_the_new_parent() {
+        let mut state = State::new();
+        let mut neighbor_table = NeighborTable::<4>::new();
+        neighbor_table.insert(neighbor(0x0002, 0x0012_4b00_0000_0004, Relationship::Parent)).unwrap();
+
+        let (frame, request) = request(ShortAddress(0x796f));
+        let mut parent_table = NeighborTable::<4>::new();
+        let response_frame = rejoin_indication(&mut parent_table, &frame, &request, PARENT_ADDRESS, PARENT_IEEE_ADDRESS, 0, 2)
+            .unwrap();
+        let response = response_frame.command::<RejoinResponse>().unwrap();
+
+        let address = rejoin_confirm(&mut state, &mut neighbor_table, &response_frame, response).unwrap();
+        assert_eq!(address, ShortAddress(0x796f));
+        assert!(state.bdbNodeIsOnANetwork);
+        let parent = neighbor_table.parent().unwrap();
+        assert_eq!(parent.network_address, PARENT_ADDRESS);
+        assert_eq!(parent.extended_address, Some(PARENT_IEEE_ADDRESS));
+        assert_eq!(neighbor_table.len(), 1);
+    }
+
+    #[test]
+    fn child_reports_a_refusal_and_a_full_neighbor_table() {
+        let mut state = State::new();
+        let (frame, request) = request(ShortAddress(0x796f));
+        let mut parent_table = NeighborTable::<4>::new();
+        let response_frame = rejoin_indication(&mut parent_table, &frame, &request, PARENT_ADDRESS, PARENT_IEEE_ADDRESS, 0, 2)
+            .unwrap();
+        let response = response_frame.command::<RejoinResponse>().unwrap();
+
+        let mut neighbor_table = NeighborTable::<1>::new();
+        neighbor_table.insert(neighbor(0x0100, 0x0012_4b00_0000_0003, Relationship::Child)).unwrap();
+        let error = rejoin_confirm(&mut state, &mut neighbor_table, &response_frame, response).unwrap_err();
+        assert_eq!(error, RejoinError::NeighborTableFull);
+        assert!(!state.bdbNodeIsOnANetwork);
+
+        let refused = RejoinResponse {
+            rejoin_status: RejoinStatus::PanAtCapacity,
+            ..*response
+        };
+        let error = rejoin_confirm(&mut state, &mut neighbor_table, &response_frame, &refused).unwrap_err();
+        assert_eq!(error, RejoinError::Refused(RejoinStatus::PanAtCapacity));
+    }
+}
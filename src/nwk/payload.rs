@@ -1,16 +1,119 @@
 use crate::nwk::commands::route_request::RouteRequest;
-use crate::serde::Serde;
-use crate::nwk::frame::SerdeError;
+use crate::nwk::commands::route_reply::RouteReply;
+use crate::nwk::commands::network_status::NetworkStatus;
+use crate::nwk::commands::leave::Leave;
+use crate::nwk::commands::route_record::RouteRecord;
+use crate::nwk::commands::rejoin_request::RejoinRequest;
+use crate::nwk::commands::rejoin_response::RejoinResponse;
+use crate::nwk::commands::link_status::LinkStatus;
+use crate::nwk::commands::network_report::NetworkReport;
+use crate::nwk::commands::network_update::NetworkUpdate;
+use crate::nwk::commands::end_device_timeout_request::EndDeviceTimeoutRequest;
+use crate::nwk::commands::end_device_timeout_response::EndDeviceTimeoutResponse;
+use crate::nwk::commands::link_power_delta::LinkPowerDelta;
+use crate::nwk::constants::MAX_NSDU_LENGTH;
+use crate::serde::{
+    Reader,
+    Serde,
+    SerdeError,
+    SerdeErrorKind,
+    Writer,
+};
 
 /// 3.3.2.1  Data Frame Format
+#[derive(Debug)]
 pub struct DataFrame {
+    pub data: heapless::Vec<u8, MAX_NSDU_LENGTH>,
+}
 
+impl DataFrame {
+    /// Takes everything left in the frame as the payload.
+    pub(crate) fn read_rest(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        let data = reader.read_rest();
+        Ok(DataFrame {
+            data: heapless::Vec::from_slice(data).map_err(|_| SerdeError::new(
+                SerdeErrorKind::CapacityExceeded { length: data.len(), capacity: MAX_NSDU_LENGTH },
+                offset,
+            ).context("payload"))?,
+        })
+    }
 }
 
 /// Table 3.40   NWK Command Frames
+#[derive(Debug)]
 pub enum NWKCommandFrame {
     // 0x01
     RouteRequest(RouteRequest),
+    // 0x02
+    RouteReply(RouteReply),
+    // 0x03
+    NetworkStatus(NetworkStatus),
+    // 0x04
+    Leave(Leave),
+    // 0x05
+    RouteRecord(RouteRecord),
+    // 0x06
+    RejoinRequest(RejoinRequest),
+    // 0x07
+    RejoinResponse(RejoinResponse),
+    // 0x08
+    LinkStatus(LinkStatus),
+    // 0x09
+    NetworkReport(NetworkReport),
+    // 0x0a
+    NetworkUpdate(NetworkUpdate),
+    // 0x0b
+    EndDeviceTimeoutRequest(EndDeviceTimeoutRequest),
+    // 0x0c
+    EndDeviceTimeoutResponse(EndDeviceTimeoutResponse),
+    // 0x0d
+    LinkPowerDelta(LinkPowerDelta),
+}
+
+impl NWKCommandFrame {
+    /// 3.4 Command Frame Identifier
+    pub fn command_identifier(&self) -> u8 {
+        match self {
+            NWKCommandFrame::RouteRequest(_) => 0x01,
+            NWKCommandFrame::RouteReply(_) => 0x02,
+            NWKCommandFrame::NetworkStatus(_) => 0x03,
+            NWKCommandFrame::Leave(_) => 0x04,
+            NWKCommandFrame::RouteRecord(_) => 0x05,
+            NWKCommandFrame::RejoinRequest(_) => 0x06,
+            NWKCommandFrame::RejoinResponse(_) => 0x07,
+            NWKCommandFrame::LinkStatus(_) => 0x08,
+            NWKCommandFrame::NetworkReport(_) => 0x09,
+            NWKCommandFrame::NetworkUpdate(_) => 0x0a,
+            NWKCommandFrame::EndDeviceTimeoutRequest(_) => 0x0b,
+            NWKCommandFrame::EndDeviceTimeoutResponse(_) => 0x0c,
+            NWKCommandFrame::LinkPowerDelta(_) => 0x0d,
+        }
+    }
+}
+
+/// A command carried by `NWKCommandFrame`, see `NPDUFrame::command`.
+pub trait NWKCommand: Sized {
+    fn from_command_frame(command: &NWKCommandFrame) -> Option<&Self>;
+}
+
+macro_rules! nwk_commands {
+    ($($command:ident),*) => {
+        $(
+            impl NWKCommand for $command {
+                fn from_command_frame(command: &NWKCommandFrame) -> Option<&Self> {
+                    match command {
+                        NWKCommandFrame::$command(command) => Some(command),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+nwk_commands!(
+    RouteRequest,
     RouteReply,
     NetworkStatus,
     Leave,
@@ -20,33 +123,99 @@ pub enum NWKCommandFrame {
     LinkStatus,
     NetworkReport,
     NetworkUpdate,
+    EndDeviceTimeoutRequest,
+    EndDeviceTimeoutResponse,
+    LinkPowerDelta
+);
+
+impl Serde for NWKCommandFrame {
+    fn encoded_len(&self) -> usize {
+        1 + match self {
+            NWKCommandFrame::RouteRequest(command) => command.encoded_len(),
+            NWKCommandFrame::RouteReply(command) => command.encoded_len(),
+            NWKCommandFrame::NetworkStatus(command) => command.encoded_len(),
+            NWKCommandFrame::Leave(command) => command.encoded_len(),
+            NWKCommandFrame::RouteRecord(command) => command.encoded_len(),
+            NWKCommandFrame::RejoinRequest(command) => command.encoded_len(),
+            NWKCommandFrame::RejoinResponse(command) => command.encoded_len(),
+            NWKCommandFrame::LinkStatus(command) => command.encoded_len(),
+            NWKCommandFrame::NetworkReport(command) => command.encoded_len(),
+            NWKCommandFrame::NetworkUpdate(command) => command.encoded_len(),
+            NWKCommandFrame::EndDeviceTimeoutRequest(command) => command.encoded_len(),
+            NWKCommandFrame::EndDeviceTimeoutResponse(command) => command.encoded_len(),
+            NWKCommandFrame::LinkPowerDelta(command) => command.encoded_len(),
+        }
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        writer.write_u8(self.command_identifier())?;
+        match self {
+            NWKCommandFrame::RouteRequest(command) => writer.write(command),
+            NWKCommandFrame::RouteReply(command) => writer.write(command),
+            NWKCommandFrame::NetworkStatus(command) => writer.write(command),
+            NWKCommandFrame::Leave(command) => writer.write(command),
+            NWKCommandFrame::RouteRecord(command) => writer.write(command),
+            NWKCommandFrame::RejoinRequest(command) => writer.write(command),
+            NWKCommandFrame::RejoinResponse(command) => writer.write(command),
+            NWKCommandFrame::LinkStatus(command) => writer.write(command),
+            NWKCommandFrame::NetworkReport(command) => writer.write(command),
+            NWKCommandFrame::NetworkUpdate(command) => writer.write(command),
+            NWKCommandFrame::EndDeviceTimeoutRequest(command) => writer.write(command),
+            NWKCommandFrame::EndDeviceTimeoutResponse(command) => writer.write(command),
+            NWKCommandFrame::LinkPowerDelta(command) => writer.write(command),
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError> {
+        let offset = reader.position();
+        match reader.read_u8()? {
+            0x01 => Ok(NWKCommandFrame::RouteRequest(reader.read()?)),
+            0x02 => Ok(NWKCommandFrame::RouteReply(reader.read()?)),
+            0x03 => Ok(NWKCommandFrame::NetworkStatus(reader.read()?)),
+            0x04 => Ok(NWKCommandFrame::Leave(reader.read()?)),
+            0x05 => Ok(NWKCommandFrame::RouteRecord(reader.read()?)),
+            0x06 => Ok(NWKCommandFrame::RejoinRequest(reader.read()?)),
+            0x07 => Ok(NWKCommandFrame::RejoinResponse(reader.read()?)),
+            0x08 => Ok(NWKCommandFrame::LinkStatus(reader.read()?)),
+            0x09 => Ok(NWKCommandFrame::NetworkReport(reader.read()?)),
+            0x0a => Ok(NWKCommandFrame::NetworkUpdate(reader.read()?)),
+            0x0b => Ok(NWKCommandFrame::EndDeviceTimeoutRequest(reader.read()?)),
+            0x0c => Ok(NWKCommandFrame::EndDeviceTimeoutResponse(reader.read()?)),
+            0x0d => Ok(NWKCommandFrame::LinkPowerDelta(reader.read()?)),
+            identifier => Err(SerdeError::new(SerdeErrorKind::UnknownNWKCommand { identifier }, offset)),
+        }
+    }
 }
 
 /// 3.3.2 Format of Individual Frame Types
+#[derive(Debug)]
 pub enum Payload {
     Data(DataFrame),
     NWKCommand(NWKCommandFrame),
-    InterPan,
+    /// Inter-PAN frames are handed up unparsed, the NWK layer only carries them.
+    InterPan(DataFrame),
 }
 
 impl Payload {
     pub fn new_nwk_command(data: &[u8]) -> Result<Self, SerdeError> {
-        if data[0] == 0x01 {
-            Ok(Payload::NWKCommand(NWKCommandFrame::RouteRequest(RouteRequest::deserialize(&data[1..])?)))
-        } else {
-            Err(SerdeError::UnknownNWKCommand)
+        Ok(Payload::NWKCommand(NWKCommandFrame::deserialize(data)?))
+    }
+
+    /// The number of bytes `write` produces.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Payload::Data(frame) => frame.data.len(),
+            Payload::NWKCommand(command) => command.encoded_len(),
+            Payload::InterPan(frame) => frame.data.len(),
         }
     }
-}
 
-// impl Serde<Payload, SerdeError> for Payload {
-//     fn serialize(&self, data: &mut [u8]) -> Result<u8, SerdeError> {
-//         Ok(0)
-//     }
-    
-//     fn deserialize(data: &[u8]) -> Result<Self, SerdeError> {
-//         // TODO: How to properly deserialize this if we just know the payload
-//         //       but not the payload type (parsed outside)?
-//         Ok(Payload::Data(DataFrame {}))
-//     }
-// }
\ No newline at end of file
+    /// Writes the payload. The payload type itself is carried by the frame control field.
+    pub fn write(&self, writer: &mut Writer) -> Result<(), SerdeError> {
+        match self {
+            Payload::Data(frame) => writer.write_bytes(&frame.data),
+            Payload::NWKCommand(command) => writer.write(command),
+            Payload::InterPan(frame) => writer.write_bytes(&frame.data),
+        }
+    }
+}
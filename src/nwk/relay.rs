@@ -0,0 +1,196 @@
+use crate::nwk::address::ShortAddress;
+use crate::nwk::commands::network_status::{
+    NetworkStatus,
+    StatusCode,
+};
+use crate::nwk::frame::{
+    NPDUFrame,
+    SerdeError,
+};
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::frame_ref::NpduMut;
+use crate::nwk::payload::NWKCommandFrame;
+use crate::serde::Serde;
+
+#[derive(Debug)]
+pub enum RelayError {
+    /// The frame does not carry a source route subframe.
+    NotSourceRouted,
+    /// This device is not the relay the source route currently points to.
+    NotNextRelay,
+    /// The radius of the frame is used up, it must be dropped.
+    RadiusExhausted,
+    Serde(SerdeError),
+}
+
+impl From<SerdeError> for RelayError {
+    fn from(error: SerdeError) -> Self {
+        RelayError::Serde(error)
+    }
+}
+
+/// Relaying a source routed frame
+///
+/// Advances the source route subframe of a received `frame` and returns the address of
+/// the next hop. A relay index of zero means the last relay was reached and the frame
+/// is delivered to its destination.
+pub fn next_source_route_hop(frame: &mut NPDUFrame, own_address: ShortAddress) -> Result<ShortAddress, RelayError> {
+    let destination_address = frame.destination_address;
+    let source_route_frame = frame.source_route_frame.as_mut().ok_or(RelayError::NotSourceRouted)?;
+
+    let relay_index = source_route_frame.get_index() as usize;
+    match source_route_frame.relay_list().get(relay_index) {
+        Some(relay) if *relay == own_address => (),
+        _ => return Err(RelayError::NotNextRelay),
+    }
+    if frame.radius == 0 {
+        return Err(RelayError::RadiusExhausted);
+    }
+
+    let next_hop = if relay_index == 0 {
+        destination_address
+    } else {
+        source_route_frame.decrement_index();
+        source_route_frame.relay_list()[relay_index - 1]
+    };
+    frame.radius -= 1;
+
+    Ok(next_hop)
+}
+
+/// Relaying a source routed frame in place
+///
+/// Same as `next_source_route_hop`, but updates the relay index and radius directly in the
+/// received buffer.
+pub fn next_source_route_hop_in_place(frame: &mut NpduMut, own_address: ShortAddress) -> Result<ShortAddress, RelayError> {
+    let view = frame.view();
+    let relay_index = view.relay_index().ok_or(RelayError::NotSourceRouted)?;
+    if view.relay(relay_index) != Some(own_address) {
+        return Err(RelayError::NotNextRelay);
+    }
+    if view.radius() == 0 {
+        return Err(RelayError::RadiusExhausted);
+    }
+
+    let next_hop = if relay_index == 0 {
+        view.destination_address()
+    } else {
+        let next_hop = view.relay(relay_index - 1).ok_or(RelayError::NotNextRelay)?;
+        frame.set_relay_index(relay_index - 1)?;
+        next_hop
+    };
+    frame.decrement_radius();
+
+    Ok(next_hop)
+}
+
+/// Advances the source route of a received `frame` and re-serializes it into `data`.
+///
+/// Returns the MAC destination for the relayed frame and the number of bytes written.
+pub fn relay_source_routed_frame(
+    frame: &mut NPDUFrame,
+    own_address: ShortAddress,
+    data: &mut [u8],
+) -> Result<(ShortAddress, usize), RelayError> {
+    let next_hop = next_source_route_hop(frame, own_address)?;
+    let length = frame.serialize(data)?;
+    Ok((next_hop, length))
+}
+
+/// Builds the network status command reporting that relaying `frame` failed.
+///
+/// The command is sent back to the originator of the source routed frame.
+pub fn source_route_failure(
+    frame: &NPDUFrame,
+    own_address: ShortAddress,
+    radius: u8,
+    sequence_number: u8,
+) -> NPDUFrame {
+    command_frame(
+        frame.source_address,
+        own_address,
+        radius,
+        sequence_number,
+        NWKCommandFrame::NetworkStatus(NetworkStatus {
+            status_code: StatusCode::SourceRouteFailure,
+            destination_address: frame.destination_address,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::frame_builder::NPDUFrameBuilder;
+    use crate::nwk::frame::SourceRouteFrame;
+    use crate::nwk::payload::{
+        DataFrame,
+        Payload,
+    };
+
+    const DESTINATION: ShortAddress = ShortAddress(0x0100);
+
+    /// A frame from the coordinator to 0x0100 over 0x0101, 0x0102 and 0x0103.
+    fn source_routed_frame(radius: u8) -> NPDUFrame {
+        let relays = [ShortAddress(0x0101), ShortAddress(0x0102), ShortAddress(0x0103)];
+        NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::from_slice(&[0xaa]).unwrap() }))
+            .destination(DESTINATION)
+            .source(ShortAddress::COORDINATOR)
+            .radius(radius)
+            .source_route(SourceRouteFrame::new(relays.iter().copied().collect()).unwrap())
+            .build(&mut 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn relay_index_counts_down_to_the_destination() {
+        let mut frame = source_routed_frame(5);
+        let mut hops = heapless::Vec::<ShortAddress, 4>::new();
+        for relay in [0x0103, 0x0102, 0x0101] {
+            hops.push(next_source_route_hop(&mut frame, ShortAddress(relay)).unwrap()).unwrap();
+        }
+        assert_eq!(hops, [ShortAddress(0x0102), ShortAddress(0x0101), DESTINATION]);
+        assert_eq!(frame.source_route_frame.as_ref().unwrap().get_index(), 0);
+        assert_eq!(frame.radius, 2);
+    }
+
+    #[test]
+    fn only_the_current_relay_forwards() {
+        let mut frame = source_routed_frame(5);
+        assert!(matches!(next_source_route_hop(&mut frame, ShortAddress(0x0102)), Err(RelayError::NotNextRelay)));
+        assert_eq!(frame.source_route_frame.as_ref().unwrap().get_index(), 2);
+
+        let mut frame = source_routed_frame(1);
+        next_source_route_hop(&mut frame, ShortAddress(0x0103)).unwrap();
+        assert!(matches!(next_source_route_hop(&mut frame, ShortAddress(0x0102)), Err(RelayError::RadiusExhausted)));
+
+        frame.source_route_frame = None;
+        assert!(matches!(next_source_route_hop(&mut frame, ShortAddress(0x0102)), Err(RelayError::NotSourceRouted)));
+    }
+
+    #[test]
+    fn relaying_in_place_matches_relaying_the_decoded_frame() {
+        let mut frame = source_routed_frame(5);
+        let mut data = [0; 64];
+        let length = frame.serialize(&mut data).unwrap();
+
+        let mut relayed = [0; 64];
+        let (next_hop, relayed_length) =
+            relay_source_routed_frame(&mut frame, ShortAddress(0x0103), &mut relayed).unwrap();
+
+        let mut in_place = NpduMut::new(&mut data[..length]).unwrap();
+        assert_eq!(next_source_route_hop_in_place(&mut in_place, ShortAddress(0x0103)).ok(), Some(next_hop));
+        assert_eq!(&data[..length], &relayed[..relayed_length]);
+    }
+
+    #[test]
+    fn failure_is_reported_to_the_originator() {
+        let frame = source_routed_frame(5);
+        let report = source_route_failure(&frame, ShortAddress(0x0102), 10, 3);
+        assert_eq!(report.destination_address, ShortAddress::COORDINATOR);
+        assert_eq!(report.source_address, ShortAddress(0x0102));
+        let status = report.command::<NetworkStatus>().unwrap();
+        assert_eq!(status.status_code, StatusCode::SourceRouteFailure);
+        assert_eq!(status.destination_address, DESTINATION);
+    }
+}
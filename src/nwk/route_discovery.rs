@@ -0,0 +1,694 @@
+use crate::nwk::address::{
+    AddressType,
+    IEEEAddress,
+    ShortAddress,
+    BROADCAST_ROUTERS_AND_COORDINATOR,
+};
+use crate::nwk::commands::route_reply::{
+    self,
+    RouteReply,
+};
+use crate::nwk::commands::route_request::{
+    self,
+    ManyToOne,
+    RouteRequest,
+};
+use crate::nwk::constants::{
+    DEFAULT_RADIUS,
+    ROUTE_DISCOVERY_TIME_MS,
+};
+use crate::nwk::frame::NPDUFrame;
+use crate::nwk::frame_builder::command_frame;
+use crate::nwk::neighbor_table::NeighborTable;
+use crate::nwk::payload::NWKCommandFrame;
+use crate::nwk::routing_table::{
+    RouteStatus,
+    RoutingTable,
+};
+use crate::state::DeviceType;
+
+/// Cost assumed for a link to a device which is not in the neighbor table.
+const UNKNOWN_LINK_COST: u8 = 7;
+
+/// Route Discovery Table Entry
+pub struct RouteDiscoveryEntry {
+    pub route_request_identifier: u8,
+    /// The originator of the route request.
+    pub source_address: ShortAddress,
+    /// The routing table key of the destination being discovered, the group identifier
+    /// for multicast route requests.
+    pub destination_address: ShortAddress,
+    /// The device the best route request was received from, the next hop back towards
+    /// the originator.
+    pub sender_address: ShortAddress,
+    /// The path cost from the originator to this device.
+    pub forward_cost: u8,
+    /// The path cost from this device to the destination.
+    pub residual_cost: u8,
+    pub expiration_ms: u64,
+}
+
+/// What to do after a route request has been received.
+#[derive(Debug)]
+pub enum RouteRequestAction {
+    /// Rebroadcast the route request with the accumulated path cost.
+    Forward(NPDUFrame),
+    /// We are the destination, or its parent, and answer with a route reply.
+    Reply(NPDUFrame),
+}
+
+/// A frame as it was received from the MAC layer.
+#[derive(Copy, Clone, Debug)]
+pub struct ReceivedFrame<'a> {
+    pub frame: &'a NPDUFrame,
+    /// The MAC source of the frame.
+    pub previous_hop: ShortAddress,
+    pub now_ms: u64,
+}
+
+/// AODV based mesh route discovery.
+///
+/// Originates, relays and answers route requests, keeps the route discovery table and
+/// fills the routing table with the next hops learned from route replies. The route
+/// discovery table holds up to `D` entries.
+pub struct RouteDiscovery<const D: usize> {
+    /// Radius of the route requests this device originates.
+    pub radius: u8,
+    route_request_identifier: u8,
+    entries: heapless::Vec<RouteDiscoveryEntry, D>,
+}
+
+/// The cost of the link over which a frame from `neighbor` was received.
+fn link_cost<const N: usize>(neighbor_table: &NeighborTable<N>, neighbor: &ShortAddress) -> u8 {
+    neighbor_table.get(neighbor).map_or(UNKNOWN_LINK_COST, |entry| entry.link_cost())
+}
+
+fn destination_key(destination: &AddressType) -> ShortAddress {
+    match destination {
+        AddressType::Singlecast(address) => *address,
+        AddressType::Multicast(group_id) => ShortAddress(group_id.0),
+    }
+}
+
+/// The next hop towards `destination`, either the destination itself if it is a neighbor
+/// or the next hop of an active route.
+pub fn next_hop<const R: usize, const N: usize>(
+    routing_table: &RoutingTable<R>,
+    neighbor_table: &NeighborTable<N>,
+    destination: &ShortAddress,
+) -> Option<ShortAddress> {
+    if neighbor_table.get(destination).is_some() {
+        Some(*destination)
+    } else {
+        routing_table.next_hop(destination)
+    }
+}
+
+impl<const D: usize> RouteDiscovery<D> {
+    pub fn new() -> Self {
+        Self {
+            radius: DEFAULT_RADIUS,
+            route_request_identifier: 0,
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RouteDiscoveryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn get(&self, source_address: &ShortAddress, route_request_identifier: u8) -> Option<&RouteDiscoveryEntry> {
+        self.entries.iter().find(|entry| {
+            entry.source_address == *source_address && entry.route_request_identifier == route_request_identifier
+        })
+    }
+
+    fn get_mut(&mut self, source_address: &ShortAddress, route_request_identifier: u8) -> Option<&mut RouteDiscoveryEntry> {
+        self.entries.iter_mut().find(|entry| {
+            entry.source_address == *source_address && entry.route_request_identifier == route_request_identifier
+        })
+    }
+
+    fn next_route_request_identifier(&mut self) -> u8 {
+        self.route_request_identifier = self.route_request_identifier.wrapping_add(1);
+        self.route_request_identifier
+    }
+
+    /// Adds a discovery entry, dropping the one closest to expiry if the table is full.
+    fn insert(&mut self, entry: RouteDiscoveryEntry) {
+        if self.entries.is_full() {
+            if let Some(index) = self.entries.iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| entry.expiration_ms)
+                .map(|(index, _)| index)
+            {
+                self.entries.swap_remove(index);
+            }
+        }
+        // Only fails for a table without any capacity.
+        self.entries.push(entry).ok();
+    }
+
+    /// Originating a route request
+    ///
+    /// Marks the route as being discovered and returns the route request to broadcast.
+    pub fn route_request<const R: usize>(
+        &mut self,
+        routing_table: &mut RoutingTable<R>,
+        destination: AddressType,
+        destination_ieee_address: Option<IEEEAddress>,
+        own_address: ShortAddress,
+        now_ms: u64,
+        sequence_number: u8,
+    ) -> NPDUFrame {
+        let route_request_identifier = self.next_route_request_identifier();
+        let destination_address = destination_key(&destination);
+        self.insert(RouteDiscoveryEntry {
+            route_request_identifier,
+            source_address: own_address,
+            destination_address,
+            sender_address: own_address,
+            forward_cost: 0,
+            residual_cost: u8::MAX,
+            expiration_ms: now_ms + u64::from(ROUTE_DISCOVERY_TIME_MS),
+        });
+
+        if routing_table.next_hop(&destination_address).is_none() {
+            // Without room the discovery still runs, the reply just finds no entry to fill.
+            let _ = routing_table.begin_discovery(destination_address);
+        }
+
+        command_frame(
+            BROADCAST_ROUTERS_AND_COORDINATOR,
+            own_address,
+            self.radius,
+            sequence_number,
+            NWKCommandFrame::RouteRequest(RouteRequest {
+                command_options: route_request::CommandOptions {
+                    many_to_one: ManyToOne::No,
+                    contains_destination_ieee_address: destination_ieee_address.is_some(),
+                    is_multicast: matches!(destination, AddressType::Multicast(_)),
+                },
+                route_request_identifier,
+                destination_address: destination,
+                path_cost: 0,
+                destination_ieee_address,
+            }),
+        )
+    }
+
+    /// Originating a many-to-one route request
+    ///
+    /// Used by concentrators to let every router in range learn a route back to them.
+    pub fn many_to_one_route_request(
+        &mut self,
+        many_to_one: ManyToOne,
+        own_address: ShortAddress,
+        radius: u8,
+        sequence_number: u8,
+    ) -> NPDUFrame {
+        let route_request_identifier = self.next_route_request_identifier();
+        command_frame(
+            BROADCAST_ROUTERS_AND_COORDINATOR,
+            own_address,
+            radius,
+            sequence_number,
+            NWKCommandFrame::RouteRequest(RouteRequest {
+                command_options: route_request::CommandOptions {
+                    many_to_one,
+                    contains_destination_ieee_address: false,
+                    is_multicast: false,
+                },
+                route_request_identifier,
+                destination_address: AddressType::Singlecast(BROADCAST_ROUTERS_AND_COORDINATOR),
+                path_cost: 0,
+                destination_ieee_address: None,
+            }),
+        )
+    }
+
+    /// Upon receipt of a route request command frame
+    ///
+    /// A route request is answered if we are its destination or the parent of the end
+    /// device it is looking for, otherwise it is forwarded as long as it arrived over a
+    /// cheaper path than any copy before it. Many-to-one route requests are recorded as
+    /// routes to their concentrator.
+    pub fn route_request_indication<const R: usize, const N: usize>(
+        &mut self,
+        routing_table: &mut RoutingTable<R>,
+        neighbor_table: &NeighborTable<N>,
+        received: ReceivedFrame,
+        request: &RouteRequest,
+        own_address: ShortAddress,
+        sequence_number: u8,
+    ) -> Option<RouteRequestAction> {
+        let ReceivedFrame { frame, previous_hop, now_ms } = received;
+        let originator = frame.source_address;
+        if originator == own_address {
+            return None;
+        }
+        let path_cost = request.path_cost.saturating_add(link_cost(neighbor_table, &previous_hop));
+        let destination_address = destination_key(&request.destination_address);
+
+        match self.get_mut(&originator, request.route_request_identifier) {
+            Some(entry) if path_cost >= entry.forward_cost => return None,
+            Some(entry) => {
+                entry.sender_address = previous_hop;
+                entry.forward_cost = path_cost;
+            },
+            None => self.insert(RouteDiscoveryEntry {
+                route_request_identifier: request.route_request_identifier,
+                source_address: originator,
+                destination_address,
+                sender_address: previous_hop,
+                forward_cost: path_cost,
+                residual_cost: u8::MAX,
+                expiration_ms: now_ms + u64::from(ROUTE_DISCOVERY_TIME_MS),
+            }),
+        }
+
+        if request.command_options.many_to_one != ManyToOne::No {
+            // Many-to-one route requests are never answered, every router on the way
+            // records the route back to the concentrator instead.
+            if routing_table.activate(originator, previous_hop).is_ok() {
+                if let Some(route) = routing_table.get_mut(&originator) {
+                    route.many_to_one = true;
+                    route.no_route_cache = request.command_options.many_to_one == ManyToOne::NoSupportForRRTable;
+                    route.route_record_required = request.command_options.many_to_one == ManyToOne::SupportForRRTable;
+                }
+            }
+        }
+
+        let responder = match request.destination_address {
+            _ if request.command_options.many_to_one != ManyToOne::No => None,
+            AddressType::Singlecast(destination) if destination == own_address => Some(destination),
+            AddressType::Singlecast(destination) => neighbor_table.get(&destination)
+                .filter(|entry| entry.is_child() && entry.device_type == DeviceType::EndDevice)
+                .map(|entry| entry.network_address),
+            AddressType::Multicast(_) => None,
+        };
+
+        if let Some(responder) = responder {
+            return Some(RouteRequestAction::Reply(command_frame(
+                previous_hop,
+                own_address,
+                DEFAULT_RADIUS,
+                sequence_number,
+                NWKCommandFrame::RouteReply(RouteReply {
+                    command_options: route_reply::CommandOptions {
+                        contains_originator_ieee_address: false,
+                        contains_responder_ieee_address: false,
+                        is_multicast: false,
+                    },
+                    route_request_identifier: request.route_request_identifier,
+                    originator_address: originator,
+                    responder_address: AddressType::Singlecast(responder),
+                    path_cost: 0,
+                    originator_ieee_address: None,
+                    responder_ieee_address: None,
+                }),
+            )));
+        }
+
+        if frame.radius <= 1 {
+            return None;
+        }
+        if request.command_options.many_to_one == ManyToOne::No && routing_table.get(&destination_address).is_none() {
+            let _ = routing_table.begin_discovery(destination_address);
+        }
+
+        let mut forward = command_frame(
+            BROADCAST_ROUTERS_AND_COORDINATOR,
+            originator,
+            frame.radius - 1,
+            frame.sequence_number,
+            NWKCommandFrame::RouteRequest(RouteRequest {
+                command_options: route_request::CommandOptions {
+                    many_to_one: request.command_options.many_to_one,
+                    contains_destination_ieee_address: request.destination_ieee_address.is_some(),
+                    is_multicast: request.command_options.is_multicast,
+                },
+                route_request_identifier: request.route_request_identifier,
+                destination_address: request.destination_address,
+                path_cost,
+                destination_ieee_address: request.destination_ieee_address,
+            }),
+        );
+        forward.source_ieee_address = frame.source_ieee_address;
+        forward.update_control();
+        Some(RouteRequestAction::Forward(forward))
+    }
+
+    /// Upon receipt of a route reply command frame
+    ///
+    /// `previous_hop` is the MAC source of the frame. Routes towards the responder are
+    /// recorded whenever the reply describes a cheaper path, see
+    /// `RoutingTable::complete_discovery`. Unless we originated the
+    /// request, the reply is passed on towards the originator and returned.
+    pub fn route_reply_indication<const R: usize, const N: usize>(
+        &mut self,
+        routing_table: &mut RoutingTable<R>,
+        neighbor_table: &NeighborTable<N>,
+        reply: &RouteReply,
+        previous_hop: ShortAddress,
+        own_address: ShortAddress,
+        sequence_number: u8,
+    ) -> Option<NPDUFrame> {
+        let path_cost = reply.path_cost.saturating_add(link_cost(neighbor_table, &previous_hop));
+        let entry = self.get_mut(&reply.originator_address, reply.route_request_identifier)?;
+        if path_cost >= entry.residual_cost {
+            return None;
+        }
+        entry.residual_cost = path_cost;
+        let sender_address = entry.sender_address;
+
+        let destination_address = destination_key(&reply.responder_address);
+        if routing_table.complete_discovery(destination_address, previous_hop).is_err() {
+            return None;
+        }
+        if let Some(route) = routing_table.get_mut(&destination_address) {
+            route.group_id_flag = reply.command_options.is_multicast;
+        }
+
+        if reply.originator_address == own_address {
+            return None;
+        }
+
+        Some(command_frame(
+            sender_address,
+            own_address,
+            DEFAULT_RADIUS,
+            sequence_number,
+            NWKCommandFrame::RouteReply(RouteReply {
+                command_options: route_reply::CommandOptions {
+                    contains_originator_ieee_address: reply.originator_ieee_address.is_some(),
+                    contains_responder_ieee_address: reply.responder_ieee_address.is_some(),
+                    is_multicast: reply.command_options.is_multicast,
+                },
+                route_request_identifier: reply.route_request_identifier,
+                originator_address: reply.originator_address,
+                responder_address: reply.responder_address,
+                path_cost,
+                originator_ieee_address: reply.originator_ieee_address,
+                responder_ieee_address: reply.responder_ieee_address,
+            }),
+        ))
+    }
+
+    /// Drops expired route discovery entries. The route each of them was discovering is
+    /// marked as failed if it is still being discovered and no other discovery for the same
+    /// destination is running.
+    pub fn expire<const R: usize>(&mut self, routing_table: &mut RoutingTable<R>, now_ms: u64) {
+        let expired: heapless::Vec<ShortAddress, D> = self.entries.iter()
+            .filter(|entry| entry.expiration_ms <= now_ms)
+            .map(|entry| entry.destination_address)
+            .collect();
+        self.entries.retain(|entry| entry.expiration_ms > now_ms);
+
+        for destination_address in expired.iter() {
+            if self.entries.iter().any(|entry| entry.destination_address == *destination_address) {
+                continue;
+            }
+            if let Some(route) = routing_table.get_mut(destination_address) {
+                if route.status == RouteStatus::DiscoveryUnderway {
+                    route.status = RouteStatus::DiscoveryFailed;
+                }
+            }
+        }
+    }
+}
+
+impl<const D: usize> Default for RouteDiscovery<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::neighbor_table::{
+        NeighborTableEntry,
+        Relationship,
+    };
+
+    const OWN_ADDRESS: ShortAddress = ShortAddress(0x0100);
+    const ORIGINATOR: ShortAddress = ShortAddress(0x0200);
+    const DESTINATION: ShortAddress = ShortAddress(0x0300);
+
+    /// Neighbors 0x0001 to 0x0003 with link costs of 3, 1 and 1. Each one has an
+    /// LQI of 255, the cost of 3 is reported by the neighbor.
+    fn neighbor_table() -> NeighborTable<4> {
+        let mut neighbor_table = NeighborTable::new();
+        for (network_address, outgoing_cost) in [(0x0001, 3), (0x0002, 0), (0x0003, 0)] {
+            let mut entry = NeighborTableEntry::new(ShortAddress(network_address), None, DeviceType::Router, Relationship::Sibling);
+            entry.lqi = 255;
+            entry.outgoing_cost = outgoing_cost;
+            neighbor_table.insert(entry).unwrap();
+        }
+        neighbor_table
+    }
+
+    /// A route request from the originator for `destination`, as received with `radius`.
+    fn received_request(destination: ShortAddress, path_cost: u8, radius: u8) -> NPDUFrame {
+        command_frame(
+            BROADCAST_ROUTERS_AND_COORDINATOR,
+            ORIGINATOR,
+            radius,
+            0x42,
+            NWKCommandFrame::RouteRequest(RouteRequest {
+                command_options: route_request::CommandOptions {
+                    many_to_one: ManyToOne::No,
+                    contains_destination_ieee_address: false,
+                    is_multicast: false,
+                },
+                route_request_identifier: 9,
+                destination_address: AddressType::Singlecast(destination),
+                path_cost,
+                destination_ieee_address: None,
+            }),
+        )
+    }
+
+    fn received_reply(originator: ShortAddress, route_request_identifier: u8, path_cost: u8) -> RouteReply {
+        RouteReply {
+            command_options: route_reply::CommandOptions {
+                contains_originator_ieee_address: false,
+                contains_responder_ieee_address: false,
+                is_multicast: false,
+            },
+            route_request_identifier,
+            originator_address: originator,
+            responder_address: AddressType::Singlecast(DESTINATION),
+            path_cost,
+            originator_ieee_address: None,
+            responder_ieee_address: None,
+        }
+    }
+
+    fn indication(
+        route_discovery: &mut RouteDiscovery<4>,
+        routing_table: &mut RoutingTable<4>,
+        frame: &NPDUFrame,
+        previous_hop: u16,
+    ) -> Option<RouteRequestAction> {
+        let received = ReceivedFrame { frame, previous_hop: ShortAddress(previous_hop), now_ms: 0 };
+        route_discovery.route_request_indication(
+            routing_table, &neighbor_table(), received, frame.command::<RouteRequest>().unwrap(), OWN_ADDRESS, 1,
+        )
+    }
+
+    #[test]
+    fn route_request_is_originated() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        let frame = route_discovery.route_request(
+            &mut routing_table, AddressType::Singlecast(DESTINATION), None, OWN_ADDRESS, 100, 5,
+        );
+
+        assert_eq!(frame.destination_address, BROADCAST_ROUTERS_AND_COORDINATOR);
+        assert_eq!(frame.source_address, OWN_ADDRESS);
+        assert_eq!(frame.radius, DEFAULT_RADIUS);
+        assert_eq!(frame.sequence_number, 5);
+        let request = frame.command::<RouteRequest>().unwrap();
+        assert_eq!(request.route_request_identifier, 1);
+        assert!(request.destination_address == AddressType::Singlecast(DESTINATION));
+        assert_eq!(request.path_cost, 0);
+
+        let entry = route_discovery.get(&OWN_ADDRESS, 1).unwrap();
+        assert_eq!(entry.destination_address, DESTINATION);
+        assert_eq!(entry.expiration_ms, 100 + u64::from(ROUTE_DISCOVERY_TIME_MS));
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::DiscoveryUnderway);
+
+        let frame = route_discovery.route_request(
+            &mut routing_table, AddressType::Singlecast(DESTINATION), None, OWN_ADDRESS, 100, 6,
+        );
+        assert_eq!(frame.command::<RouteRequest>().unwrap().route_request_identifier, 2);
+        assert_eq!(route_discovery.len(), 2);
+    }
+
+    #[test]
+    fn route_request_is_forwarded_with_accumulated_path_cost() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+
+        let forward = match indication(&mut route_discovery, &mut routing_table, &received_request(DESTINATION, 2, 5), 0x0001) {
+            Some(RouteRequestAction::Forward(forward)) => forward,
+            _ => panic!("expected the route request to be forwarded"),
+        };
+        assert_eq!(forward.destination_address, BROADCAST_ROUTERS_AND_COORDINATOR);
+        assert_eq!(forward.source_address, ORIGINATOR);
+        assert_eq!(forward.radius, 4);
+        assert_eq!(forward.sequence_number, 0x42);
+        assert_eq!(forward.command::<RouteRequest>().unwrap().route_request_identifier, 9);
+        assert_eq!(forward.command::<RouteRequest>().unwrap().path_cost, 5);
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::DiscoveryUnderway);
+
+        // Copies arriving over a path which is not cheaper are dropped.
+        assert!(indication(&mut route_discovery, &mut routing_table, &received_request(DESTINATION, 4, 5), 0x0002).is_none());
+        // An unknown neighbor has a link cost of 7.
+        assert!(indication(&mut route_discovery, &mut routing_table, &received_request(DESTINATION, 0, 5), 0x0004).is_none());
+
+        let forward = match indication(&mut route_discovery, &mut routing_table, &received_request(DESTINATION, 1, 5), 0x0002) {
+            Some(RouteRequestAction::Forward(forward)) => forward,
+            _ => panic!("expected the route request to be forwarded"),
+        };
+        assert_eq!(forward.command::<RouteRequest>().unwrap().path_cost, 2);
+        let entry = route_discovery.get(&ORIGINATOR, 9).unwrap();
+        assert_eq!(entry.sender_address, ShortAddress(0x0002));
+        assert_eq!(entry.forward_cost, 2);
+        assert_eq!(route_discovery.len(), 1);
+    }
+
+    #[test]
+    fn route_request_is_not_forwarded_beyond_its_radius() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        assert!(indication(&mut route_discovery, &mut routing_table, &received_request(DESTINATION, 0, 1), 0x0001).is_none());
+        assert!(route_discovery.get(&ORIGINATOR, 9).is_some());
+    }
+
+    #[test]
+    fn route_request_for_us_is_answered() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+
+        let reply = match indication(&mut route_discovery, &mut routing_table, &received_request(OWN_ADDRESS, 2, 5), 0x0001) {
+            Some(RouteRequestAction::Reply(reply)) => reply,
+            _ => panic!("expected a route reply"),
+        };
+        assert_eq!(reply.destination_address, ShortAddress(0x0001));
+        assert_eq!(reply.source_address, OWN_ADDRESS);
+        let reply = reply.command::<RouteReply>().unwrap();
+        assert_eq!(reply.route_request_identifier, 9);
+        assert_eq!(reply.originator_address, ORIGINATOR);
+        assert!(reply.responder_address == AddressType::Singlecast(OWN_ADDRESS));
+        assert_eq!(reply.path_cost, 0);
+    }
+
+    #[test]
+    fn route_request_for_end_device_child_is_answered() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        let mut neighbor_table = neighbor_table();
+        neighbor_table.insert(NeighborTableEntry::new(DESTINATION, None, DeviceType::EndDevice, Relationship::Child)).unwrap();
+
+        let frame = received_request(DESTINATION, 0, 5);
+        let received = ReceivedFrame { frame: &frame, previous_hop: ShortAddress(0x0002), now_ms: 0 };
+        let action = route_discovery.route_request_indication(
+            &mut routing_table, &neighbor_table, received, frame.command::<RouteRequest>().unwrap(), OWN_ADDRESS, 1,
+        );
+        match action {
+            Some(RouteRequestAction::Reply(reply)) => {
+                assert!(reply.command::<RouteReply>().unwrap().responder_address == AddressType::Singlecast(DESTINATION));
+            },
+            _ => panic!("expected a route reply"),
+        }
+    }
+
+    #[test]
+    fn route_reply_is_relayed_towards_the_originator() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        let neighbor_table = neighbor_table();
+        indication(&mut route_discovery, &mut routing_table, &received_request(DESTINATION, 0, 5), 0x0002).unwrap();
+
+        let relayed = route_discovery.route_reply_indication(
+            &mut routing_table, &neighbor_table, &received_reply(ORIGINATOR, 9, 2), ShortAddress(0x0001), OWN_ADDRESS, 3,
+        ).unwrap();
+        assert_eq!(relayed.destination_address, ShortAddress(0x0002));
+        assert_eq!(relayed.source_address, OWN_ADDRESS);
+        assert_eq!(relayed.command::<RouteReply>().unwrap().path_cost, 5);
+        assert_eq!(routing_table.next_hop(&DESTINATION), Some(ShortAddress(0x0001)));
+
+        // A reply describing a more expensive path is dropped, a cheaper one replaces the route.
+        assert!(route_discovery.route_reply_indication(
+            &mut routing_table, &neighbor_table, &received_reply(ORIGINATOR, 9, 5), ShortAddress(0x0003), OWN_ADDRESS, 4,
+        ).is_none());
+        let relayed = route_discovery.route_reply_indication(
+            &mut routing_table, &neighbor_table, &received_reply(ORIGINATOR, 9, 1), ShortAddress(0x0003), OWN_ADDRESS, 4,
+        ).unwrap();
+        assert_eq!(relayed.command::<RouteReply>().unwrap().path_cost, 2);
+        assert_eq!(routing_table.next_hop(&DESTINATION), Some(ShortAddress(0x0003)));
+        assert_eq!(route_discovery.get(&ORIGINATOR, 9).unwrap().residual_cost, 2);
+
+        // Replies to requests we never saw are dropped.
+        assert!(route_discovery.route_reply_indication(
+            &mut routing_table, &neighbor_table, &received_reply(ORIGINATOR, 10, 0), ShortAddress(0x0003), OWN_ADDRESS, 5,
+        ).is_none());
+    }
+
+    #[test]
+    fn route_reply_completes_our_discovery() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        route_discovery.route_request(&mut routing_table, AddressType::Singlecast(DESTINATION), None, OWN_ADDRESS, 0, 1);
+
+        assert!(route_discovery.route_reply_indication(
+            &mut routing_table, &neighbor_table(), &received_reply(OWN_ADDRESS, 1, 0), ShortAddress(0x0002), OWN_ADDRESS, 2,
+        ).is_none());
+        assert_eq!(routing_table.next_hop(&DESTINATION), Some(ShortAddress(0x0002)));
+
+        // The route stays usable once its discovery expires and the first frame over it
+        // validates it.
+        route_discovery.expire(&mut routing_table, u64::from(ROUTE_DISCOVERY_TIME_MS));
+        assert!(route_discovery.is_empty());
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::ValidationUnderway);
+        routing_table.use_route(&DESTINATION).unwrap();
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::Active);
+    }
+
+    #[test]
+    fn expiry_fails_only_the_route_of_the_expired_entry() {
+        let mut route_discovery = RouteDiscovery::<4>::new();
+        let mut routing_table = RoutingTable::<4>::new();
+        let other_destination = ShortAddress(0x0400);
+        route_discovery.route_request(&mut routing_table, AddressType::Singlecast(DESTINATION), None, OWN_ADDRESS, 0, 1);
+        // A route request we relay for another device.
+        indication(&mut route_discovery, &mut routing_table, &received_request(other_destination, 0, 5), 0x0001).unwrap();
+        let relayed_expiry_ms = route_discovery.get(&ORIGINATOR, 9).unwrap().expiration_ms;
+
+        // Retrying the discovery of the first destination later keeps it underway.
+        let later_ms = 1000;
+        route_discovery.route_request(&mut routing_table, AddressType::Singlecast(DESTINATION), None, OWN_ADDRESS, later_ms, 2);
+
+        route_discovery.expire(&mut routing_table, relayed_expiry_ms);
+        assert_eq!(route_discovery.len(), 1);
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::DiscoveryUnderway);
+        assert!(routing_table.get(&other_destination).unwrap().status == RouteStatus::DiscoveryFailed);
+
+        route_discovery.expire(&mut routing_table, later_ms + u64::from(ROUTE_DISCOVERY_TIME_MS) - 1);
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::DiscoveryUnderway);
+        route_discovery.expire(&mut routing_table, later_ms + u64::from(ROUTE_DISCOVERY_TIME_MS));
+        assert!(route_discovery.is_empty());
+        assert!(routing_table.get(&DESTINATION).unwrap().status == RouteStatus::DiscoveryFailed);
+    }
+}
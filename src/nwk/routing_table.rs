@@ -0,0 +1,394 @@
+use crate::nwk::address::ShortAddress;
+
+/// Route Status Values
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RouteStatus {
+    Active = 0x0,
+    DiscoveryUnderway = 0x1,
+    DiscoveryFailed = 0x2,
+    Inactive = 0x3,
+    ValidationUnderway = 0x4,
+}
+
+/// Routing Table Entry
+#[derive(Debug)]
+pub struct RoutingTableEntry {
+    pub destination_address: ShortAddress,
+    pub status: RouteStatus,
+    /// The destination does not keep a route record table.
+    pub no_route_cache: bool,
+    /// The destination is a concentrator which issued a many-to-one route request.
+    pub many_to_one: bool,
+    /// A route record has to be sent to the destination before the next data frame.
+    pub route_record_required: bool,
+    /// The destination address is a group identifier.
+    pub group_id_flag: bool,
+    pub next_hop_address: ShortAddress,
+    /// Number of aging periods since the route was last used.
+    pub age: u8,
+}
+
+impl RoutingTableEntry {
+    pub fn new(destination_address: ShortAddress, status: RouteStatus, next_hop_address: ShortAddress) -> Self {
+        Self {
+            destination_address,
+            status,
+            no_route_cache: false,
+            many_to_one: false,
+            route_record_required: false,
+            group_id_flag: false,
+            next_hop_address,
+            age: 0,
+        }
+    }
+
+    /// Whether frames may be sent over the route.
+    pub fn is_usable(&self) -> bool {
+        matches!(self.status, RouteStatus::Active | RouteStatus::ValidationUnderway)
+    }
+
+    /// Whether the entry may be dropped to make room for a new route.
+    pub fn is_evictable(&self) -> bool {
+        matches!(self.status, RouteStatus::DiscoveryFailed | RouteStatus::Inactive)
+    }
+}
+
+/// Number of aging periods after which an unused active route becomes inactive.
+pub const DEFAULT_ROUTE_AGE_LIMIT: u8 = 3;
+
+/// Next hop address of routes which are not yet known.
+const UNKNOWN_NEXT_HOP: ShortAddress = ShortAddress(0xffff);
+
+/// The table is full and the entry could not be added.
+#[derive(Debug)]
+pub struct RoutingTableFull;
+
+/// Routing Table
+///
+/// Routes are never dropped while they are active or being discovered. When the table is
+/// full, a failed or inactive route is evicted instead, preferring failed and then the
+/// longest unused routes. Removing an entry keeps the order of the others, so that
+/// `entries_from` pages stay stable. Holds up to `R` routes.
+pub struct RoutingTable<const R: usize> {
+    entries: heapless::Vec<RoutingTableEntry, R>,
+}
+
+impl<const R: usize> RoutingTable<R> {
+    pub fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        R
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RoutingTableEntry> {
+        self.entries.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut RoutingTableEntry> {
+        self.entries.iter_mut()
+    }
+
+    /// Entries starting at `start_index`, as listed in a Mgmt_Rtg_rsp.
+    pub fn entries_from(&self, start_index: usize) -> &[RoutingTableEntry] {
+        self.entries.get(start_index..).unwrap_or(&[])
+    }
+
+    /// Number of entries with the given status.
+    pub fn count(&self, status: RouteStatus) -> usize {
+        self.entries.iter().filter(|entry| entry.status == status).count()
+    }
+
+    pub fn get(&self, destination_address: &ShortAddress) -> Option<&RoutingTableEntry> {
+        self.entries.iter().find(|entry| entry.destination_address == *destination_address)
+    }
+
+    pub fn get_mut(&mut self, destination_address: &ShortAddress) -> Option<&mut RoutingTableEntry> {
+        self.entries.iter_mut().find(|entry| entry.destination_address == *destination_address)
+    }
+
+    /// Adds an entry or replaces the one for the same destination, evicting a failed or
+    /// inactive route if the table is full.
+    pub fn insert(&mut self, entry: RoutingTableEntry) -> Result<(), RoutingTableFull> {
+        if let Some(existing) = self.get_mut(&entry.destination_address) {
+            *existing = entry;
+            return Ok(());
+        }
+        if self.entries.is_full() {
+            let index = self.eviction_candidate().ok_or(RoutingTableFull)?;
+            self.entries.remove(index);
+        }
+        self.entries.push(entry).map_err(|_| RoutingTableFull)
+    }
+
+    fn eviction_candidate(&self) -> Option<usize> {
+        self.entries.iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_evictable())
+            .max_by_key(|(_, entry)| (entry.status == RouteStatus::DiscoveryFailed, entry.age))
+            .map(|(index, _)| index)
+    }
+
+    pub fn remove(&mut self, destination_address: &ShortAddress) -> Option<RoutingTableEntry> {
+        let index = self.entries.iter().position(|entry| entry.destination_address == *destination_address)?;
+        Some(self.entries.remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Marks a route as being discovered, keeping the flags of an existing entry.
+    pub fn begin_discovery(&mut self, destination_address: ShortAddress) -> Result<(), RoutingTableFull> {
+        if let Some(entry) = self.get_mut(&destination_address) {
+            entry.status = RouteStatus::DiscoveryUnderway;
+            Ok(())
+        } else {
+            self.insert(RoutingTableEntry::new(destination_address, RouteStatus::DiscoveryUnderway, UNKNOWN_NEXT_HOP))
+        }
+    }
+
+    /// Activates the route to `destination_address` over `next_hop_address`, keeping the
+    /// flags of an existing entry.
+    pub fn activate(&mut self, destination_address: ShortAddress, next_hop_address: ShortAddress) -> Result<(), RoutingTableFull> {
+        if let Some(entry) = self.get_mut(&destination_address) {
+            entry.status = RouteStatus::Active;
+            entry.next_hop_address = next_hop_address;
+            entry.age = 0;
+            Ok(())
+        } else {
+            self.insert(RoutingTableEntry::new(destination_address, RouteStatus::Active, next_hop_address))
+        }
+    }
+
+    /// Records the route learned from a route reply. A route which is still being
+    /// discovered or validated waits for validation by the first frame sent over it, see
+    /// `use_route`, any other route becomes active right away.
+    pub fn complete_discovery(&mut self, destination_address: ShortAddress, next_hop_address: ShortAddress) -> Result<(), RoutingTableFull> {
+        match self.get_mut(&destination_address) {
+            Some(entry) if matches!(entry.status, RouteStatus::DiscoveryUnderway | RouteStatus::ValidationUnderway) => {
+                entry.status = RouteStatus::ValidationUnderway;
+                entry.next_hop_address = next_hop_address;
+                entry.age = 0;
+                Ok(())
+            },
+            _ => self.activate(destination_address, next_hop_address),
+        }
+    }
+
+    /// Marks the route to `destination_address` as broken, e.g. after a Network Status
+    /// reporting a route failure.
+    pub fn deactivate(&mut self, destination_address: &ShortAddress) {
+        if let Some(entry) = self.get_mut(destination_address) {
+            entry.status = RouteStatus::Inactive;
+        }
+    }
+
+    /// Marks all routes over `next_hop_address` as broken, e.g. after the link to it failed.
+    /// Returns the destinations of the affected routes.
+    pub fn deactivate_next_hop(&mut self, next_hop_address: &ShortAddress) -> heapless::Vec<ShortAddress, R> {
+        self.entries.iter_mut()
+            .filter(|entry| entry.is_usable() && entry.next_hop_address == *next_hop_address)
+            .map(|entry| {
+                entry.status = RouteStatus::Inactive;
+                entry.destination_address
+            })
+            .collect()
+    }
+
+    /// The next hop towards `destination_address` if an active or validating route is known.
+    pub fn next_hop(&self, destination_address: &ShortAddress) -> Option<ShortAddress> {
+        self.get(destination_address)
+            .filter(|entry| entry.is_usable())
+            .map(|entry| entry.next_hop_address)
+    }
+
+    /// Looks up the next hop like `next_hop` and resets the age of the route. Sending a
+    /// frame over a route which is being validated activates it.
+    pub fn use_route(&mut self, destination_address: &ShortAddress) -> Option<ShortAddress> {
+        let entry = self.get_mut(destination_address)
+            .filter(|entry| entry.is_usable())?;
+        entry.status = RouteStatus::Active;
+        entry.age = 0;
+        Some(entry.next_hop_address)
+    }
+
+    /// Ages all routes by one period. Active routes which have not been used for more than
+    /// `age_limit` periods become inactive, unless they lead to a concentrator.
+    pub fn age(&mut self, age_limit: u8) {
+        for entry in self.entries.iter_mut() {
+            entry.age = entry.age.saturating_add(1);
+            if entry.status == RouteStatus::Active && !entry.many_to_one && entry.age > age_limit {
+                entry.status = RouteStatus::Inactive;
+            }
+        }
+    }
+}
+
+impl<const R: usize> Default for RoutingTable<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination_address: u16, status: RouteStatus, age: u8) -> RoutingTableEntry {
+        let mut entry = RoutingTableEntry::new(ShortAddress(destination_address), status, ShortAddress(0x0001));
+        entry.age = age;
+        entry
+    }
+
+    fn full_table(entries: [RoutingTableEntry; 3]) -> RoutingTable<3> {
+        let mut routing_table = RoutingTable::new();
+        for entry in entries {
+            routing_table.insert(entry).unwrap();
+        }
+        routing_table
+    }
+
+    #[test]
+    fn failed_routes_are_evicted_first() {
+        let mut routing_table = full_table([
+            route(0x0100, RouteStatus::Inactive, 9),
+            route(0x0200, RouteStatus::DiscoveryFailed, 0),
+            route(0x0300, RouteStatus::Active, 0),
+        ]);
+        routing_table.insert(route(0x0400, RouteStatus::Active, 0)).unwrap();
+        assert_eq!(routing_table.len(), 3);
+        assert!(routing_table.get(&ShortAddress(0x0200)).is_none());
+
+        routing_table.insert(route(0x0500, RouteStatus::Active, 0)).unwrap();
+        assert!(routing_table.get(&ShortAddress(0x0100)).is_none());
+        assert!(routing_table.get(&ShortAddress(0x0500)).is_some());
+    }
+
+    #[test]
+    fn longest_unused_inactive_route_is_evicted() {
+        let mut routing_table = full_table([
+            route(0x0100, RouteStatus::Inactive, 2),
+            route(0x0200, RouteStatus::Inactive, 5),
+            route(0x0300, RouteStatus::Inactive, 4),
+        ]);
+        routing_table.begin_discovery(ShortAddress(0x0400)).unwrap();
+        assert!(routing_table.get(&ShortAddress(0x0200)).is_none());
+        assert!(routing_table.get(&ShortAddress(0x0400)).unwrap().status == RouteStatus::DiscoveryUnderway);
+    }
+
+    #[test]
+    fn active_and_discovering_routes_are_never_evicted() {
+        let mut routing_table = full_table([
+            route(0x0100, RouteStatus::Active, 200),
+            route(0x0200, RouteStatus::DiscoveryUnderway, 200),
+            route(0x0300, RouteStatus::ValidationUnderway, 200),
+        ]);
+        assert!(routing_table.insert(route(0x0400, RouteStatus::Active, 0)).is_err());
+        assert!(routing_table.activate(ShortAddress(0x0400), ShortAddress(0x0002)).is_err());
+        assert_eq!(routing_table.len(), 3);
+
+        // Replacing the route of a known destination needs no room.
+        routing_table.insert(route(0x0100, RouteStatus::Inactive, 0)).unwrap();
+        assert!(routing_table.get(&ShortAddress(0x0100)).unwrap().status == RouteStatus::Inactive);
+    }
+
+    #[test]
+    fn flags_are_kept_across_rediscovery() {
+        let mut routing_table = RoutingTable::<3>::new();
+        routing_table.activate(ShortAddress(0x0100), ShortAddress(0x0001)).unwrap();
+        routing_table.get_mut(&ShortAddress(0x0100)).unwrap().many_to_one = true;
+
+        routing_table.begin_discovery(ShortAddress(0x0100)).unwrap();
+        assert_eq!(routing_table.next_hop(&ShortAddress(0x0100)), None);
+        routing_table.activate(ShortAddress(0x0100), ShortAddress(0x0002)).unwrap();
+        let entry = routing_table.get(&ShortAddress(0x0100)).unwrap();
+        assert!(entry.many_to_one);
+        assert_eq!(entry.next_hop_address, ShortAddress(0x0002));
+    }
+
+    #[test]
+    fn unused_routes_age_out() {
+        let mut routing_table = RoutingTable::<3>::new();
+        routing_table.activate(ShortAddress(0x0100), ShortAddress(0x0001)).unwrap();
+        routing_table.activate(ShortAddress(0x0200), ShortAddress(0x0001)).unwrap();
+        routing_table.activate(ShortAddress(0x0300), ShortAddress(0x0002)).unwrap();
+        routing_table.get_mut(&ShortAddress(0x0300)).unwrap().many_to_one = true;
+
+        for _ in 0..DEFAULT_ROUTE_AGE_LIMIT {
+            routing_table.age(DEFAULT_ROUTE_AGE_LIMIT);
+            assert_eq!(routing_table.use_route(&ShortAddress(0x0200)), Some(ShortAddress(0x0001)));
+        }
+        routing_table.age(DEFAULT_ROUTE_AGE_LIMIT);
+        assert_eq!(routing_table.next_hop(&ShortAddress(0x0100)), None);
+        assert_eq!(routing_table.next_hop(&ShortAddress(0x0200)), Some(ShortAddress(0x0001)));
+        assert_eq!(routing_table.next_hop(&ShortAddress(0x0300)), Some(ShortAddress(0x0002)));
+    }
+
+    #[test]
+    fn routes_over_failed_link_are_deactivated() {
+        let mut routing_table = RoutingTable::<3>::new();
+        routing_table.activate(ShortAddress(0x0100), ShortAddress(0x0001)).unwrap();
+        routing_table.activate(ShortAddress(0x0200), ShortAddress(0x0002)).unwrap();
+        routing_table.activate(ShortAddress(0x0300), ShortAddress(0x0001)).unwrap();
+
+        let mut deactivated = routing_table.deactivate_next_hop(&ShortAddress(0x0001));
+        deactivated.sort_unstable_by_key(|address| address.0);
+        assert_eq!(deactivated, [ShortAddress(0x0100), ShortAddress(0x0300)]);
+        assert_eq!(routing_table.count(RouteStatus::Inactive), 2);
+        assert_eq!(routing_table.count(RouteStatus::Active), 1);
+    }
+
+    #[test]
+    fn removal_keeps_the_order_of_the_pages() {
+        let mut routing_table = full_table([
+            route(0x0100, RouteStatus::Active, 0),
+            route(0x0200, RouteStatus::Inactive, 1),
+            route(0x0300, RouteStatus::Active, 0),
+        ]);
+        routing_table.remove(&ShortAddress(0x0100));
+        let page: heapless::Vec<ShortAddress, 3> = routing_table.entries_from(1).iter()
+            .map(|entry| entry.destination_address)
+            .collect();
+        assert_eq!(page, [ShortAddress(0x0300)]);
+
+        // Evicting makes room at the end.
+        routing_table.insert(route(0x0100, RouteStatus::Active, 0)).unwrap();
+        routing_table.insert(route(0x0400, RouteStatus::Active, 0)).unwrap();
+        let page: heapless::Vec<ShortAddress, 3> = routing_table.entries_from(0).iter()
+            .map(|entry| entry.destination_address)
+            .collect();
+        assert_eq!(page, [ShortAddress(0x0300), ShortAddress(0x0100), ShortAddress(0x0400)]);
+    }
+
+    #[test]
+    fn discovered_routes_are_validated_by_their_first_use() {
+        let mut routing_table = RoutingTable::<3>::new();
+        routing_table.begin_discovery(ShortAddress(0x0100)).unwrap();
+        routing_table.complete_discovery(ShortAddress(0x0100), ShortAddress(0x0001)).unwrap();
+        assert!(routing_table.get(&ShortAddress(0x0100)).unwrap().status == RouteStatus::ValidationUnderway);
+        assert_eq!(routing_table.next_hop(&ShortAddress(0x0100)), Some(ShortAddress(0x0001)));
+
+        // A cheaper reply during validation only moves the next hop.
+        routing_table.complete_discovery(ShortAddress(0x0100), ShortAddress(0x0002)).unwrap();
+        assert!(routing_table.get(&ShortAddress(0x0100)).unwrap().status == RouteStatus::ValidationUnderway);
+
+        assert_eq!(routing_table.use_route(&ShortAddress(0x0100)), Some(ShortAddress(0x0002)));
+        assert!(routing_table.get(&ShortAddress(0x0100)).unwrap().status == RouteStatus::Active);
+
+        // Replies for routes which were not being discovered activate them right away.
+        routing_table.complete_discovery(ShortAddress(0x0200), ShortAddress(0x0001)).unwrap();
+        assert!(routing_table.get(&ShortAddress(0x0200)).unwrap().status == RouteStatus::Active);
+    }
+}
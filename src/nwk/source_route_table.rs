@@ -0,0 +1,183 @@
+use crate::nwk::address::ShortAddress;
+use crate::nwk::commands::route_record::RouteRecord;
+use crate::nwk::constants::MAX_RELAY_COUNT;
+use crate::nwk::frame::{
+    NPDUFrame,
+    SourceRouteFrame,
+};
+
+/// Default of nwkMaxSourceRoute, the maximum number of relays in a source route.
+pub const DEFAULT_MAX_SOURCE_ROUTE: usize = 0x0c;
+
+/// Route Record Table Entry
+#[derive(Debug)]
+pub struct SourceRouteEntry {
+    pub destination: ShortAddress,
+    /// Relays towards the destination, starting with the relay closest to the destination.
+    pub relay_list: heapless::Vec<ShortAddress, MAX_RELAY_COUNT>,
+}
+
+/// Route Record Table
+///
+/// Kept by concentrators to learn the paths towards devices which sent a route record
+/// and to source route frames back to them. Holds up to `S` paths.
+pub struct SourceRouteTable<const S: usize> {
+    max_source_route: usize,
+    entries: heapless::Vec<SourceRouteEntry, S>,
+}
+
+impl<const S: usize> SourceRouteTable<S> {
+    pub fn new() -> Self {
+        Self {
+            max_source_route: DEFAULT_MAX_SOURCE_ROUTE,
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SourceRouteEntry> {
+        self.entries.iter()
+    }
+
+    pub fn get(&self, destination: &ShortAddress) -> Option<&SourceRouteEntry> {
+        self.entries.iter().find(|entry| entry.destination == *destination)
+    }
+
+    pub fn remove(&mut self, destination: &ShortAddress) -> Option<SourceRouteEntry> {
+        let index = self.entries.iter().position(|entry| entry.destination == *destination)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Upon receipt of a route record command frame.
+    ///
+    /// `source` is the network source address of the NPDU carrying the route record.
+    /// Records longer than nwkMaxSourceRoute are ignored. If the table is full, the
+    /// oldest learned path is dropped.
+    pub fn learn(&mut self, source: ShortAddress, route_record: &RouteRecord) {
+        if route_record.relay_list.len() > self.max_source_route {
+            return;
+        }
+
+        self.remove(&source);
+        if self.entries.is_full() && !self.entries.is_empty() {
+            self.entries.remove(0);
+        }
+        // Only fails for a table without any capacity.
+        self.entries.push(SourceRouteEntry {
+            destination: source,
+            relay_list: route_record.relay_list.clone(),
+        }).ok();
+    }
+
+    /// Builds the source route subframe towards `destination`.
+    ///
+    /// Returns `None` if no path is known or the destination is a direct neighbor.
+    pub fn source_route_frame(&self, destination: &ShortAddress) -> Option<SourceRouteFrame> {
+        let entry = self.get(destination)?;
+        SourceRouteFrame::new(entry.relay_list.clone())
+    }
+
+    /// Attaches the known source route to `frame` and returns the MAC destination of the
+    /// first hop. Returns `None` and leaves the frame untouched if no path is known.
+    pub fn apply(&self, frame: &mut NPDUFrame) -> Option<ShortAddress> {
+        let source_route_frame = self.source_route_frame(&frame.destination_address)?;
+        let next_hop = *source_route_frame.relay_list().get(source_route_frame.get_index() as usize)?;
+        frame.source_route_frame = Some(source_route_frame);
+        Some(next_hop)
+    }
+}
+
+impl<const S: usize> Default for SourceRouteTable<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::frame_builder::NPDUFrameBuilder;
+    use crate::nwk::payload::{
+        DataFrame,
+        Payload,
+    };
+
+    fn route_record(relays: &[u16]) -> RouteRecord {
+        RouteRecord {
+            relay_list: relays.iter().copied().map(ShortAddress).collect(),
+        }
+    }
+
+    fn data_frame(destination_address: ShortAddress) -> NPDUFrame {
+        NPDUFrameBuilder::new(Payload::Data(DataFrame { data: heapless::Vec::new() }))
+            .destination(destination_address)
+            .source(ShortAddress::COORDINATOR)
+            .build(&mut 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn source_route_starts_at_the_relay_closest_to_the_concentrator() {
+        let mut table = SourceRouteTable::<4>::new();
+        // The route record of 0x0100 passed 0x0101 first and 0x0103 last.
+        table.learn(ShortAddress(0x0100), &route_record(&[0x0101, 0x0102, 0x0103]));
+
+        let frame = table.source_route_frame(&ShortAddress(0x0100)).unwrap();
+        assert_eq!(frame.relay_list(), &[ShortAddress(0x0101), ShortAddress(0x0102), ShortAddress(0x0103)]);
+        assert_eq!(frame.get_index(), 2);
+
+        let mut frame = data_frame(ShortAddress(0x0100));
+        assert_eq!(table.apply(&mut frame), Some(ShortAddress(0x0103)));
+        assert_eq!(frame.source_route_frame.unwrap().get_index(), 2);
+    }
+
+    #[test]
+    fn unknown_and_direct_destinations_are_not_source_routed() {
+        let mut table = SourceRouteTable::<4>::new();
+        table.learn(ShortAddress(0x0100), &route_record(&[]));
+        assert!(table.get(&ShortAddress(0x0100)).is_some());
+        assert!(table.source_route_frame(&ShortAddress(0x0100)).is_none());
+
+        let mut frame = data_frame(ShortAddress(0x0200));
+        assert_eq!(table.apply(&mut frame), None);
+        assert!(frame.source_route_frame.is_none());
+    }
+
+    #[test]
+    fn learning_replaces_the_known_path() {
+        let mut table = SourceRouteTable::<4>::new();
+        table.learn(ShortAddress(0x0100), &route_record(&[0x0101, 0x0102]));
+        table.learn(ShortAddress(0x0100), &route_record(&[0x0104]));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&ShortAddress(0x0100)).unwrap().relay_list, [ShortAddress(0x0104)]);
+    }
+
+    #[test]
+    fn records_longer_than_max_source_route_are_ignored() {
+        let mut table = SourceRouteTable::<4>::new();
+        let relays: heapless::Vec<u16, 16> = (1..=DEFAULT_MAX_SOURCE_ROUTE as u16 + 1).collect();
+        table.learn(ShortAddress(0x0100), &route_record(&relays));
+        assert!(table.is_empty());
+        table.learn(ShortAddress(0x0100), &route_record(&relays[1..]));
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn full_table_drops_the_oldest_path() {
+        let mut table = SourceRouteTable::<2>::new();
+        table.learn(ShortAddress(0x0100), &route_record(&[0x0001]));
+        table.learn(ShortAddress(0x0200), &route_record(&[0x0002]));
+        table.learn(ShortAddress(0x0300), &route_record(&[0x0003]));
+        assert_eq!(table.len(), 2);
+        assert!(table.get(&ShortAddress(0x0100)).is_none());
+        assert!(table.get(&ShortAddress(0x0200)).is_some());
+        assert!(table.get(&ShortAddress(0x0300)).is_some());
+    }
+}
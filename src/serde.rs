@@ -1,4 +1,361 @@
-pub trait Serde<T, E> {
-    fn serialize(&self, data: &mut [u8]) -> Result<u8, E>;
-    fn deserialize(data: &[u8]) -> Result<T, E>;
-}
\ No newline at end of file
+//! Cursor based serialization of the wire formats.
+//!
+//! Types implement `Serde` by writing their fields to a `Writer` and reading them back
+//! from a `Reader`. The cursors do all bounds checking and keep track of the offset, so
+//! nested types simply call `write`/`read` of their fields in wire order.
+
+use core::fmt;
+
+/// Number of field and type names an error keeps, innermost first.
+const MAX_PATH_DEPTH: usize = 4;
+
+/// What went wrong while serializing or deserializing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SerdeErrorKind {
+    /// Writing needed `expected` more bytes, but only `actual` were left in the buffer.
+    NotEnoughSpace { expected: usize, actual: usize },
+    /// Reading needed `expected` more bytes, but only `actual` were left in the frame.
+    UnexpectedEnd { expected: usize, actual: usize },
+    /// The value was complete, but `count` bytes were left over.
+    TrailingBytes { count: usize },
+    /// A field holds a reserved or unknown value.
+    InvalidValue { value: u64 },
+    /// Table 3.40, the command frame identifier is unknown.
+    UnknownNWKCommand { identifier: u8 },
+    /// The relay count and index of a relay list do not fit together.
+    BrokenRelayList { relay_count: u8, relay_index: u8 },
+    /// A list or payload of `length` does not fit into its storage of `capacity`.
+    CapacityExceeded { length: usize, capacity: usize },
+    /// The frame does not contain the optional section which was accessed.
+    MissingSection,
+}
+
+impl fmt::Display for SerdeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerdeErrorKind::NotEnoughSpace { expected, actual } => {
+                write!(f, "not enough space, {} bytes needed but {} left", expected, actual)
+            },
+            SerdeErrorKind::UnexpectedEnd { expected, actual } => {
+                write!(f, "unexpected end, {} bytes needed but {} left", expected, actual)
+            },
+            SerdeErrorKind::TrailingBytes { count } => write!(f, "{} trailing bytes", count),
+            SerdeErrorKind::InvalidValue { value } => write!(f, "invalid value {:#x}", value),
+            SerdeErrorKind::UnknownNWKCommand { identifier } => {
+                write!(f, "unknown NWK command {:#04x}", identifier)
+            },
+            SerdeErrorKind::BrokenRelayList { relay_count, relay_index } => {
+                write!(f, "broken relay list, relay index {} of {} relays", relay_index, relay_count)
+            },
+            SerdeErrorKind::CapacityExceeded { length, capacity } => {
+                write!(f, "length {} exceeds the capacity of {}", length, capacity)
+            },
+            SerdeErrorKind::MissingSection => write!(f, "section not present"),
+        }
+    }
+}
+
+/// A failed (de)serialization with the position it failed at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerdeError {
+    pub kind: SerdeErrorKind,
+    /// Offset in bytes from the start of the buffer.
+    pub offset: usize,
+    path: heapless::Vec<&'static str, MAX_PATH_DEPTH>,
+    truncated: bool,
+}
+
+impl SerdeError {
+    pub fn new(kind: SerdeErrorKind, offset: usize) -> Self {
+        Self {
+            kind,
+            offset,
+            path: heapless::Vec::new(),
+            truncated: false,
+        }
+    }
+
+    pub fn invalid_value(value: impl Into<u64>, offset: usize) -> Self {
+        Self::new(SerdeErrorKind::InvalidValue { value: value.into() }, offset)
+    }
+
+    /// Adds the name of the field or type that contains the failed one.
+    pub fn context(mut self, name: &'static str) -> Self {
+        if self.path.push(name).is_err() {
+            self.truncated = true;
+        }
+        self
+    }
+
+    /// The names of the fields and types leading to the error, outermost first.
+    pub fn path(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.path.iter().rev().copied()
+    }
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)?;
+        if !self.path.is_empty() {
+            f.write_str(" in ")?;
+            if self.truncated {
+                f.write_str("..")?;
+            }
+            for (i, name) in self.path().enumerate() {
+                if i > 0 || self.truncated {
+                    f.write_str(".")?;
+                }
+                f.write_str(name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SerdeError {}
+
+/// Adds context to the error of a failed (de)serialization.
+pub trait Context {
+    fn context(self, name: &'static str) -> Self;
+}
+
+impl<T> Context for Result<T, SerdeError> {
+    fn context(self, name: &'static str) -> Self {
+        self.map_err(|error| error.context(name))
+    }
+}
+
+/// The name of `T` without its module path and generic arguments.
+fn type_name<T>() -> &'static str {
+    let name = core::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Writes into a byte buffer, advancing with every field.
+pub struct Writer<'a> {
+    data: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(data: &'a mut [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+        }
+    }
+
+    /// Number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerdeError> {
+        if self.remaining() < bytes.len() {
+            return Err(SerdeError::new(
+                SerdeErrorKind::NotEnoughSpace { expected: bytes.len(), actual: self.remaining() },
+                self.position,
+            ));
+        }
+        self.data[self.position..self.position + bytes.len()].copy_from_slice(bytes);
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    pub fn write_u8(&mut self, value: u8) -> Result<(), SerdeError> {
+        self.write_bytes(&[value])
+    }
+
+    pub fn write_i8(&mut self, value: i8) -> Result<(), SerdeError> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    pub fn write_u16(&mut self, value: u16) -> Result<(), SerdeError> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    pub fn write_u32(&mut self, value: u32) -> Result<(), SerdeError> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    pub fn write_u64(&mut self, value: u64) -> Result<(), SerdeError> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    pub fn write<T: Serde>(&mut self, value: &T) -> Result<(), SerdeError> {
+        value.write(self).context(type_name::<T>())
+    }
+}
+
+/// Reads from a byte buffer, advancing with every field.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+        }
+    }
+
+    /// Number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// An error of `kind` at the current position.
+    pub fn error(&self, kind: SerdeErrorKind) -> SerdeError {
+        SerdeError::new(kind, self.position)
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], SerdeError> {
+        if self.remaining() < length {
+            return Err(self.error(SerdeErrorKind::UnexpectedEnd { expected: length, actual: self.remaining() }));
+        }
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], SerdeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    /// Everything which was not read yet.
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+
+    pub fn peek_u8(&self) -> Result<u8, SerdeError> {
+        self.data.get(self.position)
+            .copied()
+            .ok_or_else(|| self.error(SerdeErrorKind::UnexpectedEnd { expected: 1, actual: 0 }))
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SerdeError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8, SerdeError> {
+        Ok(i8::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SerdeError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SerdeError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SerdeError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read<T: Serde>(&mut self) -> Result<T, SerdeError> {
+        T::read(self).context(type_name::<T>())
+    }
+
+    /// Fails if not all bytes were consumed.
+    pub fn finish(&self) -> Result<(), SerdeError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(SerdeErrorKind::TrailingBytes { count: self.remaining() }))
+        }
+    }
+}
+
+pub trait Serde: Sized {
+    /// The exact number of bytes `write` produces.
+    fn encoded_len(&self) -> usize;
+
+    fn write(&self, writer: &mut Writer) -> Result<(), SerdeError>;
+
+    fn read(reader: &mut Reader) -> Result<Self, SerdeError>;
+
+    /// Serializes into the start of `data` and returns the number of bytes written.
+    fn serialize(&self, data: &mut [u8]) -> Result<usize, SerdeError> {
+        if data.len() < self.encoded_len() {
+            return Err(SerdeError::new(
+                SerdeErrorKind::NotEnoughSpace { expected: self.encoded_len(), actual: data.len() },
+                0,
+            ).context(type_name::<Self>()));
+        }
+        let mut writer = Writer::new(data);
+        writer.write(self)?;
+        debug_assert_eq!(writer.position(), self.encoded_len());
+        Ok(writer.position())
+    }
+
+    /// Deserializes from `data`, which has to be consumed completely.
+    fn deserialize(data: &[u8]) -> Result<Self, SerdeError> {
+        let mut reader = Reader::new(data);
+        let value = reader.read()?;
+        reader.finish().context(type_name::<Self>())?;
+        Ok(value)
+    }
+}
+
+/// A sub-field of a bitfield, see `zigbee_rs_derive::Bitfield`.
+///
+/// `raw` holds the sub-field shifted down to bit zero and masked to its width. Writing
+/// fails with `SerdeErrorKind::InvalidValue` if `to_raw` does not fit into the sub-field.
+pub trait BitfieldValue: Sized {
+    fn to_raw(&self) -> u64;
+
+    fn from_raw(raw: u64) -> Result<Self, SerdeErrorKind>;
+}
+
+impl BitfieldValue for bool {
+    fn to_raw(&self) -> u64 {
+        *self as u64
+    }
+
+    fn from_raw(raw: u64) -> Result<Self, SerdeErrorKind> {
+        Ok(raw != 0)
+    }
+}
+
+impl BitfieldValue for u8 {
+    fn to_raw(&self) -> u64 {
+        *self as u64
+    }
+
+    fn from_raw(raw: u64) -> Result<Self, SerdeErrorKind> {
+        Ok(raw as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_names_drop_paths_and_generics() {
+        assert_eq!(type_name::<crate::nwk::address::ShortAddress>(), "ShortAddress");
+        assert_eq!(type_name::<heapless::Vec<crate::nwk::address::ShortAddress, 4>>(), "Vec");
+        assert_eq!(type_name::<u8>(), "u8");
+    }
+}
@@ -1,5 +1,6 @@
 use bitflags::bitflags;
 
+#[allow(non_snake_case)]
 pub struct State {
     pub device_type: DeviceType,
     pub bdbNodeIsOnANetwork: bool,
@@ -23,6 +24,13 @@ impl State {
     }
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DeviceType {
     Coordinator = 0b000,
     Router = 0b001,
@@ -52,6 +60,7 @@ bitflags! {
     }
 }
 
+#[allow(non_camel_case_types)]
 pub enum CommissioningStatus {
     SUCCESS = 0,
     IN_PROGRESS = 1,
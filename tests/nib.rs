@@ -0,0 +1,152 @@
+use std::convert::TryFrom;
+
+use zigbee_rs::nwk::address::{
+    ExtendedPanId,
+    IEEEAddress,
+    PanId,
+    ShortAddress,
+};
+use zigbee_rs::nwk::commands::rejoin_request::CapabilityInformation;
+use zigbee_rs::nwk::nib::{
+    NIBAttribute,
+    NIBStatus,
+    NIBStorage,
+    NIBValue,
+    NIB,
+};
+
+#[derive(Default)]
+struct Storage(Vec<(NIBAttribute, NIBValue)>);
+
+impl NIBStorage for Storage {
+    fn store(&mut self, attribute: NIBAttribute, value: NIBValue) {
+        self.0.retain(|(stored, _)| *stored != attribute);
+        self.0.push((attribute, value));
+    }
+
+    fn load(&mut self, attribute: NIBAttribute) -> Option<NIBValue> {
+        self.0.iter().find(|(stored, _)| *stored == attribute).map(|(_, value)| *value)
+    }
+}
+
+fn nib() -> NIB {
+    NIB::new(IEEEAddress(0x0012_4b00_0102_0304), 0x1234)
+}
+
+#[test]
+fn defaults() {
+    let nib = nib();
+    assert_eq!(nib.get(NIBAttribute::PanId), NIBValue::PanId(PanId::BROADCAST));
+    assert_eq!(nib.get(NIBAttribute::NetworkAddress), NIBValue::ShortAddress(ShortAddress(0xffff)));
+    assert_eq!(nib.get(NIBAttribute::SequenceNumber), NIBValue::U8(0x34));
+    assert_eq!(nib.get(NIBAttribute::MaxDepth), NIBValue::U8(0x0f));
+    assert_eq!(nib.get(NIBAttribute::SecurityLevel), NIBValue::U8(0x05));
+    assert_eq!(nib.get(NIBAttribute::IsConcentrator), NIBValue::Bool(false));
+    assert_eq!(nib.get(NIBAttribute::LinkStatusPeriod), NIBValue::U32(15_000));
+    assert_eq!(nib.get(NIBAttribute::EndDeviceTimeoutDefault), NIBValue::U8(8));
+    assert_eq!(nib.get(NIBAttribute::RouteDiscoveryRetriesPermitted), NIBValue::U8(3));
+    assert_eq!(nib.get(NIBAttribute::ParentInformation), NIBValue::U8(0));
+    assert_eq!(nib.get(NIBAttribute::CapabilityInformation), NIBValue::U8(0));
+}
+
+#[test]
+fn attribute_identifiers() {
+    for attribute in NIBAttribute::ALL.iter() {
+        assert_eq!(NIBAttribute::try_from(*attribute as u8), Ok(*attribute));
+    }
+    assert_eq!(NIBAttribute::try_from(0x94), Ok(NIBAttribute::UpdateId));
+    // nwkNeighborTable lives in `NeighborTable`.
+    assert_eq!(NIBAttribute::try_from(0x87), Err(NIBStatus::UnsupportedAttribute));
+    assert_eq!(NIBAttribute::try_from(0x00), Err(NIBStatus::UnsupportedAttribute));
+}
+
+#[test]
+fn set() {
+    let mut nib = nib();
+    for attribute in NIBAttribute::ALL.iter() {
+        let value = nib.get(*attribute);
+        assert_eq!(nib.set(*attribute, value), Ok(()), "{:?}", attribute);
+    }
+
+    nib.set(NIBAttribute::PanId, NIBValue::PanId(PanId(0x1a62))).unwrap();
+    nib.set(NIBAttribute::ManagerAddress, NIBValue::ShortAddress(ShortAddress(0x796f))).unwrap();
+    nib.set(NIBAttribute::EndDeviceTimeoutDefault, NIBValue::U8(14)).unwrap();
+    nib.set(NIBAttribute::CapabilityInformation, NIBValue::U8(0b1000_1110)).unwrap();
+    assert_eq!(nib.pan_id, PanId(0x1a62));
+    assert_eq!(nib.manager_address, ShortAddress(0x796f));
+    assert_eq!(nib.end_device_timeout_default as u8, 14);
+    assert!(nib.capability_information.contains(CapabilityInformation::ReceiverOnWhenIdle));
+
+    for (attribute, value) in [
+        (NIBAttribute::PanId, NIBValue::U16(0x1a62)),
+        (NIBAttribute::PanId, NIBValue::PanId(PanId(0x4000))),
+        (NIBAttribute::MaxDepth, NIBValue::U8(0x10)),
+        (NIBAttribute::SecurityLevel, NIBValue::U8(0x08)),
+        (NIBAttribute::ManagerAddress, NIBValue::ShortAddress(ShortAddress(0xfffc))),
+        (NIBAttribute::NetworkAddress, NIBValue::ShortAddress(ShortAddress(0xfffe))),
+        (NIBAttribute::EndDeviceTimeoutDefault, NIBValue::U8(15)),
+        (NIBAttribute::RouteDiscoveryRetriesPermitted, NIBValue::U8(4)),
+        // Reserved bits.
+        (NIBAttribute::CapabilityInformation, NIBValue::U8(0b0001_0000)),
+        (NIBAttribute::ParentInformation, NIBValue::U8(0b0000_1000)),
+    ]
+    .iter()
+    {
+        assert_eq!(nib.set(*attribute, *value), Err(NIBStatus::InvalidParameter), "{:?}", attribute);
+    }
+    assert_eq!(nib.pan_id, PanId(0x1a62));
+    assert_eq!(nib.max_depth, 0x0f);
+}
+
+#[test]
+fn sequence_number_wraps() {
+    let mut nib = nib();
+    nib.sequence_number = 0xff;
+    assert_eq!(nib.next_sequence_number(), 0xff);
+    assert_eq!(nib.next_sequence_number(), 0x00);
+}
+
+#[test]
+fn persistence() {
+    let mut storage = Storage::default();
+    let mut nib = nib();
+    nib.set(NIBAttribute::PanId, NIBValue::PanId(PanId(0x1a62))).unwrap();
+    nib.set(NIBAttribute::ExtendedPanId, NIBValue::ExtendedPanId(ExtendedPanId(0xdddd_dddd_dddd_dddd)))
+        .unwrap();
+    nib.network_address = ShortAddress(0x796f);
+    nib.update_id = 3;
+    nib.save(&mut storage);
+    assert!(storage.0.iter().all(|(attribute, _)| attribute.is_persistent()));
+    assert!(storage.load(NIBAttribute::SequenceNumber).is_none());
+
+    nib.set_and_store(NIBAttribute::UpdateId, NIBValue::U8(4), &mut storage).unwrap();
+    nib.set_and_store(NIBAttribute::MaxRouters, NIBValue::U8(8), &mut storage).unwrap();
+    assert!(storage.load(NIBAttribute::MaxRouters).is_none());
+
+    let mut restored = NIB::new(IEEEAddress(0), 0);
+    restored.restore(&mut storage).unwrap();
+    assert_eq!(restored.pan_id, PanId(0x1a62));
+    assert_eq!(restored.extended_pan_id, ExtendedPanId(0xdddd_dddd_dddd_dddd));
+    assert_eq!(restored.network_address, ShortAddress(0x796f));
+    assert_eq!(restored.update_id, 4);
+    assert_eq!(restored.ieee_address, IEEEAddress(0x0012_4b00_0102_0304));
+    assert_eq!(restored.max_routers, 5);
+
+    storage.store(NIBAttribute::MaxDepth, NIBValue::Bool(true));
+    assert_eq!(restored.restore(&mut storage), Err(NIBStatus::InvalidParameter));
+}
+
+#[test]
+fn rejected_restore_changes_nothing() {
+    let mut storage = Storage::default();
+    storage.store(NIBAttribute::PanId, NIBValue::PanId(PanId(0x1a62)));
+    storage.store(NIBAttribute::NetworkAddress, NIBValue::ShortAddress(ShortAddress(0x796f)));
+    // Restored after the attributes above.
+    storage.store(NIBAttribute::SecurityLevel, NIBValue::U8(0x08));
+
+    let mut nib = nib();
+    assert_eq!(nib.restore(&mut storage), Err(NIBStatus::InvalidParameter));
+    assert_eq!(nib.pan_id, PanId::BROADCAST);
+    assert_eq!(nib.network_address, ShortAddress(0xffff));
+    assert_eq!(nib.security_level, 0x05);
+}
@@ -0,0 +1,445 @@
+//! Serialize/deserialize round trips for every `Serde` implementation.
+//!
+//! The frame types do not implement `Debug` or `PartialEq`, so the strategies generate
+//! the raw field values and the tests compare encodings: a value is encoded, decoded and
+//! encoded again, and both encodings have to match. Commands that carry only a status byte
+//! or flags are wrapped in `NWKCommandFrame`, so the command identifier is covered as well.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use zigbee_rs::nwk::address::{
+    AddressType,
+    ExtendedPanId,
+    GroupIdentifier,
+    IEEEAddress,
+    PanId,
+    ShortAddress,
+};
+use zigbee_rs::nwk::commands::end_device_timeout_request::{
+    EndDeviceTimeoutRequest,
+    RequestedTimeout,
+};
+use zigbee_rs::nwk::commands::end_device_timeout_response::{
+    EndDeviceTimeoutResponse,
+    ParentInformation,
+    TimeoutStatus,
+};
+use zigbee_rs::nwk::commands::leave::Leave;
+use zigbee_rs::nwk::commands::link_power_delta::{
+    self,
+    LinkPowerDelta,
+    PowerDeltaEntry,
+    PowerDeltaType,
+};
+use zigbee_rs::nwk::commands::link_status::{
+    self,
+    LinkStatus,
+    LinkStatusEntry,
+};
+use zigbee_rs::nwk::commands::network_report::{
+    NetworkReport,
+    ReportInformation,
+    MAX_REPORT_INFORMATION_COUNT,
+};
+use zigbee_rs::nwk::commands::network_status::{
+    NetworkStatus,
+    StatusCode,
+};
+use zigbee_rs::nwk::commands::network_update::{
+    NetworkUpdate,
+    UpdateInformation,
+};
+use zigbee_rs::nwk::commands::rejoin_request::{
+    CapabilityInformation,
+    RejoinRequest,
+};
+use zigbee_rs::nwk::commands::rejoin_response::{
+    RejoinResponse,
+    RejoinStatus,
+};
+use zigbee_rs::nwk::commands::route_record::RouteRecord;
+use zigbee_rs::nwk::commands::route_reply::{
+    self,
+    RouteReply,
+};
+use zigbee_rs::nwk::commands::route_request::{
+    self,
+    ManyToOne,
+    RouteRequest,
+};
+use zigbee_rs::nwk::constants::{
+    MAX_NSDU_LENGTH,
+    MAX_RELAY_COUNT,
+};
+use zigbee_rs::nwk::frame::{
+    DiscoverRoute,
+    FrameControl,
+    FrameTypeEnum,
+    MulticastControl,
+    MulticastMode,
+    NPDUFrame,
+    SourceRouteFrame,
+};
+use zigbee_rs::nwk::payload::{
+    DataFrame,
+    NWKCommandFrame,
+    Payload,
+};
+use zigbee_rs::serde::Serde;
+
+const FRAME_TYPES: [FrameTypeEnum; 3] = [FrameTypeEnum::Data, FrameTypeEnum::NWKCommand, FrameTypeEnum::InterPan];
+const DISCOVER_ROUTES: [DiscoverRoute; 2] = [DiscoverRoute::SurpressDiscovery, DiscoverRoute::EnableDiscovery];
+const MULTICAST_MODES: [MulticastMode; 2] = [MulticastMode::NonmemberMode, MulticastMode::MemberMode];
+const MANY_TO_ONE: [ManyToOne; 3] = [ManyToOne::No, ManyToOne::SupportForRRTable, ManyToOne::NoSupportForRRTable];
+const POWER_DELTA_TYPES: [PowerDeltaType; 3] = [PowerDeltaType::Notification, PowerDeltaType::Request, PowerDeltaType::Response];
+const REJOIN_STATUSES: [RejoinStatus; 3] = [RejoinStatus::Success, RejoinStatus::PanAtCapacity, RejoinStatus::PanAccessDenied];
+const TIMEOUT_STATUSES: [TimeoutStatus; 2] = [TimeoutStatus::Success, TimeoutStatus::IncorrectValue];
+const STATUS_CODES: [StatusCode; 20] = [
+    StatusCode::NoRouteAvailable,
+    StatusCode::TreeLinkFailure,
+    StatusCode::NonTreeLinkFailure,
+    StatusCode::LowBatteryLevel,
+    StatusCode::NoRoutingCapacity,
+    StatusCode::NoIndirectCapacity,
+    StatusCode::IndirectTransactionExpiry,
+    StatusCode::TargetDeviceUnavailable,
+    StatusCode::TargetAddressUnallocated,
+    StatusCode::ParentLinkFailure,
+    StatusCode::ValidateRoute,
+    StatusCode::SourceRouteFailure,
+    StatusCode::ManyToOneRouteFailure,
+    StatusCode::AddressConflict,
+    StatusCode::VerifyAddresses,
+    StatusCode::PanIdentifierUpdate,
+    StatusCode::NetworkAddressUpdate,
+    StatusCode::BadFrameCounter,
+    StatusCode::BadKeySequenceNumber,
+    StatusCode::UnknownCommand,
+];
+
+fn encode<T: Serde>(value: &T) -> Vec<u8> {
+    let mut data = vec![0; value.encoded_len()];
+    let length = value.serialize(&mut data).unwrap_or_else(|error| panic!("{}", error));
+    assert_eq!(length, value.encoded_len());
+    data
+}
+
+fn decode<T: Serde>(data: &[u8]) -> T {
+    T::deserialize(data).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Encodes `value` and checks that decoding and encoding again gives the same bytes.
+fn round_trip<T: Serde>(value: &T) -> T {
+    let data = encode(value);
+    let decoded = decode::<T>(&data);
+    assert_eq!(encode(&decoded), data);
+    decoded
+}
+
+fn address_type(address: u16, is_multicast: bool) -> AddressType {
+    if is_multicast {
+        AddressType::Multicast(GroupIdentifier(address))
+    } else {
+        AddressType::Singlecast(ShortAddress(address))
+    }
+}
+
+fn route_request(
+    many_to_one: usize,
+    route_request_identifier: u8,
+    destination_address: AddressType,
+    path_cost: u8,
+    destination_ieee_address: Option<u64>,
+) -> RouteRequest {
+    RouteRequest {
+        command_options: route_request::CommandOptions {
+            many_to_one: MANY_TO_ONE[many_to_one],
+            contains_destination_ieee_address: destination_ieee_address.is_some(),
+            is_multicast: destination_address.is_multicast(),
+        },
+        route_request_identifier,
+        destination_address,
+        path_cost,
+        destination_ieee_address: destination_ieee_address.map(IEEEAddress),
+    }
+}
+
+proptest! {
+    #[test]
+    fn addresses(address: u16, ieee_address: u64) {
+        prop_assert_eq!(round_trip(&ShortAddress(address)).0, address);
+        prop_assert_eq!(round_trip(&GroupIdentifier(address)).0, address);
+        prop_assert_eq!(round_trip(&IEEEAddress(ieee_address)).0, ieee_address);
+        prop_assert_eq!(round_trip(&PanId(address)).0, address);
+        prop_assert_eq!(round_trip(&ExtendedPanId(ieee_address)).0, ieee_address);
+    }
+
+    #[test]
+    fn frame_control(frame_type in 0..3usize, protocol_version in 0..16u8, discover_route in 0..2usize) {
+        let control = FrameControl::new(FRAME_TYPES[frame_type], protocol_version, DISCOVER_ROUTES[discover_route]);
+        let decoded = round_trip(&control);
+        prop_assert_eq!(decoded.frame_type as u8, FRAME_TYPES[frame_type] as u8);
+        prop_assert_eq!(decoded.protocol_version, protocol_version);
+        prop_assert_eq!(decoded.discover_route as u8, DISCOVER_ROUTES[discover_route] as u8);
+    }
+
+    #[test]
+    fn multicast_control(mode in 0..2usize, nonmember_radius in 0..8u8, max_nonmember_radius in 0..8u8) {
+        let decoded = round_trip(&MulticastControl {
+            multicast_mode: MULTICAST_MODES[mode],
+            nonmember_radius,
+            max_nonmember_radius,
+        });
+        prop_assert_eq!(decoded.multicast_mode as u8, MULTICAST_MODES[mode] as u8);
+        prop_assert_eq!(decoded.nonmember_radius, nonmember_radius);
+        prop_assert_eq!(decoded.max_nonmember_radius, max_nonmember_radius);
+    }
+
+    #[test]
+    fn source_route_frame(relays in vec(any::<u16>(), 1..=MAX_RELAY_COUNT), decrements: usize) {
+        let mut frame = SourceRouteFrame::new(relays.iter().copied().map(ShortAddress).collect()).unwrap();
+        for _ in 0..decrements % relays.len() {
+            frame.decrement_index();
+        }
+        let decoded = round_trip(&frame);
+        prop_assert_eq!(decoded.get_index(), frame.get_index());
+        prop_assert!(decoded.relay_list().iter().map(|relay| relay.0).eq(relays));
+    }
+
+    #[test]
+    fn route_request_command(
+        many_to_one in 0..3usize,
+        route_request_identifier: u8,
+        destination_address: u16,
+        is_multicast: bool,
+        path_cost: u8,
+        destination_ieee_address: Option<u64>,
+    ) {
+        let destination_address = address_type(destination_address, is_multicast);
+        let command = route_request(many_to_one, route_request_identifier, destination_address, path_cost, destination_ieee_address);
+        let decoded = round_trip(&command);
+        prop_assert_eq!(decoded.command_options.many_to_one as u8, MANY_TO_ONE[many_to_one] as u8);
+        prop_assert!(decoded.destination_address == destination_address);
+        prop_assert_eq!(decoded.destination_ieee_address.map(|address| address.0), destination_ieee_address);
+    }
+
+    #[test]
+    fn route_reply_command(
+        route_request_identifier: u8,
+        originator_address: u16,
+        responder_address: u16,
+        is_multicast: bool,
+        path_cost: u8,
+        originator_ieee_address: Option<u64>,
+        responder_ieee_address: Option<u64>,
+    ) {
+        let responder_address = address_type(responder_address, is_multicast);
+        let decoded = round_trip(&RouteReply {
+            command_options: route_reply::CommandOptions {
+                contains_originator_ieee_address: originator_ieee_address.is_some(),
+                contains_responder_ieee_address: responder_ieee_address.is_some(),
+                is_multicast,
+            },
+            route_request_identifier,
+            originator_address: ShortAddress(originator_address),
+            responder_address,
+            path_cost,
+            originator_ieee_address: originator_ieee_address.map(IEEEAddress),
+            responder_ieee_address: responder_ieee_address.map(IEEEAddress),
+        });
+        prop_assert!(decoded.responder_address == responder_address);
+        prop_assert_eq!(decoded.originator_ieee_address.map(|address| address.0), originator_ieee_address);
+        prop_assert_eq!(decoded.responder_ieee_address.map(|address| address.0), responder_ieee_address);
+    }
+
+    #[test]
+    fn network_status_command(status_code in 0..STATUS_CODES.len(), destination_address: u16) {
+        let decoded = round_trip(&NWKCommandFrame::NetworkStatus(NetworkStatus {
+            status_code: STATUS_CODES[status_code],
+            destination_address: ShortAddress(destination_address),
+        }));
+        let NWKCommandFrame::NetworkStatus(decoded) = decoded else {
+            panic!("expected a network status command");
+        };
+        prop_assert_eq!(decoded.status_code as u8, STATUS_CODES[status_code] as u8);
+        prop_assert_eq!(decoded.destination_address.0, destination_address);
+    }
+
+    #[test]
+    fn leave_command(rejoin: bool, request: bool, remove_children: bool) {
+        let decoded = round_trip(&Leave { rejoin, request, remove_children });
+        prop_assert_eq!((decoded.rejoin, decoded.request, decoded.remove_children), (rejoin, request, remove_children));
+    }
+
+    #[test]
+    fn route_record_command(relays in vec(any::<u16>(), 0..=MAX_RELAY_COUNT)) {
+        let decoded = round_trip(&RouteRecord {
+            relay_list: relays.iter().copied().map(ShortAddress).collect(),
+        });
+        prop_assert_eq!(decoded.relay_list.len(), relays.len());
+    }
+
+    #[test]
+    fn rejoin_commands(
+        capability_information: u8,
+        network_address: u16,
+        rejoin_status in 0..3usize,
+    ) {
+        let capability_information = CapabilityInformation::from_bits_truncate(capability_information);
+        let decoded = round_trip(&NWKCommandFrame::RejoinRequest(RejoinRequest { capability_information }));
+        let NWKCommandFrame::RejoinRequest(decoded) = decoded else {
+            panic!("expected a rejoin request command");
+        };
+        prop_assert_eq!(decoded.capability_information.bits(), capability_information.bits());
+
+        let decoded = round_trip(&NWKCommandFrame::RejoinResponse(RejoinResponse {
+            network_address: ShortAddress(network_address),
+            rejoin_status: REJOIN_STATUSES[rejoin_status],
+        }));
+        let NWKCommandFrame::RejoinResponse(decoded) = decoded else {
+            panic!("expected a rejoin response command");
+        };
+        prop_assert_eq!(decoded.network_address.0, network_address);
+        prop_assert_eq!(decoded.rejoin_status as u8, REJOIN_STATUSES[rejoin_status] as u8);
+    }
+
+    #[test]
+    fn link_status_command(
+        first_frame: bool,
+        last_frame: bool,
+        entries in vec((any::<u16>(), 0..8u8, 0..8u8), 0..=link_status::MAX_ENTRY_COUNT),
+    ) {
+        let decoded = round_trip(&LinkStatus {
+            first_frame,
+            last_frame,
+            entries: entries.iter().map(|&(address, incoming_cost, outgoing_cost)| LinkStatusEntry {
+                neighbor_network_address: ShortAddress(address),
+                incoming_cost,
+                outgoing_cost,
+            }).collect(),
+        });
+        prop_assert_eq!((decoded.first_frame, decoded.last_frame), (first_frame, last_frame));
+        prop_assert_eq!(decoded.entries.len(), entries.len());
+    }
+
+    #[test]
+    fn network_report_command(extended_pan_id: u64, pan_ids in vec(any::<u16>(), 0..=MAX_REPORT_INFORMATION_COUNT)) {
+        let decoded = round_trip(&NetworkReport {
+            extended_pan_id: ExtendedPanId(extended_pan_id),
+            report_information: ReportInformation::PanIdentifierConflict(pan_ids.iter().copied().map(PanId).collect()),
+        });
+        let ReportInformation::PanIdentifierConflict(decoded_pan_ids) = decoded.report_information;
+        prop_assert!(decoded_pan_ids.iter().map(|pan_id| pan_id.0).eq(pan_ids));
+    }
+
+    #[test]
+    fn network_update_command(extended_pan_id: u64, update_id: u8, pan_id: u16) {
+        let decoded = round_trip(&NetworkUpdate {
+            extended_pan_id: ExtendedPanId(extended_pan_id),
+            update_id,
+            update_information: UpdateInformation::PanIdentifierUpdate(PanId(pan_id)),
+        });
+        prop_assert_eq!((decoded.extended_pan_id.0, decoded.update_id), (extended_pan_id, update_id));
+        prop_assert!(decoded.update_information == UpdateInformation::PanIdentifierUpdate(PanId(pan_id)));
+    }
+
+    #[test]
+    fn requested_timeout(value in 0..=RequestedTimeout::Minutes16384 as u8) {
+        let timeout = RequestedTimeout::from_u8(value).unwrap();
+        prop_assert_eq!(round_trip(&timeout) as u8, value);
+    }
+
+    #[test]
+    fn end_device_timeout_commands(
+        requested_timeout: u8,
+        end_device_configuration: u8,
+        status in 0..2usize,
+        parent_information: u8,
+    ) {
+        let parent_information = ParentInformation::from_bits_truncate(parent_information);
+        let decoded = round_trip(&NWKCommandFrame::EndDeviceTimeoutRequest(EndDeviceTimeoutRequest {
+            requested_timeout,
+            end_device_configuration,
+        }));
+        let NWKCommandFrame::EndDeviceTimeoutRequest(decoded) = decoded else {
+            panic!("expected an end device timeout request command");
+        };
+        prop_assert_eq!(
+            (decoded.requested_timeout, decoded.end_device_configuration),
+            (requested_timeout, end_device_configuration)
+        );
+
+        let decoded = round_trip(&NWKCommandFrame::EndDeviceTimeoutResponse(EndDeviceTimeoutResponse {
+            status: TIMEOUT_STATUSES[status],
+            parent_information,
+        }));
+        let NWKCommandFrame::EndDeviceTimeoutResponse(decoded) = decoded else {
+            panic!("expected an end device timeout response command");
+        };
+        prop_assert_eq!(decoded.status as u8, TIMEOUT_STATUSES[status] as u8);
+        prop_assert_eq!(decoded.parent_information.bits(), parent_information.bits());
+    }
+
+    #[test]
+    fn link_power_delta_command(
+        power_delta_type in 0..3usize,
+        entries in vec((any::<u16>(), any::<i8>()), 0..=link_power_delta::MAX_ENTRY_COUNT),
+    ) {
+        let decoded = round_trip(&LinkPowerDelta {
+            power_delta_type: POWER_DELTA_TYPES[power_delta_type],
+            entries: entries.iter().map(|&(address, power_delta)| PowerDeltaEntry {
+                device_address: ShortAddress(address),
+                power_delta,
+            }).collect(),
+        });
+        prop_assert_eq!(decoded.power_delta_type as u8, POWER_DELTA_TYPES[power_delta_type] as u8);
+        prop_assert_eq!(decoded.entries.len(), entries.len());
+    }
+
+    #[test]
+    fn npdu_frame(
+        discover_route in 0..2usize,
+        destination_address: u16,
+        source_address: u16,
+        radius: u8,
+        sequence_number: u8,
+        destination_ieee_address: Option<u64>,
+        source_ieee_address: Option<u64>,
+        multicast_control in proptest::option::of((0..2usize, 0..8u8, 0..8u8)),
+        relays in proptest::option::of(vec(any::<u16>(), 1..=8)),
+        data in proptest::option::of(vec(any::<u8>(), 0..=MAX_NSDU_LENGTH - 48)),
+        many_to_one in 0..3usize,
+    ) {
+        let (frame_type, payload) = match data {
+            Some(data) => (FrameTypeEnum::Data, Payload::Data(DataFrame { data: data.iter().copied().collect() })),
+            None => (FrameTypeEnum::NWKCommand, Payload::NWKCommand(NWKCommandFrame::RouteRequest(
+                route_request(many_to_one, sequence_number, AddressType::Singlecast(ShortAddress(destination_address)), 0, None),
+            ))),
+        };
+        let frame = NPDUFrame {
+            control: FrameControl::new(frame_type, 2, DISCOVER_ROUTES[discover_route]),
+            destination_address: ShortAddress(destination_address),
+            source_address: ShortAddress(source_address),
+            radius,
+            sequence_number,
+            destination_ieee_address: destination_ieee_address.map(IEEEAddress),
+            source_ieee_address: source_ieee_address.map(IEEEAddress),
+            multicast_control: multicast_control.map(|(mode, nonmember_radius, max_nonmember_radius)| MulticastControl {
+                multicast_mode: MULTICAST_MODES[mode],
+                nonmember_radius,
+                max_nonmember_radius,
+            }),
+            source_route_frame: relays.map(|relays| SourceRouteFrame::new(relays.into_iter().map(ShortAddress).collect()).unwrap()),
+            payload,
+        };
+        let decoded = round_trip(&frame);
+        prop_assert_eq!(decoded.control.frame_type as u8, frame_type as u8);
+        prop_assert_eq!(decoded.control.discover_route as u8, DISCOVER_ROUTES[discover_route] as u8);
+        prop_assert_eq!(decoded.control.is_multicast(), frame.multicast_control.is_some());
+        prop_assert_eq!(decoded.control.contains_source_route_frame(), frame.source_route_frame.is_some());
+        prop_assert_eq!(decoded.destination_address, frame.destination_address);
+        prop_assert_eq!(decoded.source_address, frame.source_address);
+        prop_assert_eq!(decoded.destination_ieee_address, frame.destination_ieee_address);
+        prop_assert_eq!(decoded.source_ieee_address, frame.source_ieee_address);
+    }
+}
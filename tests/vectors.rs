@@ -0,0 +1,368 @@
+//! Golden byte vectors laid out after the frame formats of the Zigbee specification,
+//! document 05-3474-20 (see the README). Each vector names the section it follows, so
+//! that it can be checked against the specification independently of the crate. Every
+//! vector has to decode to the expected fields and encode back to the very same bytes.
+
+use zigbee_rs::nwk::address::{
+    AddressType,
+    BroadcastClass,
+    ExtendedPanId,
+    GroupIdentifier,
+    IEEEAddress,
+    PanId,
+    ShortAddress,
+};
+use zigbee_rs::nwk::commands::route_request::RouteRequest;
+use zigbee_rs::nwk::frame::{
+    DiscoverRoute,
+    FrameControl,
+    FrameTypeEnum,
+    MulticastControl,
+    MulticastMode,
+    NPDUFrame,
+    SourceRouteFrame,
+};
+use zigbee_rs::nwk::payload::{
+    NWKCommandFrame,
+    Payload,
+};
+use zigbee_rs::serde::{
+    Serde,
+    SerdeError,
+    SerdeErrorKind,
+};
+
+fn decode<T: Serde>(data: &[u8]) -> T {
+    let value = T::deserialize(data).unwrap_or_else(|error| panic!("{}", error));
+    let mut encoded = vec![0; value.encoded_len()];
+    let length = value.serialize(&mut encoded).unwrap_or_else(|error| panic!("{}", error));
+    assert_eq!(&encoded[..length], data);
+    value
+}
+
+fn decode_error<T: Serde>(data: &[u8]) -> SerdeError {
+    match T::deserialize(data) {
+        Ok(_) => panic!("{:02x?} must not decode", data),
+        Err(error) => error,
+    }
+}
+
+fn encode_error<T: Serde>(value: &T) -> SerdeError {
+    let mut encoded = vec![0; value.encoded_len()];
+    match value.serialize(&mut encoded) {
+        Ok(_) => panic!("{:02x?} must not encode", encoded),
+        Err(error) => error,
+    }
+}
+
+/// Unicast data frame towards the coordinator with route discovery enabled, 3.3.1 General
+/// NPDU Frame Format and 3.3.2.1 Data Frame Format.
+#[test]
+fn npdu_data_frame() {
+    let frame: NPDUFrame = decode(&[
+        0x48, 0x00, // data, protocol version 2, enable route discovery
+        0x00, 0x00, // destination 0x0000
+        0x6f, 0x79, // source 0x796f
+        0x1e, // radius
+        0x5a, // sequence number
+        0x40, 0x0a, 0x06, 0x00, // payload
+    ]);
+    assert_eq!(frame.control.frame_type as u8, 0b00);
+    assert_eq!(frame.control.protocol_version, 2);
+    assert_eq!(frame.control.discover_route as u8, 0b01);
+    assert_eq!(frame.destination_address, ShortAddress(0x0000));
+    assert_eq!(frame.source_address, ShortAddress(0x796f));
+    assert_eq!((frame.radius, frame.sequence_number), (0x1e, 0x5a));
+    match frame.payload {
+        Payload::Data(data) => assert_eq!(&data.data[..], &[0x40, 0x0a, 0x06, 0x00]),
+        _ => panic!("expected a data frame"),
+    }
+}
+
+/// Many-to-one route request a concentrator broadcasts to all routers, 3.4.1 Route Request
+/// Command inside an NPDU carrying the source IEEE address, 3.3.1.7.
+#[test]
+fn npdu_many_to_one_route_request() {
+    let frame: NPDUFrame = decode(&[
+        0x09, 0x10, // NWK command, protocol version 2, source IEEE address present
+        0xfc, 0xff, // destination 0xfffc
+        0x00, 0x00, // source 0x0000
+        0x1e, // radius
+        0x8c, // sequence number
+        0x04, 0x03, 0x02, 0x01, 0x00, 0x4b, 0x12, 0x00, // source IEEE address
+        0x01, // route request
+        0x08, // many-to-one with route record table
+        0x2d, // route request identifier
+        0xfc, 0xff, // destination 0xfffc
+        0x00, // path cost
+    ]);
+    assert_eq!(frame.control.frame_type as u8, 0b01);
+    assert!(frame.control.contains_source_ieee_address());
+    assert_eq!(frame.source_ieee_address, Some(IEEEAddress(0x0012_4b00_0102_0304)));
+    match frame.payload {
+        Payload::NWKCommand(NWKCommandFrame::RouteRequest(command)) => {
+            assert_eq!(command.command_options.many_to_one as u8, 0x1);
+            assert_eq!(command.route_request_identifier, 0x2d);
+            assert!(command.destination_address == AddressType::Singlecast(ShortAddress(0xfffc)));
+        },
+        _ => panic!("expected a route request"),
+    }
+}
+
+/// Multicast data frame which is source routed over two relays, 3.3.1.8 Multicast Control
+/// Field and 3.3.1.9 Source Route Subframe Field.
+#[test]
+fn npdu_multicast_source_routed() {
+    let frame: NPDUFrame = decode(&[
+        0x08, 0x05, // data, protocol version 2, multicast, source route present
+        0x01, 0x00, // group 0x0001
+        0x34, 0x12, // source 0x1234
+        0x05, // radius
+        0x10, // sequence number
+        0xed, // member mode, nonmember radius 3, max nonmember radius 7
+        0x02, 0x01, 0x11, 0x11, 0x22, 0x22, // two relays, relay index 1
+        0xaa, 0xbb, // payload
+    ]);
+    assert!(frame.control.is_multicast());
+    assert!(frame.control.contains_source_route_frame());
+    let multicast_control = frame.multicast_control.unwrap();
+    assert_eq!(multicast_control.multicast_mode as u8, 0b01);
+    assert_eq!(multicast_control.nonmember_radius, 3);
+    assert_eq!(multicast_control.max_nonmember_radius, 7);
+    let source_route_frame = frame.source_route_frame.unwrap();
+    assert_eq!(source_route_frame.get_index(), 1);
+    assert_eq!(source_route_frame.relay_list(), &[ShortAddress(0x1111), ShortAddress(0x2222)]);
+}
+
+/// Route reply travelling back to the originator of a route request, 3.4.2 Route Reply
+/// Command with the responder IEEE address, 3.4.2.3.1.
+#[test]
+fn npdu_route_reply() {
+    let frame: NPDUFrame = decode(&[
+        0x09, 0x00, // NWK command, protocol version 2, suppress route discovery
+        0x00, 0x00, // destination 0x0000
+        0x6f, 0x79, // source 0x796f
+        0x1e, // radius
+        0x5b, // sequence number
+        0x02, // route reply
+        0x20, // responder IEEE address present
+        0x2d, // route request identifier
+        0x00, 0x00, // originator 0x0000
+        0x6f, 0x79, // responder 0x796f
+        0x07, // path cost
+        0x08, 0x07, 0x06, 0x05, 0x00, 0x4b, 0x12, 0x00, // responder IEEE address
+    ]);
+    match frame.payload {
+        Payload::NWKCommand(NWKCommandFrame::RouteReply(command)) => {
+            assert!(!command.command_options.contains_originator_ieee_address);
+            assert!(command.command_options.contains_responder_ieee_address);
+            assert!(!command.command_options.is_multicast);
+            assert_eq!(command.route_request_identifier, 0x2d);
+            assert_eq!(command.originator_address, ShortAddress(0x0000));
+            assert!(command.responder_address == AddressType::Singlecast(ShortAddress(0x796f)));
+            assert_eq!(command.path_cost, 0x07);
+            assert_eq!(command.originator_ieee_address, None);
+            assert_eq!(command.responder_ieee_address, Some(IEEEAddress(0x0012_4b00_0506_0708)));
+        },
+        _ => panic!("expected a route reply"),
+    }
+}
+
+/// Link status a router broadcasts to its neighbours, 3.4.8 Link Status Command
+/// with the entry layout of 3.4.8.3.2.
+#[test]
+fn npdu_link_status() {
+    let frame: NPDUFrame = decode(&[
+        0x09, 0x10, // NWK command, protocol version 2, source IEEE address present
+        0xfc, 0xff, // destination 0xfffc
+        0x6f, 0x79, // source 0x796f
+        0x01, // radius
+        0x5c, // sequence number
+        0x08, 0x07, 0x06, 0x05, 0x00, 0x4b, 0x12, 0x00, // source IEEE address
+        0x08, // link status
+        0x62, // first and last frame, two entries
+        0x00, 0x00, 0x11, // 0x0000, incoming cost 1, outgoing cost 1
+        0x34, 0x12, 0x73, // 0x1234, incoming cost 3, outgoing cost 7
+    ]);
+    assert_eq!(frame.destination_address, ShortAddress(0xfffc));
+    assert_eq!(frame.radius, 1);
+    match frame.payload {
+        Payload::NWKCommand(NWKCommandFrame::LinkStatus(command)) => {
+            assert!(command.first_frame && command.last_frame);
+            assert_eq!(command.entries.len(), 2);
+            assert_eq!(command.entries[0].neighbor_network_address, ShortAddress(0x0000));
+            assert_eq!((command.entries[0].incoming_cost, command.entries[0].outgoing_cost), (1, 1));
+            assert_eq!(command.entries[1].neighbor_network_address, ShortAddress(0x1234));
+            assert_eq!((command.entries[1].incoming_cost, command.entries[1].outgoing_cost), (3, 7));
+        },
+        _ => panic!("expected a link status"),
+    }
+}
+
+#[test]
+fn npdu_reserved_discover_route() {
+    let error = decode_error::<NPDUFrame>(&[0x88, 0x00, 0x00, 0x00, 0x34, 0x12, 0x1e, 0x01]);
+    assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 0b10 });
+    assert_eq!(error.offset, 0);
+    assert_eq!(error.path().collect::<Vec<_>>(), ["NPDUFrame", "FrameControl", "discover_route"]);
+}
+
+#[test]
+fn npdu_truncated() {
+    let error = decode_error::<NPDUFrame>(&[0x48, 0x00, 0x00, 0x00, 0x6f]);
+    assert_eq!(error.kind, SerdeErrorKind::UnexpectedEnd { expected: 2, actual: 1 });
+    assert_eq!(error.offset, 4);
+    assert_eq!(error.path().collect::<Vec<_>>(), ["NPDUFrame", "source_address", "ShortAddress"]);
+}
+
+#[test]
+fn npdu_unknown_command() {
+    let error = decode_error::<NPDUFrame>(&[0x09, 0x00, 0x00, 0x00, 0x34, 0x12, 0x1e, 0x01, 0x77]);
+    assert_eq!(error.kind, SerdeErrorKind::UnknownNWKCommand { identifier: 0x77 });
+    assert_eq!(error.offset, 8);
+}
+
+/// 3.4.1.3.1 Command Options Field with the destination IEEE address, 3.4.1.3.5.
+#[test]
+fn route_request_unicast_with_ieee_address() {
+    let command: RouteRequest = decode(&[
+        0x20, // destination IEEE address present
+        0x07, // route request identifier
+        0x34, 0x12, // destination 0x1234
+        0x00, // path cost
+        0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, // destination IEEE address
+    ]);
+    assert_eq!(command.command_options.many_to_one as u8, 0x0);
+    assert!(command.command_options.contains_destination_ieee_address);
+    assert!(!command.command_options.is_multicast);
+    assert_eq!(command.route_request_identifier, 0x07);
+    assert_eq!(command.destination_ieee_address, Some(IEEEAddress(0x1122_3344_5566_7788)));
+}
+
+#[test]
+fn route_request_multicast() {
+    let command: RouteRequest = decode(&[0x40, 0x07, 0x01, 0x00, 0x03]);
+    assert!(command.command_options.is_multicast);
+    assert!(command.destination_address.is_multicast());
+    assert_eq!(command.path_cost, 0x03);
+}
+
+#[test]
+fn route_request_many_to_one_without_route_record_table() {
+    let command: RouteRequest = decode(&[0x10, 0x2d, 0xfc, 0xff, 0x00]);
+    assert_eq!(command.command_options.many_to_one as u8, 0x2);
+}
+
+#[test]
+fn route_request_reserved_many_to_one() {
+    let error = decode_error::<RouteRequest>(&[0x18, 0x2d, 0xfc, 0xff, 0x00]);
+    assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 0b11 });
+    assert_eq!(error.path().collect::<Vec<_>>(), ["RouteRequest", "CommandOptions", "many_to_one"]);
+}
+
+/// 3.3.1.9 Source Route Subframe Field, relay count 3 and relay index 2.
+#[test]
+fn source_route_frame() {
+    let frame: SourceRouteFrame = decode(&[0x03, 0x02, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00]);
+    assert_eq!(frame.get_index(), 2);
+    assert_eq!(frame.relay_list(), &[ShortAddress(0x0001), ShortAddress(0x0002), ShortAddress(0x0003)]);
+}
+
+#[test]
+fn source_route_frame_broken() {
+    assert_eq!(
+        decode_error::<SourceRouteFrame>(&[0x02, 0x02, 0x11, 0x11, 0x22, 0x22]).kind,
+        SerdeErrorKind::BrokenRelayList { relay_count: 2, relay_index: 2 },
+    );
+    assert_eq!(
+        decode_error::<SourceRouteFrame>(&[0x00, 0x00]).kind,
+        SerdeErrorKind::BrokenRelayList { relay_count: 0, relay_index: 0 },
+    );
+    assert_eq!(
+        decode_error::<SourceRouteFrame>(&[0x02, 0x01, 0x11, 0x11, 0x22]).kind,
+        SerdeErrorKind::UnexpectedEnd { expected: 2, actual: 1 },
+    );
+}
+
+/// 3.3.1.8 Multicast Control Field, mode in bits 0-1 and the radii in bits 2-4 and 5-7.
+#[test]
+fn multicast_control() {
+    let control: MulticastControl = decode(&[0xed]);
+    assert_eq!(control.multicast_mode as u8, 0b01);
+    assert_eq!((control.nonmember_radius, control.max_nonmember_radius), (3, 7));
+
+    let control: MulticastControl = decode(&[0x00]);
+    assert_eq!(control.multicast_mode as u8, 0b00);
+    assert_eq!((control.nonmember_radius, control.max_nonmember_radius), (0, 0));
+}
+
+#[test]
+fn multicast_control_reserved_mode() {
+    let error = decode_error::<MulticastControl>(&[0x02]);
+    assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 0b10 });
+    assert_eq!(error.path().collect::<Vec<_>>(), ["MulticastControl", "multicast_mode"]);
+}
+
+#[test]
+fn multicast_control_nonmember_radius_out_of_range() {
+    let control = MulticastControl {
+        multicast_mode: MulticastMode::MemberMode,
+        nonmember_radius: 8,
+        max_nonmember_radius: 7,
+    };
+    let error = encode_error(&control);
+    assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 8 });
+    assert_eq!(error.path().collect::<Vec<_>>(), ["MulticastControl", "nonmember_radius"]);
+
+    let control = MulticastControl { max_nonmember_radius: 0xff, ..control };
+    assert_eq!(encode_error(&control).kind, SerdeErrorKind::InvalidValue { value: 8 });
+    let control = MulticastControl { nonmember_radius: 7, ..control };
+    assert_eq!(encode_error(&control).kind, SerdeErrorKind::InvalidValue { value: 0xff });
+}
+
+#[test]
+fn frame_control_protocol_version_out_of_range() {
+    let error = encode_error(&FrameControl::new(FrameTypeEnum::Data, 16, DiscoverRoute::SurpressDiscovery));
+    assert_eq!(error.kind, SerdeErrorKind::InvalidValue { value: 16 });
+    assert_eq!(error.path().collect::<Vec<_>>(), ["FrameControl", "protocol_version"]);
+
+    let frame: NPDUFrame = decode(&[0x3c, 0x00, 0x00, 0x00, 0x34, 0x12, 0x1e, 0x01]);
+    assert_eq!(frame.control.protocol_version, 0xf);
+}
+
+#[test]
+fn short_address_classes() {
+    assert!(ShortAddress::COORDINATOR.is_coordinator());
+    assert!(ShortAddress(0xfff7).is_unicast());
+    for address in 0xfff8..=0xfffa {
+        assert!(ShortAddress(address).is_reserved());
+    }
+    assert!(ShortAddress(0xfffe).is_reserved());
+    assert_eq!(ShortAddress(0xfffb).broadcast_class(), Some(BroadcastClass::LowPowerRouters));
+    assert_eq!(ShortAddress(0xfffc).broadcast_class(), Some(BroadcastClass::RoutersAndCoordinator));
+    assert_eq!(ShortAddress(0xfffd).broadcast_class(), Some(BroadcastClass::RxOnWhenIdle));
+    assert_eq!(ShortAddress(0xffff).broadcast_class(), Some(BroadcastClass::AllDevices));
+    assert_eq!(ShortAddress(0xfffe).broadcast_class(), None);
+}
+
+#[test]
+fn address_strings() {
+    assert_eq!(ShortAddress(0x796f).to_string(), "0x796f");
+    assert_eq!("0x796f".parse(), Ok(ShortAddress(0x796f)));
+    assert_eq!("fffc".parse(), Ok(ShortAddress(0xfffc)));
+    assert_eq!("0x1a62".parse(), Ok(PanId(0x1a62)));
+    assert_eq!(GroupIdentifier(0x0001).to_string(), "0x0001");
+
+    let address = IEEEAddress(0x0012_4b00_0102_0304);
+    assert_eq!(address.to_string(), "00:12:4b:00:01:02:03:04");
+    assert_eq!("00:12:4b:00:01:02:03:04".parse(), Ok(address));
+    assert_eq!("0x00124b0001020304".parse(), Ok(address));
+    assert_eq!(address.to_le_bytes(), [0x04, 0x03, 0x02, 0x01, 0x00, 0x4b, 0x12, 0x00]);
+    assert_eq!(ExtendedPanId(0xdddd_dddd_dddd_dddd).to_string(), "dd:dd:dd:dd:dd:dd:dd:dd");
+
+    assert!("0x12345".parse::<ShortAddress>().is_err());
+    assert!("00:12:4b:00:01:02:03".parse::<IEEEAddress>().is_err());
+    assert!("00:12:4b:00:01:02:03:04:05".parse::<IEEEAddress>().is_err());
+    assert!("00:12:4b:00:1:02:03:04".parse::<ExtendedPanId>().is_err());
+    assert!("+1".parse::<PanId>().is_err());
+}
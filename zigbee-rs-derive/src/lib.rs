@@ -0,0 +1,401 @@
+//! Derives for the bitfield structures of the zigbee-rs wire formats.
+//!
+//! `#[derive(Bitfield)]` implements `zigbee_rs::serde::Serde` for a struct whose fields
+//! are sub-fields of a little endian bitfield:
+//!
+//! ```ignore
+//! #[derive(Bitfield)]
+//! #[bitfield(bytes = 1)]
+//! pub struct MulticastControl {
+//!     #[bits(0..2)]
+//!     pub multicast_mode: MulticastMode,
+//!     #[bits(2..5)]
+//!     pub nonmember_radius: u8,
+//!     #[bits(5..8)]
+//!     pub max_nonmember_radius: u8,
+//! }
+//! ```
+//!
+//! A field is either a single bit `#[bits(n)]` or a range `#[bits(start..end)]`. Bits not
+//! covered by any field are reserved, written as zero and ignored when reading. Field
+//! types implement `zigbee_rs::serde::BitfieldValue`, which `#[derive(BitfieldEnum)]`
+//! provides for enums with explicit discriminants. Writing a value which does not fit
+//! into its sub-field fails with `SerdeErrorKind::InvalidValue`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    spanned::Spanned,
+    Data,
+    DeriveInput,
+    Error,
+    Expr,
+    ExprLit,
+    ExprRange,
+    Fields,
+    Lit,
+    RangeLimits,
+};
+
+/// Largest bitfield, the sub-fields are assembled in a `u64`.
+const MAX_BYTES: usize = 8;
+
+#[proc_macro_derive(Bitfield, attributes(bitfield, bits))]
+pub fn derive_bitfield(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    bitfield(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(BitfieldEnum)]
+pub fn derive_bitfield_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    bitfield_enum(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A sub-field covering `width` bits starting at bit `offset`.
+struct Bits {
+    offset: usize,
+    width: usize,
+}
+
+impl Bits {
+    fn mask(&self) -> u64 {
+        if self.width == 64 {
+            u64::MAX
+        } else {
+            (1 << self.width) - 1
+        }
+    }
+}
+
+fn int_literal(expr: &Expr) -> syn::Result<usize> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse(),
+        _ => Err(Error::new(expr.span(), "expected an integer literal")),
+    }
+}
+
+fn parse_bits(expr: &Expr) -> syn::Result<Bits> {
+    match expr {
+        Expr::Range(ExprRange { start: Some(start), limits, end: Some(end), .. }) => {
+            let offset = int_literal(start)?;
+            let end = match limits {
+                RangeLimits::HalfOpen(_) => int_literal(end)?,
+                RangeLimits::Closed(_) => int_literal(end)? + 1,
+            };
+            if end <= offset {
+                return Err(Error::new(expr.span(), "empty bit range"));
+            }
+            Ok(Bits {
+                offset,
+                width: end - offset,
+            })
+        },
+        _ => Ok(Bits {
+            offset: int_literal(expr)?,
+            width: 1,
+        }),
+    }
+}
+
+/// Reads `#[bitfield(bytes = N)]`, one byte if absent.
+fn parse_bytes(input: &DeriveInput) -> syn::Result<usize> {
+    let mut bytes = 1;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("bitfield")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bytes") {
+                bytes = meta.value()?.parse::<syn::LitInt>()?.base10_parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unknown bitfield attribute, expected `bytes`"))
+            }
+        })?;
+    }
+    if bytes == 0 || bytes > MAX_BYTES {
+        return Err(Error::new(input.ident.span(), "bitfields have to be 1 to 8 bytes long"));
+    }
+    Ok(bytes)
+}
+
+fn bitfield(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let bytes = parse_bytes(&input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new(input.ident.span(), "Bitfield requires named fields")),
+        },
+        _ => return Err(Error::new(input.ident.span(), "Bitfield can only be derived for structs")),
+    };
+
+    let mut used = 0u64;
+    let mut writes = Vec::new();
+    let mut reads = Vec::new();
+    for field in fields {
+        let attr = field.attrs.iter()
+            .find(|attr| attr.path().is_ident("bits"))
+            .ok_or_else(|| Error::new(field.span(), "missing #[bits(..)] attribute"))?;
+        let bits = parse_bits(&attr.parse_args()?)?;
+        if bits.offset + bits.width > bytes * 8 {
+            return Err(Error::new(attr.span(), "sub-field exceeds the bitfield"));
+        }
+        let field_mask = bits.mask() << bits.offset;
+        if used & field_mask != 0 {
+            return Err(Error::new(attr.span(), "sub-field overlaps another sub-field"));
+        }
+        used |= field_mask;
+
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let offset = bits.offset;
+        let mask = bits.mask();
+        writes.push(quote! {
+            let value = ::zigbee_rs::serde::BitfieldValue::to_raw(&self.#ident);
+            if value > #mask {
+                return Err(::zigbee_rs::serde::SerdeError::new(
+                    ::zigbee_rs::serde::SerdeErrorKind::InvalidValue { value },
+                    writer.position(),
+                ).context(stringify!(#ident)));
+            }
+            raw |= value << #offset;
+        });
+        reads.push(quote! {
+            #ident: <#ty as ::zigbee_rs::serde::BitfieldValue>::from_raw((raw >> #offset) & #mask)
+                .map_err(|kind| ::zigbee_rs::serde::SerdeError::new(kind, offset).context(stringify!(#ident)))?,
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::zigbee_rs::serde::Serde for #name #ty_generics #where_clause {
+            fn encoded_len(&self) -> usize {
+                #bytes
+            }
+
+            fn write(&self, writer: &mut ::zigbee_rs::serde::Writer) -> Result<(), ::zigbee_rs::serde::SerdeError> {
+                let mut raw: u64 = 0;
+                #(#writes)*
+                writer.write_bytes(&raw.to_le_bytes()[..#bytes])
+            }
+
+            fn read(reader: &mut ::zigbee_rs::serde::Reader) -> Result<Self, ::zigbee_rs::serde::SerdeError> {
+                let offset = reader.position();
+                let mut buffer = [0u8; 8];
+                buffer[..#bytes].copy_from_slice(reader.read_bytes(#bytes)?);
+                let raw = u64::from_le_bytes(buffer);
+                Ok(Self {
+                    #(#reads)*
+                })
+            }
+        }
+    })
+}
+
+fn bitfield_enum(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => return Err(Error::new(input.ident.span(), "BitfieldEnum can only be derived for enums")),
+    };
+
+    let mut to_raw = Vec::new();
+    let mut from_raw = Vec::new();
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) || variant.discriminant.is_none() {
+            return Err(Error::new(
+                variant.span(),
+                "BitfieldEnum requires unit variants with explicit discriminants",
+            ));
+        }
+        let ident = &variant.ident;
+        to_raw.push(quote! {
+            Self::#ident => Self::#ident as u64,
+        });
+        from_raw.push(quote! {
+            if raw == Self::#ident as u64 {
+                return Ok(Self::#ident);
+            }
+        });
+    }
+
+    let name = &input.ident;
+    Ok(quote! {
+        impl ::zigbee_rs::serde::BitfieldValue for #name {
+            fn to_raw(&self) -> u64 {
+                match self {
+                    #(#to_raw)*
+                }
+            }
+
+            fn from_raw(raw: u64) -> Result<Self, ::zigbee_rs::serde::SerdeErrorKind> {
+                #(#from_raw)*
+                Err(::zigbee_rs::serde::SerdeErrorKind::InvalidValue { value: raw })
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn bitfield_error(input: DeriveInput) -> String {
+        match bitfield(input) {
+            Ok(_) => panic!("bitfield must be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    fn bitfield_enum_error(input: DeriveInput) -> String {
+        match bitfield_enum(input) {
+            Ok(_) => panic!("bitfield enum must be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn bit_ranges() {
+        let bits = parse_bits(&parse_quote!(3)).unwrap();
+        assert_eq!((bits.offset, bits.width, bits.mask()), (3, 1, 0b1));
+        let bits = parse_bits(&parse_quote!(2..6)).unwrap();
+        assert_eq!((bits.offset, bits.width, bits.mask()), (2, 4, 0b1111));
+        let bits = parse_bits(&parse_quote!(2..=6)).unwrap();
+        assert_eq!((bits.offset, bits.width, bits.mask()), (2, 5, 0b11111));
+        let bits = parse_bits(&parse_quote!(0..64)).unwrap();
+        assert_eq!(bits.mask(), u64::MAX);
+
+        assert!(parse_bits(&parse_quote!(4..4)).is_err());
+        assert!(parse_bits(&parse_quote!(4..2)).is_err());
+        assert!(parse_bits(&parse_quote!(a..2)).is_err());
+    }
+
+    #[test]
+    fn overlapping_sub_fields_are_rejected() {
+        let error = bitfield_error(parse_quote! {
+            struct Control {
+                #[bits(0..3)]
+                mode: u8,
+                #[bits(2..5)]
+                radius: u8,
+            }
+        });
+        assert_eq!(error, "sub-field overlaps another sub-field");
+
+        let error = bitfield_error(parse_quote! {
+            struct Control {
+                #[bits(0..8)]
+                mode: u8,
+                #[bits(7)]
+                flag: bool,
+            }
+        });
+        assert_eq!(error, "sub-field overlaps another sub-field");
+    }
+
+    #[test]
+    fn sub_fields_have_to_fit_the_bitfield() {
+        let error = bitfield_error(parse_quote! {
+            struct Control {
+                #[bits(5..9)]
+                radius: u8,
+            }
+        });
+        assert_eq!(error, "sub-field exceeds the bitfield");
+
+        let error = bitfield_error(parse_quote! {
+            #[bitfield(bytes = 2)]
+            struct Control {
+                #[bits(16)]
+                flag: bool,
+            }
+        });
+        assert_eq!(error, "sub-field exceeds the bitfield");
+
+        assert!(bitfield(parse_quote! {
+            #[bitfield(bytes = 2)]
+            struct Control {
+                #[bits(8..16)]
+                radius: u8,
+            }
+        }).is_ok());
+    }
+
+    #[test]
+    fn bitfield_size_is_checked() {
+        for bytes in [0usize, 9] {
+            let error = bitfield_error(parse_quote! {
+                #[bitfield(bytes = #bytes)]
+                struct Control {
+                    #[bits(0)]
+                    flag: bool,
+                }
+            });
+            assert_eq!(error, "bitfields have to be 1 to 8 bytes long");
+        }
+
+        let error = bitfield_error(parse_quote! {
+            #[bitfield(length = 1)]
+            struct Control {
+                #[bits(0)]
+                flag: bool,
+            }
+        });
+        assert_eq!(error, "unknown bitfield attribute, expected `bytes`");
+    }
+
+    #[test]
+    fn every_field_needs_bits() {
+        let error = bitfield_error(parse_quote! {
+            struct Control {
+                #[bits(0)]
+                flag: bool,
+                radius: u8,
+            }
+        });
+        assert_eq!(error, "missing #[bits(..)] attribute");
+
+        assert_eq!(bitfield_error(parse_quote!(struct Control(u8);)), "Bitfield requires named fields");
+        assert_eq!(bitfield_error(parse_quote!(enum Control { A = 0 })), "Bitfield can only be derived for structs");
+    }
+
+    #[test]
+    fn enum_sub_fields() {
+        let output = bitfield(parse_quote! {
+            struct Control {
+                #[bits(0..2)]
+                mode: Mode,
+                #[bits(2..5)]
+                radius: u8,
+            }
+        }).unwrap().to_string();
+        // Both directions go through `BitfieldValue`, writes check the sub-field width.
+        assert!(output.contains("< Mode as :: zigbee_rs :: serde :: BitfieldValue > :: from_raw"));
+        assert!(output.contains("if value > 3u64"));
+        assert!(output.contains("if value > 7u64"));
+
+        let output = bitfield_enum(parse_quote! {
+            enum Mode {
+                NonmemberMode = 0b00,
+                MemberMode = 0b01,
+            }
+        }).unwrap().to_string();
+        assert!(output.contains("impl :: zigbee_rs :: serde :: BitfieldValue for Mode"));
+        assert!(output.contains("InvalidValue"));
+    }
+
+    #[test]
+    fn enum_variants_need_discriminants() {
+        let expected = "BitfieldEnum requires unit variants with explicit discriminants";
+        assert_eq!(bitfield_enum_error(parse_quote!(enum Mode { A = 0, B })), expected);
+        assert_eq!(bitfield_enum_error(parse_quote!(enum Mode { A(u8) = 0 })), expected);
+        assert_eq!(bitfield_enum_error(parse_quote!(struct Mode { a: u8 })), "BitfieldEnum can only be derived for enums");
+    }
+}